@@ -0,0 +1,335 @@
+use flate2::read::GzDecoder;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use crate::errors::{ErrorKind, ScanError};
+use crate::tree::Node;
+
+/// Extensions of the files looked into; anything else isn't even opened.
+const EXTENSIONS: &[&str] = &["zip", "jar", "war", "ear", "apk", "whl", "tar", "tgz"];
+
+/// Far more than any real GNU long name or pax record takes, so a broken
+/// header can't make us allocate whatever size it claims.
+const MAX_TAR_RECORD: u64 = 1024 * 1024;
+
+/// One file or directory stored in an archive.
+struct Member {
+    path: String,
+    is_dir: bool,
+    size: u64,
+}
+
+/// The members of an archive, nested by their path components.
+#[derive(Default)]
+struct Dir {
+    dirs: BTreeMap<String, Dir>,
+    files: BTreeMap<String, u64>,
+}
+
+/// Turn the zip and (gzipped) tar files below `node` (itself `depth` below the
+/// root) into directories holding what is stored in them, down to `max_depth`
+/// below the root. The archive keeps its size on disk; the directories inside
+/// total up their members' sizes. Archives that can't be read stay plain
+/// files and are reported.
+pub fn expand(node: &mut Node, depth: usize, max_depth: usize, errors: &mut Vec<ScanError>) {
+    for child in &mut node.children {
+        if child.is_dir {
+            expand(child, depth + 1, max_depth, errors);
+        } else if depth + 2 <= max_depth && is_archive(&child.path) {
+            match members(&child.path) {
+                Ok(members) => {
+                    let mut root = Dir::default();
+                    for member in members {
+                        root.insert(&member);
+                    }
+                    child.children = root.into_nodes(&child.path, max_depth - depth - 2);
+                    child.is_dir = true;
+                }
+                Err(err) => errors.push(ScanError {
+                    path: Some(child.path.clone()),
+                    kind: ErrorKind::Io,
+                    message: format!("unreadable archive: {}", err),
+                }),
+            }
+        }
+    }
+}
+
+/// The paths stored in the zip file at `path`, e.g. to tell what an Office
+/// document holds.
+#[cfg_attr(not(feature = "docs"), allow(dead_code))]
+pub fn zip_paths(path: &Path) -> io::Result<Vec<String>> {
+    let mut file = BufReader::new(File::open(path)?);
+    Ok(zip_members(&mut file)?.into_iter().map(|member| member.path).collect())
+}
+
+fn is_archive(path: &Path) -> bool {
+    let is_listed = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()));
+    // Not every .gz file is a tar one, so those go by the whole name
+    is_listed
+        || path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.to_ascii_lowercase().ends_with(".tar.gz"))
+}
+
+fn members(path: &Path) -> io::Result<Vec<Member>> {
+    let mut file = BufReader::new(File::open(path)?);
+    let mut magic = [0u8; 4];
+    file.read_exact(&mut magic)?;
+    file.seek(SeekFrom::Start(0))?;
+    if magic.starts_with(b"PK") {
+        zip_members(&mut file)
+    } else if magic.starts_with(&[0x1f, 0x8b]) {
+        // A compressed stream can't seek, so member data is read past instead
+        tar_members(&mut GzDecoder::new(file), |file, len| {
+            io::copy(&mut file.by_ref().take(len), &mut io::sink()).map(drop)
+        })
+    } else {
+        tar_members(&mut file, |file, len| {
+            file.seek(SeekFrom::Current(len as i64)).map(drop)
+        })
+    }
+}
+
+impl Dir {
+    fn insert(&mut self, member: &Member) {
+        // Tar members often start with `./`
+        let mut parts: Vec<&str> = member
+            .path
+            .split('/')
+            .filter(|part| !part.is_empty() && *part != ".")
+            .collect();
+        let Some(last) = parts.pop() else {
+            return;
+        };
+        let mut dir = self;
+        for part in parts {
+            dir = dir.dirs.entry(part.to_string()).or_default();
+        }
+        if member.is_dir {
+            dir.dirs.entry(last.to_string()).or_default();
+        } else {
+            dir.files.insert(last.to_string(), member.size);
+        }
+    }
+
+    /// The nodes for this directory's contents in name order, `levels` deep.
+    fn into_nodes(self, path: &Path, levels: usize) -> Vec<Node> {
+        let dirs = self.dirs.into_iter().map(|(name, dir)| {
+            let path = path.join(name);
+            let children = if levels > 0 {
+                dir.into_nodes(&path, levels - 1)
+            } else {
+                Vec::new()
+            };
+            virtual_node(path, true, children.iter().map(|child| child.size).sum(), children)
+        });
+        let files = self
+            .files
+            .into_iter()
+            .map(|(name, size)| virtual_node(path.join(name), false, size, Vec::new()));
+
+        let mut nodes: Vec<Node> = dirs.chain(files).collect();
+        nodes.sort_by(|a, b| a.path.cmp(&b.path));
+        nodes
+    }
+}
+
+fn virtual_node(path: PathBuf, is_dir: bool, size: u64, children: Vec<Node>) -> Node {
+    Node {
+        path,
+        is_dir,
+        size,
+        link_target: None,
+        mtime: None,
+        children,
+    }
+}
+
+/// Read the central directory at the end of a zip file (Zip64 included).
+fn zip_members(file: &mut (impl Read + Seek)) -> io::Result<Vec<Member>> {
+    const END_OF_DIRECTORY: [u8; 4] = [b'P', b'K', 5, 6];
+    const ZIP64_END_OF_DIRECTORY: [u8; 4] = [b'P', b'K', 6, 6];
+    const ZIP64_LOCATOR: [u8; 4] = [b'P', b'K', 6, 7];
+    const DIRECTORY_ENTRY: [u8; 4] = [b'P', b'K', 1, 2];
+
+    // The end record sits in the last 22 bytes, plus up to 64K of comment
+    let len = file.seek(SeekFrom::End(0))?;
+    let tail_len = len.min(22 + 65_535);
+    file.seek(SeekFrom::Start(len - tail_len))?;
+    let mut tail = vec![0; tail_len as usize];
+    file.read_exact(&mut tail)?;
+    let end = tail
+        .windows(4)
+        .rposition(|window| window == END_OF_DIRECTORY)
+        .filter(|&at| at + 22 <= tail.len())
+        .ok_or_else(|| invalid("no zip central directory"))?;
+
+    let record = &tail[end..];
+    let mut count = u64::from(le16(&record[10..]));
+    let mut offset = u64::from(le32(&record[16..]));
+    if (count == 0xFFFF || offset == 0xFFFF_FFFF)
+        && end >= 20
+        && tail[end - 20..end - 16] == ZIP64_LOCATOR
+    {
+        file.seek(SeekFrom::Start(le64(&tail[end - 12..])))?;
+        let mut record = [0u8; 56];
+        file.read_exact(&mut record)?;
+        if record[..4] != ZIP64_END_OF_DIRECTORY {
+            return Err(invalid("broken zip64 end record"));
+        }
+        count = le64(&record[32..]);
+        offset = le64(&record[48..]);
+    }
+
+    file.seek(SeekFrom::Start(offset))?;
+    let mut members = Vec::new();
+    for _ in 0..count {
+        let mut header = [0u8; 46];
+        file.read_exact(&mut header)?;
+        if header[..4] != DIRECTORY_ENTRY {
+            return Err(invalid("broken zip central directory"));
+        }
+        let mut size = u64::from(le32(&header[24..]));
+        let name_len = usize::from(le16(&header[28..]));
+        let extra_len = usize::from(le16(&header[30..]));
+        let comment_len = le16(&header[32..]);
+
+        let mut name = vec![0; name_len];
+        file.read_exact(&mut name)?;
+        let mut extra = vec![0; extra_len];
+        file.read_exact(&mut extra)?;
+        file.seek(SeekFrom::Current(i64::from(comment_len)))?;
+
+        // Sizes too big for 32 bits live in the Zip64 extra field, which
+        // starts with the uncompressed size
+        if size == 0xFFFF_FFFF {
+            let mut fields = extra.as_slice();
+            while fields.len() >= 4 {
+                let (id, field_len) = (le16(fields), usize::from(le16(&fields[2..])));
+                if id == 1 && field_len >= 8 && fields.len() >= 12 {
+                    size = le64(&fields[4..]);
+                    break;
+                }
+                fields = fields.get(4 + field_len..).unwrap_or_default();
+            }
+        }
+
+        let path = String::from_utf8_lossy(&name).replace('\\', "/");
+        members.push(Member {
+            is_dir: path.ends_with('/'),
+            path,
+            size,
+        });
+    }
+    Ok(members)
+}
+
+/// Read the headers of a tar file, with GNU long names and pax `path`
+/// records, calling `skip` to get past the `len` bytes of each member's data.
+fn tar_members<R: Read>(
+    file: &mut R,
+    mut skip: impl FnMut(&mut R, u64) -> io::Result<()>,
+) -> io::Result<Vec<Member>> {
+    let mut members = Vec::new();
+    let mut long_name: Option<String> = None;
+    loop {
+        let mut header = [0u8; 512];
+        if file.read(&mut header[..1])? == 0 {
+            break;
+        }
+        file.read_exact(&mut header[1..])?;
+        // Two zero blocks end the archive
+        if header.iter().all(|&b| b == 0) {
+            break;
+        }
+        if &header[257..262] != b"ustar" && members.is_empty() && long_name.is_none() {
+            return Err(invalid("not a tar archive"));
+        }
+
+        let size = octal(&header[124..136]).ok_or_else(|| invalid("broken tar header"))?;
+        let padded = size.div_ceil(512) * 512;
+        match header[156] {
+            b'L' | b'x' => {
+                if size > MAX_TAR_RECORD {
+                    return Err(invalid("tar long name or pax record too large"));
+                }
+                let mut data = vec![0; size as usize];
+                file.read_exact(&mut data)?;
+                skip(file, padded - size)?;
+                long_name = if header[156] == b'L' {
+                    Some(text(&data).to_string())
+                } else {
+                    pax_path(&data).or(long_name)
+                };
+                continue;
+            }
+            // Global pax headers apply to everything, but carry no member
+            b'g' => {
+                skip(file, padded)?;
+                continue;
+            }
+            _ => {}
+        }
+
+        let path = long_name.take().unwrap_or_else(|| {
+            let (name, prefix) = (text(&header[..100]), text(&header[345..500]));
+            if prefix.is_empty() {
+                name.to_string()
+            } else {
+                format!("{}/{}", prefix, name)
+            }
+        });
+        members.push(Member {
+            is_dir: header[156] == b'5' || path.ends_with('/'),
+            path,
+            size,
+        });
+        skip(file, padded)?;
+    }
+    Ok(members)
+}
+
+/// The `path` from pax extended header records (`"<len> path=<value>\n"`).
+fn pax_path(data: &[u8]) -> Option<String> {
+    String::from_utf8_lossy(data)
+        .lines()
+        .filter_map(|record| record.split_once(' ').map(|(_, rest)| rest))
+        .find_map(|record| record.strip_prefix("path=").map(str::to_string))
+}
+
+/// A NUL-terminated header field.
+fn text(field: &[u8]) -> &str {
+    let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    std::str::from_utf8(&field[..end]).unwrap_or_default()
+}
+
+fn octal(field: &[u8]) -> Option<u64> {
+    let digits = text(field).trim_matches([' ', '\0']);
+    if digits.is_empty() {
+        return Some(0);
+    }
+    u64::from_str_radix(digits, 8).ok()
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn le16(bytes: &[u8]) -> u16 {
+    u16::from_le_bytes([bytes[0], bytes[1]])
+}
+
+fn le32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn le64(bytes: &[u8]) -> u64 {
+    (u64::from(le32(&bytes[4..])) << 32) | u64::from(le32(bytes))
+}
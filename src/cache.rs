@@ -0,0 +1,228 @@
+use ignore::WalkBuilder;
+use sha2::{Digest, Sha256};
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::cli::{DirMtime, ListArgs, TimeKind};
+use crate::codeowners;
+use crate::git;
+use crate::hash;
+use crate::term;
+
+/// Entries that haven't been rewritten for this long are dropped on the next store.
+const MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Files whose edits change the listing without touching their directory's mtime.
+const RULE_FILES: &[&str] = &[".gitignore", ".ignore", ".treee-order"];
+
+/// Rendered tree output for --cache, stored under `$XDG_CACHE_HOME/treee`.
+///
+/// The key covers the arguments, the working directory, the color and width
+/// decisions, and the mtime of every directory the scan would visit (plus the
+/// sizes and times of files when the listing reads them, the CODEOWNERS rules
+/// and the git HEAD when it shows them), so adding, removing or renaming
+/// anything below the root makes for a new entry.
+pub struct Cache {
+    path: PathBuf,
+}
+
+impl Cache {
+    /// The cache entry for this invocation, or `None` when there is no cache
+    /// directory to keep it in.
+    pub fn new(args: &ListArgs, use_color: bool) -> Option<Self> {
+        let dir = dir()?;
+        let mut hasher = Sha256::default();
+        hasher.update(env!("CARGO_PKG_VERSION").as_bytes());
+        if let Ok(cwd) = env::current_dir() {
+            hasher.update(cwd.as_os_str().as_encoded_bytes());
+        }
+        hasher.update(b"\0");
+        for arg in env::args_os().skip(1) {
+            if !matches!(arg.to_str(), Some("--cache" | "--no-cache" | "--refresh")) {
+                hasher.update(arg.as_encoded_bytes());
+                hasher.update(b"\0");
+            }
+        }
+        let width = term::width(args.width).unwrap_or(0);
+        hasher.update(format!("{}\0{}\0", use_color, width).as_bytes());
+        add_tree_state(&mut hasher, args);
+        add_repo_state(&mut hasher, args);
+
+        Some(Self {
+            path: dir.join(hash::hex(&hasher.finalize())),
+        })
+    }
+
+    /// The output stored by an earlier identical run, if any.
+    pub fn load(&self) -> Option<Vec<u8>> {
+        fs::read(&self.path).ok()
+    }
+
+    /// Keep `output` for later runs, and drop stale entries while at it.
+    pub fn store(&self, output: &[u8]) -> io::Result<()> {
+        let Some(dir) = self.path.parent() else {
+            return Ok(());
+        };
+        fs::create_dir_all(dir)?;
+
+        // Written aside and renamed, so a concurrent run never reads half an entry
+        let partial = self.path.with_extension(format!("{}.tmp", std::process::id()));
+        File::create(&partial)?.write_all(output)?;
+        fs::rename(&partial, &self.path)?;
+
+        let now = SystemTime::now();
+        for entry in fs::read_dir(dir)?.flatten() {
+            let stale = entry
+                .metadata()
+                .and_then(|meta| meta.modified())
+                .is_ok_and(|modified| now.duration_since(modified).unwrap_or_default() > MAX_AGE);
+            if stale {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `$XDG_CACHE_HOME/treee`, falling back to `~/.cache/treee`
+/// (`%LOCALAPPDATA%\treee` on Windows).
+fn dir() -> Option<PathBuf> {
+    let absolute = |var: &str| {
+        env::var_os(var)
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+    };
+
+    let base = if cfg!(windows) {
+        absolute("LOCALAPPDATA")?
+    } else {
+        absolute("XDG_CACHE_HOME").or_else(|| Some(absolute("HOME")?.join(".cache")))?
+    };
+    Some(base.join("treee"))
+}
+
+/// Whether the listing depends on more than which files there are: their
+/// sizes, times, modes, owners or contents, through a column, sort key or
+/// filter.
+fn reads_files(args: &ListArgs) -> bool {
+    let scan = &args.scan;
+    args.du
+        || args.size
+        || args.highlight_size.is_some()
+        || args.collapse_files
+        || args.date
+        || args.permissions
+        || args.user
+        || args.group
+        || args.inodes
+        || args.device
+        || args.nlink
+        || args.flag_hardlinks
+        || args.media_info
+        || args.doc_info
+        || args.on_disk
+        || args.lang_bars
+        || scan.sort_mtime
+        || scan.dir_mtime == DirMtime::Newest
+        || !scan.kinds.is_empty()
+        || scan.min_resolution.is_some()
+        || scan.exif_date_between.is_some()
+        || scan.executable
+        || scan.broken_links
+        || scan.hide_special
+        || scan.scan_secrets_names
+        || scan.descend_archives
+        || args.blame_heat
+        || args.git_owner.is_some()
+}
+
+/// What the listing reads from outside the walk: the CODEOWNERS rules, and
+/// the commit checked out for the annotations that come from git history.
+fn add_repo_state(hasher: &mut Sha256, args: &ListArgs) {
+    if args.codeowners || args.scan.owned_by.is_some() {
+        let rules = codeowners::file(&args.path).and_then(|file| fs::read(file).ok());
+        hasher.update(rules.unwrap_or_default());
+        hasher.update(b"\0");
+    }
+    if args.blame_heat || args.git_owner.is_some() {
+        hasher.update(git::head(&args.path).unwrap_or_default().as_bytes());
+        hasher.update(b"\0");
+    }
+}
+
+/// Hash the mtimes of the directories below the root, walked with the same
+/// hidden, ignore and depth settings as the scan itself, and those of files
+/// too when the listing reads them.
+fn add_tree_state(hasher: &mut Sha256, args: &ListArgs) {
+    let scan = &args.scan;
+    // --unignore brings back ignored paths, so their directories count too
+    let respect_ignores = scan.unignore.is_empty() && !scan.no_git_ignore;
+    let with_files = reads_files(args);
+    let with_atime = scan.time == TimeKind::Atime;
+
+    let walker = WalkBuilder::new(&args.path)
+        .max_depth(Some(scan.depth))
+        .hidden(!scan.all && !scan.traverse_hidden)
+        .git_ignore(respect_ignores && !scan.no_repo_gitignore)
+        .git_exclude(respect_ignores && !scan.no_exclude_file)
+        .git_global(respect_ignores && !scan.no_global_gitignore)
+        .ignore(scan.unignore.is_empty())
+        .parents(scan.unignore.is_empty())
+        .follow_links(scan.follow)
+        .build();
+
+    for entry in walker {
+        let Ok(entry) = entry else {
+            hasher.update(b"?\n");
+            continue;
+        };
+        let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+        if !is_dir && !with_files {
+            continue;
+        }
+
+        add_state(hasher, entry.path(), with_atime);
+        if is_dir {
+            // Usually hidden, so looked up directly rather than left to the walk
+            for name in RULE_FILES {
+                let path = entry.path().join(name);
+                if path.exists() {
+                    add_state(hasher, &path, with_atime);
+                }
+            }
+        }
+    }
+}
+
+/// The path with its size and times; the change time covers what chmod,
+/// chown and new hard links do without touching the mtime.
+fn add_state(hasher: &mut Sha256, path: &Path, with_atime: bool) {
+    let nanos = |time: io::Result<SystemTime>| {
+        time.ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .unwrap_or_default()
+            .as_nanos()
+    };
+    hasher.update(path.as_os_str().as_encoded_bytes());
+    match fs::metadata(path) {
+        Ok(meta) => {
+            let mtime = nanos(meta.modified());
+            hasher.update(format!("\0{}\0{}", mtime, meta.len()).as_bytes());
+            if with_atime {
+                hasher.update(format!("\0{}", nanos(meta.accessed())).as_bytes());
+            }
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::MetadataExt;
+                hasher.update(format!("\0{}.{}", meta.ctime(), meta.ctime_nsec()).as_bytes());
+            }
+            #[cfg(not(unix))]
+            hasher.update(format!("\0{}", meta.permissions().readonly()).as_bytes());
+            hasher.update(b"\n");
+        }
+        Err(_) => hasher.update(b"\0?\n"),
+    }
+}
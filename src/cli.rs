@@ -0,0 +1,1099 @@
+use clap::{ArgGroup, Args, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::tree::SizeStyle;
+
+/// Printed by `--version`; `-V` prints just the crate version.
+const LONG_VERSION: &str = concat!(
+    env!("CARGO_PKG_VERSION"),
+    "\ncommit:   ",
+    env!("TREEE_GIT_HASH"),
+    "\nbuilt:    ",
+    env!("TREEE_BUILD_DATE"),
+    "\ntarget:   ",
+    env!("TREEE_TARGET"),
+    "\nfeatures: ",
+    env!("TREEE_FEATURES"),
+);
+
+#[derive(Parser)]
+#[command(name = "treee")]
+#[command(about = "A fast tree command with gitignore support and flexible filtering")]
+#[command(version, long_version = LONG_VERSION)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Log progress, filter decisions and timings to stderr (-v info, -vv debug, -vvv trace)
+    #[arg(short = 'v', long = "verbose", action = clap::ArgAction::Count, global = true)]
+    pub verbose: u8,
+
+    /// Format of the -v log output
+    #[arg(long, value_enum, default_value_t = LogFormat::Text, global = true)]
+    pub log_format: LogFormat,
+
+    /// Arguments for the default `list` command
+    #[command(flatten)]
+    pub list: ListArgs,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Display the directory tree (default)
+    List(ListArgs),
+
+    /// Display the tree with file sizes and cumulative directory sizes
+    Du(ListArgs),
+
+    /// Compare two directory trees and show added, removed and changed entries
+    Diff(DiffArgs),
+
+    /// Redraw the tree whenever the directory contents change
+    Watch(WatchArgs),
+
+    /// Generate shell completion scripts
+    Completions(CompletionsArgs),
+}
+
+/// Only one output mode (`output` group) can be selected at a time.
+#[derive(Args, Clone)]
+#[command(group(ArgGroup::new("output").multiple(false)))]
+pub struct ListArgs {
+    /// Directory to traverse
+    #[arg(default_value = ".")]
+    pub path: PathBuf,
+
+    /// Scan from the root of the git repository containing PATH
+    #[arg(long)]
+    pub repo: bool,
+
+    #[command(flatten)]
+    pub scan: ScanArgs,
+
+    #[command(flatten)]
+    pub color: ColorArgs,
+
+    /// Print full paths instead of tree format
+    #[arg(long)]
+    pub full_path: bool,
+
+    /// Print paths relative to the scanned directory one per line, without
+    /// tree glyphs or the root, for fzf and other pickers; add -f to list
+    /// files only
+    #[arg(
+        long,
+        // Every other --output mode but -0 and --shell-quote, which quote the paths
+        conflicts_with_all = [
+            "full_path", "grid", "json", "ndjson", "dot", "mermaid", "plantuml", "compat",
+            "csv", "tsv", "format", "manifest", "baseline_owners", "packages",
+            "resolve_links_display", "xml", "markdown", "rst", "org", "html", "serve_json",
+            "stats_by", "suggest_prune", "fingerprint", "total", "exec", "exec_batch",
+        ]
+    )]
+    pub flat: bool,
+
+    /// Set for --flat -f, which still walks directories for the files in them
+    #[arg(skip)]
+    pub flat_files_only: bool,
+
+    /// Print paths in columns across the terminal width, like `ls` (implies --full-path)
+    #[arg(long, group = "output")]
+    pub grid: bool,
+
+    /// List every drive letter with its label and free space, then the tree of
+    /// each (Windows only; `treee \\.\` does the same)
+    #[arg(long, conflicts_with_all = ["output", "full_path"])]
+    pub drives: bool,
+
+    /// Label the root line with the path as given instead of just its name
+    #[arg(long)]
+    pub show_root_path: bool,
+
+    /// Print canonical absolute paths in --full-path, --shell-quote and --json output
+    /// (and the root line with --show-root-path)
+    #[arg(long, conflicts_with = "relative_to")]
+    pub absolute: bool,
+
+    /// Print paths in --full-path, --shell-quote, --json and --csv output relative to DIR
+    /// [default: the scanned directory]
+    #[arg(long, value_name = "DIR", num_args = 0..=1)]
+    pub relative_to: Option<Option<PathBuf>>,
+
+    /// Print the tree as nested JSON objects, with scan errors in a trailing `errors` array
+    #[arg(short = 'J', long, group = "output", conflicts_with = "full_path")]
+    pub json: bool,
+
+    /// Write one JSON object per entry as it is found (NDJSON), without holding the
+    /// tree in memory; errors are written as `"type": "error"` lines
+    #[arg(long, group = "output", conflicts_with = "full_path")]
+    pub ndjson: bool,
+
+    /// Metadata to include in --json, --ndjson, --csv and --tsv output, as a
+    /// comma-separated list [default: target, and size with --du; size and
+    /// mtime for --csv and --tsv]
+    #[arg(long, value_enum, value_name = "FIELDS", value_delimiter = ',')]
+    pub fields: Option<Vec<Field>>,
+
+    /// Also write the tree as JSON (like --json) to FILE, alongside the normal output
+    #[arg(long, value_name = "FILE")]
+    pub json_out: Option<PathBuf>,
+
+    /// Print the tree as a Graphviz digraph, with directories as boxes and files
+    /// as leaves (render with e.g. `dot -Tsvg`)
+    #[arg(long, group = "output", conflicts_with = "full_path")]
+    pub dot: bool,
+
+    /// Print the tree as a Mermaid flowchart, for Markdown renderers that draw
+    /// `mermaid` code blocks (GitHub, GitLab)
+    #[arg(long, group = "output", conflicts_with = "full_path")]
+    pub mermaid: bool,
+
+    /// Direction of the --mermaid flowchart
+    #[arg(long, value_enum, value_name = "DIR", default_value_t = MermaidDirection::Td, requires = "mermaid")]
+    pub mermaid_direction: MermaidDirection,
+
+    /// Print the tree as a PlantUML work breakdown structure, or a mind map with
+    /// `--plantuml=mindmap`
+    #[arg(
+        long,
+        value_enum,
+        value_name = "STYLE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "wbs",
+        group = "output",
+        conflicts_with = "full_path"
+    )]
+    pub plantuml: Option<PlantUmlStyle>,
+
+    /// Mimic another tree program's output, for scripts that parse it; `windows`
+    /// is the layout of `tree /F /A`
+    #[arg(long, value_enum, value_name = "STYLE", group = "output", conflicts_with = "full_path")]
+    pub compat: Option<Compat>,
+
+    /// Print one comma-separated row per entry: path, depth, type, then the
+    /// --fields (size and modification time by default)
+    #[arg(long, group = "output", conflicts_with = "full_path")]
+    pub csv: bool,
+
+    /// Like --csv, but tab-separated; tabs, newlines and backslashes in paths
+    /// are escaped as \t, \n and \\
+    #[arg(long, group = "output", conflicts_with = "full_path")]
+    pub tsv: bool,
+
+    /// Print one line per entry laid out by TEMPLATE: %p path, %n name, %s size
+    /// in bytes, %m modification time (UTC, ISO 8601), %d depth, %% a percent
+    /// sign; \t and \n stand for tab and newline, e.g. '%s\t%p'
+    #[arg(long, value_name = "TEMPLATE", group = "output", conflicts_with = "full_path")]
+    pub format: Option<String>,
+
+    /// Print `<hash>  <path>` for every file, as `sha256sum` does (or `sha1sum`
+    /// and `md5sum` with `--manifest=sha1` or `--manifest=md5`), so the output
+    /// can be checked with `sha256sum -c`
+    #[arg(
+        long,
+        value_enum,
+        value_name = "ALGORITHM",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "sha256",
+        group = "output",
+        conflicts_with = "full_path"
+    )]
+    pub manifest: Option<HashAlgorithm>,
+
+    /// Checkpoint --manifest digests to FILE as they are taken, and skip files
+    /// it records unchanged (same size and mtime), so an interrupted scan can
+    /// be run again to pick up where it stopped
+    #[arg(long, value_name = "FILE", requires = "manifest")]
+    pub resume: Option<PathBuf>,
+
+    /// Compare owners and permissions with a baseline recorded by
+    /// `treee -J --fields owner,mode > FILE` and show the entries that drifted;
+    /// exits with 1 when any did
+    #[arg(long, value_name = "FILE", group = "output", conflicts_with = "full_path")]
+    pub baseline_owners: Option<PathBuf>,
+
+    /// Collapse the tree to the package roots in it (directories with a
+    /// Cargo.toml, package.json, go.mod or pyproject.toml), each with the
+    /// number and size of its own files
+    #[arg(long, group = "output", conflicts_with = "full_path")]
+    pub packages: bool,
+
+    /// List every symlink with the chain of links it follows, grouped by the
+    /// file or directory they finally resolve to (missing targets and loops
+    /// included)
+    #[arg(long, group = "output", conflicts_with = "full_path")]
+    pub resolve_links_display: bool,
+
+    /// Print the tree as XML, in the same format as `tree -X`
+    #[arg(short = 'X', long, group = "output", conflicts_with = "full_path")]
+    pub xml: bool,
+
+    /// Print the tree as Markdown: a nested bullet list, or the usual tree
+    /// drawing in a code fence with `--markdown=fenced`
+    #[arg(
+        long,
+        value_enum,
+        value_name = "STYLE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "list",
+        group = "output",
+        conflicts_with = "full_path"
+    )]
+    pub markdown: Option<MarkdownStyle>,
+
+    /// Print the tree as reStructuredText for Sphinx: a nested bullet list, or the
+    /// tree drawing as a literal block with `--rst=literal`
+    #[arg(
+        long,
+        value_enum,
+        value_name = "STYLE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "list",
+        group = "output",
+        conflicts_with = "full_path"
+    )]
+    pub rst: Option<RstStyle>,
+
+    /// Print the tree as an Emacs org-mode outline, directories as headings and
+    /// files as links below them, or as a nested list with `--org=list`
+    #[arg(
+        long,
+        value_enum,
+        value_name = "STYLE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "headings",
+        group = "output",
+        conflicts_with = "full_path"
+    )]
+    pub org: Option<OrgStyle>,
+
+    /// Print the tree as a standalone HTML page, with directories that expand and
+    /// collapse on click
+    #[arg(long, group = "output", conflicts_with = "full_path")]
+    pub html: bool,
+
+    /// Stay running as a file-tree backend for editors: answer newline-delimited
+    /// JSON queries on stdin ({"method": "children", "path": "src"} or
+    /// {"method": "search", "pattern": "*.rs"}) and report changes as
+    /// {"event": "changed"}
+    #[arg(long, group = "output", conflicts_with = "full_path")]
+    pub serve_json: bool,
+
+    /// HTML page to fill in instead of the built-in one; {{title}}, {{css}} and
+    /// {{tree}} are replaced with the page title, styles and the tree
+    #[arg(long, value_name = "FILE", requires = "html")]
+    pub html_template: Option<PathBuf>,
+
+    /// Title of the HTML page (default: the scanned path)
+    #[arg(long, value_name = "TITLE", requires = "html")]
+    pub html_title: Option<String>,
+
+    /// Color scheme of the HTML page
+    #[arg(long, value_enum, value_name = "THEME", default_value_t = HtmlTheme::Light, requires = "html")]
+    pub html_theme: HtmlTheme,
+
+    /// Inline the styles in FILE into the HTML page, after the default ones so
+    /// its rules take precedence; entries carry the classes dir, file, symlink
+    /// and executable
+    #[arg(long, value_name = "FILE", requires = "html")]
+    pub html_css: Option<PathBuf>,
+
+    /// Link HTML entries to BASE followed by their path relative to the root,
+    /// instead of to local filesystem paths
+    #[arg(short = 'H', long, value_name = "BASE", requires = "html")]
+    pub base_href: Option<String>,
+
+    /// Rewrite HTML links with 'REGEX=>REPLACEMENT' ($1 refers to groups; can be used multiple times)
+    #[arg(long, value_name = "RULE", requires = "html", action = clap::ArgAction::Append)]
+    pub link_rewrite: Vec<String>,
+
+    /// Show file sizes and cumulative directory sizes
+    #[arg(long)]
+    pub du: bool,
+
+    /// Show the size of each file next to its name, like `tree -s`; directories
+    /// only get one with --du
+    #[arg(short = 's', long)]
+    pub size: bool,
+
+    /// Show the type and permissions of each entry before its name, like
+    /// `drwxr-xr-x` (`tree -p`); on Windows, its R/H/S/A attributes instead
+    #[arg(short = 'p', long)]
+    pub permissions: bool,
+
+    /// Show permissions as octal modes like `0644` and `0755` instead (implies
+    /// -p)
+    #[arg(long)]
+    pub octal_perms: bool,
+
+    /// Show the user owning each entry before its name, or its uid when it has
+    /// no name (the owner's account or SID on Windows)
+    #[arg(short = 'u', long)]
+    pub user: bool,
+
+    /// Show the group owning each entry before its name, or its gid when it
+    /// has no name (the primary group's account or SID on Windows)
+    #[arg(short = 'g', long)]
+    pub group: bool,
+
+    /// Show the inode number of each entry before its name, to spot hard links
+    /// (the file index on Windows)
+    #[arg(long)]
+    pub inodes: bool,
+
+    /// Show the device number of each entry before its name, to see where
+    /// the tree crosses into another filesystem (the volume serial on Windows)
+    #[arg(long)]
+    pub device: bool,
+
+    /// Show how many hard links each entry has before its name, like `ls -l`
+    #[arg(long)]
+    pub nlink: bool,
+
+    /// Mark files with more than one hard link, e.g. `[3 links]`, to see what
+    /// a hard-linked backup really shares
+    #[arg(long)]
+    pub flag_hardlinks: bool,
+
+    /// Show when each entry was last modified before its name, like
+    /// `Oct 14 09:30` (local time; UTC on Windows)
+    #[arg(short = 'D', long)]
+    pub date: bool,
+
+    /// Format -D times like strftime(3), e.g. "%Y-%m-%d %H:%M" (implies -D)
+    #[arg(long, value_name = "FORMAT")]
+    pub timefmt: Option<String>,
+
+    /// Print sizes in powers of 1024 (4.2K, 13M), as the tree does by default;
+    /// CSV and TSV cells too
+    #[arg(long, conflicts_with_all = ["si", "bytes"])]
+    pub human_readable: bool,
+
+    /// Print sizes in powers of 1000 (4.3k, 14M) instead, CSV and TSV cells too
+    #[arg(long, conflicts_with = "bytes")]
+    pub si: bool,
+
+    /// Print exact byte counts wherever sizes are shown
+    #[arg(long)]
+    pub bytes: bool,
+
+    /// Hide files and append "(N files, SIZE)" to each directory instead
+    #[arg(long, conflicts_with = "files_only")]
+    pub collapse_files: bool,
+
+    /// Start each line with the entry's depth below the root (the root is 0)
+    #[arg(long)]
+    pub show_depth: bool,
+
+    /// Show how many directories and files each directory holds, at any depth
+    #[arg(long)]
+    pub counts: bool,
+
+    /// Answer "what is in here and how big is it": two levels with directories
+    /// first, counts and sizes, which still cover everything down to -L
+    #[arg(long, conflicts_with_all = ["output", "full_path"])]
+    pub overview: bool,
+
+    /// Highlight files larger than SIZE (e.g. 100M) and directories whose total exceeds it
+    #[arg(long, value_name = "SIZE", value_parser = crate::tree::parse_size)]
+    pub highlight_size: Option<u64>,
+
+    /// Print entry counts and file sizes grouped by KEY instead of the tree
+    #[arg(long, value_enum, value_name = "KEY", group = "output")]
+    pub stats_by: Option<StatsKey>,
+
+    /// Suggest the fewest directories to exclude to bring the scan down to TARGET:
+    /// a number of entries (or a size with --prune-by size), or a percentage of
+    /// the current total such as '10%'
+    #[arg(long, value_name = "TARGET", group = "output")]
+    pub suggest_prune: Option<String>,
+
+    /// What --suggest-prune reduces
+    #[arg(long, value_enum, value_name = "METRIC", default_value_t = PruneMetric::Entries, requires = "suggest_prune")]
+    pub prune_by: PruneMetric,
+
+    /// Print a SHA-256 over the names, types and sizes of everything that passes
+    /// the filters, to check in one comparison that a layout hasn't changed
+    #[arg(long, group = "output")]
+    pub fingerprint: bool,
+
+    /// Also hash file contents into the --fingerprint
+    #[arg(long, requires = "fingerprint")]
+    pub fingerprint_content: bool,
+
+    /// Only print the total size of everything matching the filters (like `du -sh`)
+    #[arg(long, group = "output")]
+    pub total: bool,
+
+    /// Run a command for each matched entry instead of printing the tree,
+    /// e.g. 'wc -l {}' ({} path, {/} name, {//} parent, {.} and {/.} without extension)
+    #[arg(long, value_name = "CMD", group = "output")]
+    pub exec: Option<String>,
+
+    /// Run a command once with all matched entries as arguments
+    #[arg(long, value_name = "CMD", group = "output")]
+    pub exec_batch: Option<String>,
+
+    /// Number of --exec commands, or files hashed for --manifest, to run in
+    /// parallel [default: number of CPUs]
+    #[arg(short = 'j', long, value_name = "N")]
+    pub jobs: Option<usize>,
+
+    /// Print paths separated by NUL bytes, for `xargs -0` (implies --full-path
+    /// and no colors)
+    #[arg(short = '0', long, group = "output")]
+    pub print0: bool,
+
+    /// Print one shell-quoted path per line (implies --full-path and no colors)
+    #[arg(
+        long,
+        value_enum,
+        value_name = "STYLE",
+        num_args = 0..=1,
+        default_missing_value = "posix",
+        group = "output"
+    )]
+    pub shell_quote: Option<QuoteStyle>,
+
+    /// Show page counts of PDFs and sheet or slide counts of Excel and
+    /// PowerPoint files after their names (requires the `docs` feature)
+    #[arg(long)]
+    pub doc_info: bool,
+
+    /// Make names clickable links to their files in terminals that support
+    /// OSC 8 hyperlinks (only when printing to a terminal)
+    #[arg(long)]
+    pub hyperlinks: bool,
+
+    /// Color tracked files by how recently a commit touched them, from red
+    /// (this week) through yellow, green and cyan to gray (over a year), using
+    /// the git history rather than mtimes that checkouts reset
+    #[arg(long)]
+    pub blame_heat: bool,
+
+    /// Show who wrote most of each file and directory, by commits touching it
+    /// or, with --git-owner=lines, by lines today (slower: blames every file)
+    #[arg(
+        long,
+        value_enum,
+        value_name = "BY",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "commits"
+    )]
+    pub git_owner: Option<OwnerBy>,
+
+    /// Follow each directory with a bar of the languages in it by bytes, like
+    /// GitHub's, and the top three with their shares
+    #[arg(long)]
+    pub lang_bars: bool,
+
+    /// Show who owns each file and directory by the repository's CODEOWNERS
+    /// file (`.github/`, the root, `docs/` or `.gitlab/`)
+    #[arg(long)]
+    pub codeowners: bool,
+
+    /// Show the dimensions of images and videos and the length of audio and
+    /// video files after their names (requires the `media` feature)
+    #[arg(long)]
+    pub media_info: bool,
+
+    /// Show how much of the disk files on btrfs and ZFS really take, and their
+    /// compression ratio (requires the `fs-compression` feature; reading btrfs
+    /// extents needs root)
+    #[arg(long)]
+    pub on_disk: bool,
+
+    /// Output width in columns, 0 for unlimited [default: terminal width or $COLUMNS]
+    #[arg(long, value_name = "N")]
+    pub width: Option<usize>,
+
+    /// Characters to draw the tree branches with
+    #[arg(long, value_enum, default_value_t = Charset::Utf8)]
+    pub charset: Charset,
+
+    /// Connector before every entry but the last in a directory [default: from --charset]
+    #[arg(long, value_name = "STR")]
+    pub glyph_branch: Option<String>,
+
+    /// Connector before the last entry in a directory [default: from --charset]
+    #[arg(long, value_name = "STR")]
+    pub glyph_last: Option<String>,
+
+    /// Indent below a directory that has more entries to come [default: from --charset]
+    #[arg(long, value_name = "STR")]
+    pub glyph_vertical: Option<String>,
+
+    /// Indent below a directory's last entry [default: spaces]
+    #[arg(long, value_name = "STR")]
+    pub glyph_indent: Option<String>,
+
+    /// Reuse the tree printed by an identical earlier run while nothing below PATH
+    /// has been added, removed or renamed; kept in $XDG_CACHE_HOME/treee
+    #[arg(long, overrides_with = "no_cache")]
+    pub cache: bool,
+
+    /// Scan and render afresh, ignoring any earlier --cache
+    #[arg(long, overrides_with = "cache")]
+    pub no_cache: bool,
+
+    /// Scan afresh and replace the cached tree (implies --cache)
+    #[arg(long)]
+    pub refresh: bool,
+
+    /// Write the output to FILE instead of stdout, without colors unless they
+    /// are forced with --color=always; FILE ending in .gz or .zst is compressed
+    #[arg(short = 'o', long = "output", value_name = "FILE")]
+    pub output_file: Option<PathBuf>,
+
+    /// Compress the output, whatever -o FILE is called
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    pub compress: Option<Compression>,
+
+    /// Don't print warnings such as unreadable directories (still reflected in the exit status)
+    #[arg(short = 'q', long)]
+    pub quiet: bool,
+}
+
+/// Options that decide which entries are collected from a directory.
+#[derive(Args, Clone)]
+pub struct ScanArgs {
+    /// Maximum depth to traverse
+    #[arg(short = 'L', long, default_value = "10")]
+    pub depth: usize,
+
+    /// Show only DEPTH levels below directories named like GLOB, while the rest
+    /// of the tree uses -L, e.g. 'node_modules=1' (can be used multiple times)
+    #[arg(long, value_name = "GLOB=DEPTH", action = clap::ArgAction::Append)]
+    pub depth_for: Vec<String>,
+
+    /// Show hidden files
+    #[arg(short = 'a', long)]
+    pub all: bool,
+
+    /// Descend into hidden directories without showing hidden entries,
+    /// except directories that lead to a shown entry
+    #[arg(long, conflicts_with = "all")]
+    pub traverse_hidden: bool,
+
+    /// Show directories only; with --du their sizes still include the files inside
+    #[arg(short = 'd', long, visible_alias = "dirs-only", conflicts_with = "files_only")]
+    pub directories_only: bool,
+
+    /// Include paths matching these glob patterns (can be used multiple times)
+    #[arg(short = 'I', long = "include", action = clap::ArgAction::Append)]
+    pub include_patterns: Vec<String>,
+
+    /// Exclude paths matching these glob patterns (can be used multiple times)
+    #[arg(short = 'E', long = "exclude", action = clap::ArgAction::Append)]
+    pub exclude_patterns: Vec<String>,
+
+    /// File name patterns to match (glob patterns, can be used multiple times);
+    /// append `@N` to only match files up to depth N, e.g. '*.md@2'
+    #[arg(short = 'P', long = "pattern", action = clap::ArgAction::Append)]
+    pub file_patterns: Vec<String>,
+
+    /// Hide files matching GLOB that are deeper than DEPTH, e.g. '*.md=2'
+    /// (can be used multiple times)
+    #[arg(long = "pattern-depth", value_name = "GLOB=DEPTH", action = clap::ArgAction::Append)]
+    pub pattern_depths: Vec<String>,
+
+    /// File name regular expressions to match (can be used multiple times)
+    #[arg(long = "regex", action = clap::ArgAction::Append)]
+    pub regex_patterns: Vec<String>,
+
+    /// Only show files whose content looks like KIND, whatever their extension
+    /// (can be used multiple times)
+    #[arg(long = "kind", value_enum, value_name = "KIND", action = clap::ArgAction::Append)]
+    pub kinds: Vec<FileKind>,
+
+    /// Only show images and videos at least WIDTHxHEIGHT pixels, e.g. 1920x1080
+    /// (requires the `media` feature)
+    #[arg(long, value_name = "WxH", value_parser = crate::media::parse_resolution)]
+    pub min_resolution: Option<crate::media::Resolution>,
+
+    /// Only show photos taken between FROM..TO according to their EXIF data,
+    /// e.g. 2023-01..2023-06, 2023-03-14.. or ..2022 (requires the `media`
+    /// feature)
+    #[arg(long, value_name = "FROM..TO", value_parser = crate::media::parse_date_range)]
+    pub exif_date_between: Option<crate::media::DateRange>,
+
+    /// Only show symlinks whose target doesn't exist, and the directories containing them
+    #[arg(long)]
+    pub broken_links: bool,
+
+    /// Only show files named like credentials (id_rsa, .env, *.pem, *.p12, ...)
+    /// that users other than their owner can read, hidden and ignored ones
+    /// included, and the directories containing them (Unix only)
+    #[arg(long)]
+    pub scan_secrets_names: bool,
+
+    /// Only show files the CODEOWNERS file gives to OWNER (a team, user or
+    /// email, `@` optional), and the directories containing them
+    #[arg(long, value_name = "OWNER")]
+    pub owned_by: Option<String>,
+
+    /// Hide FIFOs, sockets and device nodes
+    #[arg(long, conflicts_with = "include_special")]
+    pub hide_special: bool,
+
+    /// Count the sizes of FIFOs, sockets and device nodes, which are left out of
+    /// size totals by default
+    #[arg(long)]
+    pub include_special: bool,
+
+    /// Only show executable files (execute permission on Unix, PATHEXT on Windows)
+    #[arg(long)]
+    pub executable: bool,
+
+    /// Stop walking as soon as N files (directories with -d) pass the filters,
+    /// and show only those; the walk goes in name order so the result is stable
+    #[arg(long, value_name = "N")]
+    pub first: Option<std::num::NonZeroUsize>,
+
+    /// Regex engine used by --regex
+    #[arg(long, value_enum, default_value_t = RegexEngine::Default)]
+    pub regex_engine: RegexEngine,
+
+    /// Disable gitignore rules
+    #[arg(long = "no-git-ignore")]
+    pub no_git_ignore: bool,
+
+    /// Ignore the global excludes file (`core.excludesFile`, usually ~/.config/git/ignore)
+    #[arg(long = "no-global-gitignore")]
+    pub no_global_gitignore: bool,
+
+    /// Ignore the repository's own `.gitignore` files
+    #[arg(long = "no-repo-gitignore")]
+    pub no_repo_gitignore: bool,
+
+    /// Ignore the repository's `.git/info/exclude` file
+    #[arg(long = "no-exclude-file")]
+    pub no_exclude_file: bool,
+
+    /// Show paths matching PATTERN even though ignore files hide them, e.g.
+    /// 'dist/**'; patterns work like .gitignore lines at the root (can be used
+    /// multiple times)
+    #[arg(long, value_name = "PATTERN", action = clap::ArgAction::Append)]
+    pub unignore: Vec<String>,
+
+    /// Show only files (opposite of --directories-only)
+    #[arg(short = 'f', long)]
+    pub files_only: bool,
+
+    /// Show entries matching these glob patterns but leave them out of directory
+    /// size totals (can be used multiple times)
+    #[arg(long = "du-exclude", value_name = "PATTERN", action = clap::ArgAction::Append)]
+    pub du_exclude_patterns: Vec<String>,
+
+    /// Mark tmpfs and overlay mounts (from /proc/self/mountinfo) and leave them
+    /// out of --du totals, so scanning / in a container counts the writable
+    /// layer; --proc-mounts=annotate still counts them (Linux only)
+    #[arg(
+        long,
+        value_enum,
+        value_name = "MODE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "exclude"
+    )]
+    pub proc_mounts: Option<MountMode>,
+
+    /// Walk at most N entries a second (`500/s`), or read at most SIZE of file
+    /// contents a second when hashing (`20M/s`); give both to limit both
+    #[arg(
+        long,
+        value_name = "RATE",
+        value_parser = crate::throttle::parse_rate,
+        action = clap::ArgAction::Append
+    )]
+    pub throttle: Vec<crate::throttle::Rate>,
+
+    /// Run at the lowest CPU and I/O priority, so a scan of busy storage
+    /// yields to everything else (like `nice -n 19 ionice -c 2 -n 7`)
+    #[arg(long)]
+    pub nice: bool,
+
+    /// List what zip and tar files (gzipped ones too) hold as if they were
+    /// directories, down to the depth -L allows
+    #[arg(long)]
+    pub descend_archives: bool,
+
+    /// Follow symbolic links to directories, like `tree -l`
+    #[arg(short = 'l', long)]
+    pub follow: bool,
+
+    /// With --follow, stop at entries reached through more than N symlinks and
+    /// report them, guarding against runaway link farms
+    #[arg(long, value_name = "N", requires = "follow")]
+    pub max_link_depth: Option<usize>,
+
+    /// Count the size of symlink targets instead of the links themselves (like `du -L`)
+    #[arg(long)]
+    pub dereference_size: bool,
+
+    /// Ignore `.treee-order` files, which list a directory's children in display order
+    #[arg(long)]
+    pub no_order_files: bool,
+
+    /// List directories before files (`.treee-order` files still come first)
+    #[arg(long)]
+    pub dirsfirst: bool,
+
+    /// Sort by modification time, newest first
+    #[arg(short = 't', long)]
+    pub sort_mtime: bool,
+
+    /// Which timestamp -D shows, -t sorts by and --json, --csv and --format
+    /// report as mtime
+    #[arg(long, value_enum, value_name = "WHICH", default_value_t = TimeKind::Mtime)]
+    pub time: TimeKind,
+
+    /// Which modification time directories have, for sorting and for mtime in
+    /// --json and --csv output
+    #[arg(long, value_enum, value_name = "WHICH", default_value_t = DirMtime::Own)]
+    pub dir_mtime: DirMtime,
+
+    /// Answer within milliseconds, for shell prompts and editor widgets: walk
+    /// in parallel, leave entries unsorted, skip colors and `.treee-order`
+    /// files, and stop at 3 levels and 1000 entries
+    #[arg(long, conflicts_with_all = ["first", "unignore", "sort_mtime"])]
+    pub fast: bool,
+}
+
+#[derive(Args, Clone)]
+pub struct ColorArgs {
+    /// When to use colors
+    #[arg(long, value_enum, value_name = "WHEN", default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+
+    /// Always use colors, even when output is piped (same as --color always)
+    #[arg(long, conflicts_with_all = ["color", "no_color"])]
+    pub force_color: bool,
+
+    /// Don't use colors (same as --color never)
+    #[arg(long, conflicts_with = "color")]
+    pub no_color: bool,
+}
+
+impl ListArgs {
+    /// Whether the tree itself is printed, rather than one of the other outputs
+    /// such as --json or --total.
+    pub fn prints_tree(&self) -> bool {
+        !(self.ndjson
+            || self.serve_json
+            || self.json
+            || self.json_out.is_some()
+            || self.total
+            || self.fingerprint
+            || self.stats_by.is_some()
+            || self.suggest_prune.is_some()
+            || self.exec.is_some()
+            || self.exec_batch.is_some()
+            || self.dot
+            || self.mermaid
+            || self.plantuml.is_some()
+            || self.compat.is_some()
+            || self.csv
+            || self.tsv
+            || self.format.is_some()
+            || self.rst.is_some()
+            || self.org.is_some()
+            || self.markdown.is_some()
+            || self.xml
+            || self.manifest.is_some()
+            || self.baseline_owners.is_some()
+            || self.packages
+            || self.resolve_links_display
+            || self.html)
+    }
+
+    /// How sizes should be written, when asked for.
+    pub fn size_style(&self) -> Option<SizeStyle> {
+        if self.bytes {
+            Some(SizeStyle::Bytes)
+        } else if self.si {
+            Some(SizeStyle::Si)
+        } else if self.human_readable {
+            Some(SizeStyle::Binary)
+        } else {
+            None
+        }
+    }
+
+    /// How many --exec commands or --manifest hashes run at once.
+    pub fn jobs(&self) -> usize {
+        self.jobs.unwrap_or_else(|| {
+            std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1)
+        })
+    }
+
+    /// The metadata fields machine output should carry.
+    pub fn fields(&self) -> Vec<Field> {
+        match &self.fields {
+            Some(fields) => fields.clone(),
+            None if self.csv || self.tsv => vec![Field::Size, Field::Mtime],
+            None if self.du => vec![Field::Size, Field::Target],
+            None => vec![Field::Target],
+        }
+    }
+}
+
+impl ColorArgs {
+    pub fn choice(&self) -> ColorChoice {
+        if self.force_color {
+            ColorChoice::Always
+        } else if self.no_color {
+            ColorChoice::Never
+        } else {
+            self.color
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct DiffArgs {
+    /// The directory to compare from
+    pub old: PathBuf,
+
+    /// The directory to compare to
+    pub new: PathBuf,
+
+    #[command(flatten)]
+    pub scan: ScanArgs,
+
+    #[command(flatten)]
+    pub color: ColorArgs,
+
+    /// Don't print warnings such as unreadable directories (still reflected in the exit status)
+    #[arg(short = 'q', long)]
+    pub quiet: bool,
+}
+
+#[derive(Args)]
+pub struct WatchArgs {
+    #[command(flatten)]
+    pub list: ListArgs,
+
+    /// Seconds between checks for changes
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value = "1.0",
+        value_parser = crate::watch::parse_interval
+    )]
+    pub interval: Duration,
+}
+
+#[derive(Args)]
+pub struct CompletionsArgs {
+    /// Shell to generate completions for
+    pub shell: clap_complete::Shell,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// Color when writing to a terminal or a CI log that renders colors,
+    /// honoring NO_COLOR, CLICOLOR and CLICOLOR_FORCE
+    Auto,
+    /// Always color, even when piped (e.g. into `less -R`)
+    Always,
+    /// Never color
+    Never,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DirMtime {
+    /// The directory's own, which changes when entries are added or removed
+    Own,
+    /// The newest of everything below it, to find recently worked-on projects
+    Newest,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TimeKind {
+    /// When the contents were last modified
+    Mtime,
+    /// When the contents or metadata (permissions, owner, links) last changed
+    Ctime,
+    /// When the entry was last read, as far as the mount records it
+    Atime,
+    /// When the entry was created, where the filesystem keeps it
+    #[value(alias = "birth")]
+    Created,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Charset {
+    /// Box-drawing characters: ├── └── │
+    #[value(alias = "utf-8")]
+    Utf8,
+    /// Plain ASCII, for logs and terminals that mangle Unicode: |-- `-- |
+    Ascii,
+    /// Box-drawing with a rounded last branch: ├── ╰── │
+    Rounded,
+    /// Heavy box-drawing lines: ┣━━ ┗━━ ┃
+    Heavy,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum QuoteStyle {
+    /// POSIX shells (sh, bash, zsh): single quotes with '\'' escapes
+    Posix,
+    /// PowerShell: single quotes with doubled quotes
+    Powershell,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Field {
+    /// Size in bytes; cumulative for directories in --json
+    Size,
+    /// Modification time (UTC, ISO 8601)
+    Mtime,
+    /// Permission bits in octal (Unix only)
+    #[value(alias = "perms")]
+    Mode,
+    /// Name of the owning user (Unix only)
+    Owner,
+    /// Where a symlink points, and whether that exists
+    Target,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum MermaidDirection {
+    /// Top down
+    Td,
+    /// Left to right
+    Lr,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PlantUmlStyle {
+    /// @startwbs: a work breakdown structure
+    Wbs,
+    /// @startmindmap: a mind map
+    Mindmap,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OwnerBy {
+    /// Most commits touching the entry
+    Commits,
+    /// Most lines in `git blame`
+    Lines,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum MountMode {
+    /// Mark the mounts and leave them out of directory sizes
+    Exclude,
+    /// Only mark them
+    Annotate,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum HashAlgorithm {
+    Sha256,
+    /// Only for tools that expect it; not collision resistant
+    Sha1,
+    /// Only for tools that expect it; not collision resistant
+    Md5,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum HtmlTheme {
+    /// Dark text on white
+    Light,
+    /// Light text on a dark background
+    Dark,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Compat {
+    /// `tree /F /A` from Windows: volume header, files first, `+---` and `\---`
+    Windows,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum StatsKey {
+    /// Levels below the root: wide and shallow or narrow and deep
+    Depth,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PruneMetric {
+    /// Number of files and directories
+    Entries,
+    /// Total size of the files
+    Size,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum RstStyle {
+    /// Nested bullet list, with names as inline literals
+    List,
+    /// The tree drawing as a `::` literal block
+    Literal,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OrgStyle {
+    /// One heading level per directory level
+    Headings,
+    /// Indented plain list
+    List,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum MarkdownStyle {
+    /// Indented bullet list, with names as inline code
+    List,
+    /// The tree drawing inside a ```text block
+    Fenced,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// Human readable lines
+    Text,
+    /// One JSON object per event
+    Json,
+}
+
+/// File categories recognized from magic numbers by --kind.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum FileKind {
+    /// PNG, JPEG, GIF, WebP, BMP, TIFF, ICO, HEIC/AVIF
+    Image,
+    /// zip, gzip, bzip2, xz, zstd, 7z, RAR, tar
+    Archive,
+    /// MP3, FLAC, Ogg, WAV, M4A
+    Audio,
+    /// MP4/MOV, Matroska/WebM, AVI, FLV, MPEG
+    Video,
+    /// ELF, PE, Mach-O binaries and scripts with a shebang
+    Executable,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum RegexEngine {
+    /// Rust's regex crate: linear time, no look-around or backreferences
+    Default,
+    /// PCRE2: supports look-around and backreferences (requires the `pcre2` feature)
+    Pcre2,
+}
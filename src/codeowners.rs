@@ -0,0 +1,163 @@
+use anyhow::{Context, Result, bail};
+use ignore::Match;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::tree::{self, Node};
+
+/// Where GitHub (the first three) and GitLab look for the file, relative to
+/// the repository root.
+const LOCATIONS: &[&str] = &[
+    ".github/CODEOWNERS",
+    "CODEOWNERS",
+    "docs/CODEOWNERS",
+    ".gitlab/CODEOWNERS",
+];
+
+/// The rules of a repository's CODEOWNERS file.
+pub struct CodeOwners {
+    /// The repository root, which the patterns are relative to
+    root: PathBuf,
+    matcher: Gitignore,
+    /// The owners of each pattern, with the line of its last rule; the last
+    /// rule matching an entry decides who owns it
+    rules: HashMap<String, (usize, Vec<String>)>,
+}
+
+/// The CODEOWNERS file `CodeOwners::find` reads for `path`, if there is one.
+pub fn file(path: &Path) -> Option<PathBuf> {
+    file_in(&repo_root(path).ok()?)
+}
+
+fn repo_root(path: &Path) -> Result<PathBuf> {
+    Ok(match tree::find_repo_root(path) {
+        Some(root) => root,
+        None => std::path::absolute(path)?,
+    })
+}
+
+fn file_in(root: &Path) -> Option<PathBuf> {
+    LOCATIONS.iter().map(|l| root.join(l)).find(|file| file.is_file())
+}
+
+impl CodeOwners {
+    /// Read the CODEOWNERS file of the repository holding `path` (or of `path`
+    /// itself outside git).
+    pub fn find(path: &Path) -> Result<Self> {
+        let root = repo_root(path)?;
+        let Some(file) = file_in(&root) else {
+            let tried = LOCATIONS.join(", ");
+            bail!("No CODEOWNERS file in '{}' (looked for {})", root.display(), tried);
+        };
+        let text = fs::read_to_string(&file)
+            .with_context(|| format!("Failed to read '{}'", file.display()))?;
+        Self::parse(root, &text).with_context(|| format!("Failed to parse '{}'", file.display()))
+    }
+
+    fn parse(root: PathBuf, text: &str) -> Result<Self> {
+        let mut builder = GitignoreBuilder::new(&root);
+        let mut rules = HashMap::new();
+        // GitLab sections name the owners of the rules below them that name none
+        let mut defaults: Vec<String> = Vec::new();
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            // `[Docs] @docs`, `^[Docs]` when approval is optional, `[Docs][2]`
+            // when it takes two
+            if let Some(section) = line.strip_prefix('^').unwrap_or(line).strip_prefix('[') {
+                let rest = section.split_once(']').map_or("", |(_, rest)| rest);
+                let rest = rest
+                    .strip_prefix('[')
+                    .and_then(|approvals| approvals.split_once(']'))
+                    .map_or(rest, |(_, rest)| rest);
+                defaults = owners(rest);
+                continue;
+            }
+
+            let (pattern, rest) = split_pattern(line);
+            // Negation means nothing in CODEOWNERS
+            if pattern.starts_with('!') {
+                continue;
+            }
+            let mut owners = owners(rest);
+            if owners.is_empty() {
+                owners = defaults.clone();
+            }
+            builder.add_line(None, pattern)?;
+            rules.insert(pattern.to_string(), (number, owners));
+        }
+
+        Ok(Self {
+            root,
+            matcher: builder.build()?,
+            rules,
+        })
+    }
+
+    /// Who owns `path`, by the last rule matching it or a directory above it.
+    /// Empty when no rule does, or the rule names no one.
+    pub fn owners_of(&self, path: &Path, is_dir: bool) -> &[String] {
+        let Ok(path) = std::path::absolute(path) else {
+            return &[];
+        };
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return &[];
+        };
+        relative
+            .ancestors()
+            .filter(|p| !p.as_os_str().is_empty())
+            .enumerate()
+            .filter_map(|(i, p)| match self.matcher.matched(p, is_dir || i > 0) {
+                Match::Ignore(glob) => self.rules.get(glob.original()),
+                _ => None,
+            })
+            .max_by_key(|(number, _)| *number)
+            .map_or(&[], |(_, owners)| owners.as_slice())
+    }
+
+    /// Whether `owner` (with or without its `@`, in any case) owns `path`.
+    pub fn is_owned_by(&self, path: &Path, is_dir: bool, owner: &str) -> bool {
+        let owner = owner.trim_start_matches('@');
+        self.owners_of(path, is_dir)
+            .iter()
+            .any(|o| o.trim_start_matches('@').eq_ignore_ascii_case(owner))
+    }
+
+    /// The owners of every entry of the tree, root included, separated by
+    /// spaces; unowned entries are left out.
+    pub fn annotate(&self, root: &Node) -> HashMap<PathBuf, String> {
+        std::iter::once(root)
+            .chain(root.descendants())
+            .filter_map(|node| {
+                let owners = self.owners_of(&node.path, node.is_dir);
+                (!owners.is_empty()).then(|| (node.path.clone(), owners.join(" ")))
+            })
+            .collect()
+    }
+}
+
+/// Split a rule into its pattern, in which `\ ` escapes a space, and the rest.
+fn split_pattern(line: &str) -> (&str, &str) {
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '\\' => escaped = !escaped,
+            c if c.is_whitespace() && !escaped => return (&line[..i], &line[i..]),
+            _ => escaped = false,
+        }
+    }
+    (line, "")
+}
+
+/// The owners listed in `text`, up to a trailing comment.
+fn owners(text: &str) -> Vec<String> {
+    text.split_whitespace()
+        .take_while(|owner| !owner.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
@@ -0,0 +1,45 @@
+use std::env;
+
+use crate::cli::ColorChoice;
+
+/// CI services whose log viewers render ANSI escapes even though stdout is a pipe.
+const ANSI_CI_VARS: [&str; 8] = [
+    "GITHUB_ACTIONS",
+    "GITLAB_CI",
+    "BUILDKITE",
+    "CIRCLECI",
+    "TRAVIS",
+    "TF_BUILD",
+    "DRONE",
+    "TEAMCITY_VERSION",
+];
+
+/// Decide whether output should be colored and configure `colored` to match,
+/// since it otherwise strips colors on its own whenever stdout is not a terminal.
+pub fn init(choice: ColorChoice) -> bool {
+    let use_color = match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => detect(),
+    };
+
+    colored::control::set_override(use_color);
+    use_color
+}
+
+fn detect() -> bool {
+    if env::var("CLICOLOR_FORCE").is_ok_and(|v| !v.is_empty() && v != "0") {
+        return true;
+    }
+    if env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+        return false;
+    }
+    if env::var("CLICOLOR").is_ok_and(|v| v == "0") {
+        return false;
+    }
+    if env::var("TERM").is_ok_and(|v| v == "dumb") {
+        return false;
+    }
+
+    atty::is(atty::Stream::Stdout) || ANSI_CI_VARS.iter().any(|var| env::var_os(var).is_some())
+}
@@ -0,0 +1,84 @@
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::tree::Node;
+
+/// Print the tree the way `tree /F /A` does on Windows: the volume header,
+/// then each directory's files before its subdirectories, with `+---` and
+/// `\---` connectors and a separator line after every block of files.
+pub fn print_windows(root: &Node, path: &Path) -> io::Result<()> {
+    let mut out = BufWriter::new(io::stdout().lock());
+    // Outside Windows there is no volume to describe, but the header keeps its
+    // two lines so scripts skipping them still line up
+    let (label, serial) = volume(path).unwrap_or_default();
+    if label.is_empty() {
+        writeln!(out, "Folder PATH listing")?;
+    } else {
+        writeln!(out, "Folder PATH listing for volume {}", label)?;
+    }
+    writeln!(out, "Volume serial number is {:04X}-{:04X}", serial >> 16, serial & 0xFFFF)?;
+    writeln!(out, "{}", path.display())?;
+    write_dir(&mut out, root, "")?;
+    out.flush()
+}
+
+fn write_dir(out: &mut impl Write, node: &Node, prefix: &str) -> io::Result<()> {
+    let (dirs, files): (Vec<&Node>, Vec<&Node>) = node.children.iter().partition(|c| c.is_dir);
+
+    // tree.com keeps the trailing spaces of these lines, and so do we
+    let bar = if dirs.is_empty() { "    " } else { "|   " };
+    for file in &files {
+        writeln!(out, "{}{}{}", prefix, bar, file.name())?;
+    }
+    if !files.is_empty() {
+        writeln!(out, "{}{}", prefix, bar)?;
+    }
+
+    for (i, dir) in dirs.iter().enumerate() {
+        let is_last = i == dirs.len() - 1;
+        let (connector, indent) = if is_last { ("\\---", "    ") } else { ("+---", "|   ") };
+        writeln!(out, "{}{}{}", prefix, connector, dir.name())?;
+        write_dir(out, dir, &format!("{}{}", prefix, indent))?;
+    }
+    Ok(())
+}
+
+/// The label and serial number of the volume holding `path`.
+#[cfg(windows)]
+fn volume(path: &Path) -> Option<(String, u32)> {
+    use std::path::{Component, PathBuf};
+    use std::ptr::null_mut;
+    use windows_sys::Win32::Storage::FileSystem::GetVolumeInformationW;
+
+    let absolute = std::path::absolute(path).ok()?;
+    let Some(Component::Prefix(prefix)) = absolute.components().next() else {
+        return None;
+    };
+    let root: PathBuf = [prefix.as_os_str(), "\\".as_ref()].iter().collect();
+    let wide: Vec<u16> = root.to_string_lossy().encode_utf16().chain(Some(0)).collect();
+
+    let mut label = [0u16; 261];
+    let mut serial = 0u32;
+    let ok = unsafe {
+        GetVolumeInformationW(
+            wide.as_ptr(),
+            label.as_mut_ptr(),
+            label.len() as u32,
+            &mut serial,
+            null_mut(),
+            null_mut(),
+            null_mut(),
+            0,
+        )
+    } != 0;
+    if !ok {
+        return None;
+    }
+    let len = label.iter().position(|&c| c == 0).unwrap_or(label.len());
+    Some((String::from_utf16_lossy(&label[..len]), serial))
+}
+
+#[cfg(not(windows))]
+fn volume(_path: &Path) -> Option<(String, u32)> {
+    None
+}
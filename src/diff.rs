@@ -0,0 +1,220 @@
+use anyhow::Result;
+use colored::*;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::cli::DiffArgs;
+use crate::printer::TreePrinter;
+use crate::tree::{self, Node, format_size};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Change {
+    Added,
+    Removed,
+    Changed,
+    Unchanged,
+}
+
+/// Whether an entry is a directory and its size, as seen on one side of the diff.
+type Side = Option<(bool, u64)>;
+
+struct DiffNode {
+    name: String,
+    is_dir: bool,
+    change: Change,
+    old_size: u64,
+    new_size: u64,
+    children: Vec<DiffNode>,
+}
+
+#[derive(Default)]
+struct Summary {
+    added: usize,
+    removed: usize,
+    changed: usize,
+}
+
+/// The outcome of a diff, mapped to `diff(1)`-style exit codes by the caller.
+pub enum Outcome {
+    Same,
+    Different,
+    /// Some entries could not be read, so the comparison may be incomplete
+    Incomplete,
+}
+
+/// Compare the two trees named in `args` and print the entries that differ.
+pub fn run(args: &DiffArgs, use_color: bool) -> Result<Outcome> {
+    for path in [&args.old, &args.new] {
+        if !path.exists() {
+            anyhow::bail!("Path '{}' does not exist", path.display());
+        }
+    }
+
+    let _span = tracing::info_span!("diff").entered();
+
+    let old_scan = tree::scan(&args.old, &args.scan, true)?;
+    let new_scan = tree::scan(&args.new, &args.scan, true)?;
+    let had_errors = tree::report_errors(&old_scan.errors, args.quiet)
+        | tree::report_errors(&new_scan.errors, args.quiet);
+    let (old, new) = (old_scan.root, new_scan.root);
+
+    let mut entries: BTreeMap<PathBuf, (Side, Side)> = BTreeMap::new();
+    for (path, side) in flatten(&old) {
+        entries.entry(path).or_default().0 = Some(side);
+    }
+    for (path, side) in flatten(&new) {
+        entries.entry(path).or_default().1 = Some(side);
+    }
+
+    // Group entries by their parent directory, relative to both roots
+    let mut dir_contents: HashMap<PathBuf, Vec<(PathBuf, Side, Side)>> = HashMap::new();
+    for (path, (old, new)) in entries {
+        let parent = path.parent().unwrap_or(Path::new("")).to_path_buf();
+        dir_contents.entry(parent).or_default().push((path, old, new));
+    }
+
+    let children = build_children(Path::new(""), &mut dir_contents);
+
+    let printer = TreePrinter::new(use_color, false, false);
+    let root = format!("{} -> {}", args.old.display(), args.new.display());
+    let mut out = BufWriter::new(io::stdout().lock());
+    writeln!(out, "{}", if use_color { root.bold().to_string() } else { root })?;
+
+    let mut summary = Summary::default();
+    write_children(&children, &printer, "", use_color, &mut summary, &mut out)?;
+
+    writeln!(
+        out,
+        "\n{} added, {} removed, {} changed",
+        summary.added, summary.removed, summary.changed
+    )?;
+    out.flush()?;
+
+    Ok(if had_errors {
+        Outcome::Incomplete
+    } else if summary.added + summary.removed + summary.changed > 0 {
+        Outcome::Different
+    } else {
+        Outcome::Same
+    })
+}
+
+/// List every entry below `root` by its path relative to `root`.
+fn flatten(root: &Node) -> Vec<(PathBuf, (bool, u64))> {
+    fn walk(node: &Node, root: &Path, out: &mut Vec<(PathBuf, (bool, u64))>) {
+        for child in &node.children {
+            if let Ok(relative) = child.path.strip_prefix(root) {
+                out.push((relative.to_path_buf(), (child.is_dir, child.size)));
+            }
+            walk(child, root, out);
+        }
+    }
+
+    let mut out = Vec::new();
+    walk(root, &root.path, &mut out);
+    out
+}
+
+fn build_children(
+    parent: &Path,
+    dir_contents: &mut HashMap<PathBuf, Vec<(PathBuf, Side, Side)>>,
+) -> Vec<DiffNode> {
+    let Some(contents) = dir_contents.remove(parent) else {
+        return Vec::new();
+    };
+
+    contents
+        .into_iter()
+        .filter_map(|(path, old, new)| {
+            let change = match (old, new) {
+                (None, Some(_)) => Change::Added,
+                (Some(_), None) => Change::Removed,
+                (Some((old_dir, old_size)), Some((new_dir, new_size))) => {
+                    if old_dir != new_dir || (!new_dir && old_size != new_size) {
+                        Change::Changed
+                    } else {
+                        Change::Unchanged
+                    }
+                }
+                (None, None) => unreachable!("every entry exists on at least one side"),
+            };
+
+            let children = build_children(&path, dir_contents);
+
+            // Only keep unchanged entries when they lead to a change
+            if change == Change::Unchanged && children.is_empty() {
+                return None;
+            }
+
+            let (is_dir, new_size) = new.or(old).unwrap_or_default();
+            Some(DiffNode {
+                name: path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                is_dir,
+                change,
+                old_size: old.map(|(_, size)| size).unwrap_or(0),
+                new_size,
+                children,
+            })
+        })
+        .collect()
+}
+
+fn write_children(
+    children: &[DiffNode],
+    printer: &TreePrinter,
+    prefix: &str,
+    use_color: bool,
+    summary: &mut Summary,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    for (i, child) in children.iter().enumerate() {
+        let is_last = i == children.len() - 1;
+
+        let text = match child.change {
+            Change::Added => {
+                summary.added += 1;
+                format!("+ {}", child.name)
+            }
+            Change::Removed => {
+                summary.removed += 1;
+                format!("- {}", child.name)
+            }
+            Change::Changed => {
+                summary.changed += 1;
+                if child.is_dir {
+                    format!("~ {}", child.name)
+                } else {
+                    format!(
+                        "~ {} ({} -> {})",
+                        child.name,
+                        format_size(child.old_size),
+                        format_size(child.new_size)
+                    )
+                }
+            }
+            Change::Unchanged => child.name.clone(),
+        };
+
+        let text = if use_color {
+            match child.change {
+                Change::Added => text.green().to_string(),
+                Change::Removed => text.red().to_string(),
+                Change::Changed => text.yellow().to_string(),
+                Change::Unchanged if child.is_dir => text.blue().bold().to_string(),
+                Change::Unchanged => text,
+            }
+        } else {
+            text
+        };
+
+        printer.write_line(out, prefix, is_last, &text)?;
+
+        let child_prefix = printer.get_child_prefix(prefix, is_last);
+        write_children(&child.children, printer, &child_prefix, use_color, summary, out)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,109 @@
+use std::fmt;
+use std::path::Path;
+
+/// What `--doc-info` shows for a PDF or Office file.
+#[cfg_attr(not(feature = "docs"), allow(dead_code))]
+pub enum DocInfo {
+    Pages(u64),
+    Sheets(usize),
+    Slides(usize),
+}
+
+impl fmt::Display for DocInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (count, unit) = match *self {
+            Self::Pages(count) => (count, "page"),
+            Self::Sheets(count) => (count as u64, "sheet"),
+            Self::Slides(count) => (count as u64, "slide"),
+        };
+        write!(f, "{} {}{}", count, unit, if count == 1 { "" } else { "s" })
+    }
+}
+
+/// Fail unless treee was built with the `docs` feature.
+pub fn ensure_enabled() -> anyhow::Result<()> {
+    if cfg!(feature = "docs") {
+        Ok(())
+    } else {
+        anyhow::bail!("Document support is not enabled; rebuild treee with `--features docs`")
+    }
+}
+
+/// Count the pages of a PDF, or the sheets or slides of an Excel or PowerPoint
+/// file, or `None` for anything else.
+#[cfg(feature = "docs")]
+pub fn probe(path: &Path) -> Option<DocInfo> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    match extension.as_str() {
+        "pdf" => formats::pdf_pages(path).ok().flatten().map(DocInfo::Pages),
+        "xlsx" | "xlsm" => formats::count_parts(path, "xl/worksheets/sheet").map(DocInfo::Sheets),
+        "pptx" | "pptm" => formats::count_parts(path, "ppt/slides/slide").map(DocInfo::Slides),
+        _ => None,
+    }
+}
+
+#[cfg(not(feature = "docs"))]
+pub fn probe(_path: &Path) -> Option<DocInfo> {
+    None
+}
+
+#[cfg(feature = "docs")]
+mod formats {
+    use std::fs::File;
+    use std::io::{self, BufReader, Read};
+    use std::path::Path;
+
+    use crate::archive;
+
+    /// Bytes carried over between chunks, more than any match spans.
+    const OVERLAP: usize = 32;
+
+    /// Count the page objects (`/Type /Page`, not `/Pages`). Pages stored in
+    /// compressed object streams can't be seen, so those PDFs give `None`.
+    pub fn pdf_pages(path: &Path) -> io::Result<Option<u64>> {
+        let mut file = BufReader::new(File::open(path)?);
+        let mut window = Vec::new();
+        let mut chunk = vec![0u8; 1 << 20];
+        let mut pages = 0;
+        loop {
+            let read = file.read(&mut chunk)?;
+            window.extend_from_slice(&chunk[..read]);
+            // Matches starting in the overlap wait for the next chunk, unless
+            // this is the end
+            let limit = if read == 0 {
+                window.len()
+            } else {
+                window.len().saturating_sub(OVERLAP)
+            };
+            pages += (0..limit).filter(|&at| is_page_object(&window[at..])).count() as u64;
+            window.drain(..limit);
+            if read == 0 {
+                break;
+            }
+        }
+        Ok((pages > 0).then_some(pages))
+    }
+
+    fn is_page_object(bytes: &[u8]) -> bool {
+        let Some(rest) = bytes.strip_prefix(b"/Type") else {
+            return false;
+        };
+        let start = rest.iter().take_while(|b| b.is_ascii_whitespace()).count();
+        let Some(rest) = rest[start..].strip_prefix(b"/Page") else {
+            return false;
+        };
+        // `/Pages` is a node of the page tree, not a page
+        rest.first().is_none_or(|b| !b.is_ascii_alphanumeric())
+    }
+
+    /// How many `<prefix><N>.xml` parts the Office file holds.
+    pub fn count_parts(path: &Path, prefix: &str) -> Option<usize> {
+        let paths = archive::zip_paths(path).ok()?;
+        let count = paths
+            .iter()
+            .filter_map(|part| part.strip_prefix(prefix)?.strip_suffix(".xml"))
+            .filter(|number| !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit()))
+            .count();
+        Some(count)
+    }
+}
@@ -0,0 +1,48 @@
+use std::io::{self, BufWriter, Write};
+
+use crate::tree::{Node, format_size};
+
+/// Print the tree as a Graphviz `digraph`, one node per entry and an edge from
+/// every directory to each of its children.
+pub fn print(root: &Node, with_size: bool) -> io::Result<()> {
+    let mut out = BufWriter::new(io::stdout().lock());
+    writeln!(out, "digraph tree {{")?;
+    writeln!(out, "    rankdir=LR;")?;
+    writeln!(out, "    node [fontname=\"monospace\"];")?;
+
+    let mut next_id = 0;
+    write_node(&mut out, root, with_size, &mut next_id)?;
+
+    writeln!(out, "}}")?;
+    out.flush()
+}
+
+/// Write `node` and its subtree, returning the id given to `node`. Ids are
+/// numbered in tree order, so the output is stable across runs.
+fn write_node(
+    out: &mut impl Write,
+    node: &Node,
+    with_size: bool,
+    next_id: &mut usize,
+) -> io::Result<usize> {
+    let id = *next_id;
+    *next_id += 1;
+
+    let mut label = escape(&node.name());
+    if with_size {
+        label = format!("{}\\n{}", label, format_size(node.size));
+    }
+    let shape = if node.is_dir { "box" } else { "plaintext" };
+    writeln!(out, "    n{} [label=\"{}\", shape={}];", id, label, shape)?;
+
+    for child in &node.children {
+        let child_id = write_node(out, child, with_size, next_id)?;
+        writeln!(out, "    n{} -> n{};", id, child_id)?;
+    }
+    Ok(id)
+}
+
+/// Escape text for a double-quoted DOT string.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
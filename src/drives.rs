@@ -0,0 +1,135 @@
+use anyhow::Result;
+use colored::*;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+use crate::cli::ListArgs;
+use crate::language;
+use crate::paths::PathStyle;
+use crate::tree::{self, format_size};
+
+/// A mounted drive letter and what Windows reports about its volume.
+#[cfg_attr(not(windows), allow(dead_code))]
+pub struct Drive {
+    pub root: PathBuf,
+    pub label: String,
+    /// Free and total bytes, or `None` when the drive isn't ready (e.g. an
+    /// empty card reader)
+    pub space: Option<(u64, u64)>,
+}
+
+/// Print every drive as a top-level entry followed by its tree.
+/// Returns whether any non-fatal errors occurred along the way.
+pub fn run(args: &ListArgs, use_color: bool) -> Result<bool> {
+    let with_sizes = args.du
+        || args.size
+        || args.highlight_size.is_some()
+        || args.collapse_files
+        || args.lang_bars;
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    let mut had_errors = false;
+    for drive in list()? {
+        let name = drive.root.to_string_lossy();
+        let name = if use_color {
+            name.blue().bold().to_string()
+        } else {
+            name.into_owned()
+        };
+        let label = if drive.label.is_empty() {
+            String::new()
+        } else {
+            format!(" [{}]", drive.label)
+        };
+
+        // Drives that aren't ready can't be read, so only list them
+        let Some((free, total)) = drive.space else {
+            writeln!(out, "{}{} (not ready)", name, label)?;
+            continue;
+        };
+        writeln!(
+            out,
+            "{}{} ({} free of {})",
+            name,
+            label,
+            format_size(free),
+            format_size(total)
+        )?;
+
+        let scan = tree::scan(&drive.root, &args.scan, with_sizes)?;
+        let paths = PathStyle::new(
+            args.absolute,
+            args.relative_to.as_ref().map(|dir| dir.as_deref()),
+            &drive.root,
+        );
+        let lang_bars = args.lang_bars.then(|| language::tally(&scan.root));
+        crate::tree_printer(args, &scan.root, paths, use_color)?
+            .with_lang_bars(lang_bars)
+            .write_children(&scan.root, "", 1, &mut out)?;
+        // Keep each drive's warnings next to its listing
+        out.flush()?;
+        had_errors |= tree::report_errors(&scan.errors, args.quiet);
+    }
+
+    Ok(had_errors)
+}
+
+#[cfg(windows)]
+fn list() -> Result<Vec<Drive>> {
+    use std::ptr::null_mut;
+    use windows_sys::Win32::Storage::FileSystem::{
+        GetDiskFreeSpaceExW, GetLogicalDrives, GetVolumeInformationW,
+    };
+
+    // One bit per drive letter, A: being bit 0
+    let mask = unsafe { GetLogicalDrives() };
+    if mask == 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+
+    let drives = (0..26u8)
+        .filter(|letter| mask & (1 << letter) != 0)
+        .map(|letter| {
+            let root = format!("{}:\\", (b'A' + letter) as char);
+            let wide: Vec<u16> = root.encode_utf16().chain(Some(0)).collect();
+
+            let mut label = [0u16; 261];
+            let has_label = unsafe {
+                GetVolumeInformationW(
+                    wide.as_ptr(),
+                    label.as_mut_ptr(),
+                    label.len() as u32,
+                    null_mut(),
+                    null_mut(),
+                    null_mut(),
+                    null_mut(),
+                    0,
+                )
+            } != 0;
+            let label = if has_label {
+                let len = label.iter().position(|&c| c == 0).unwrap_or(label.len());
+                String::from_utf16_lossy(&label[..len])
+            } else {
+                String::new()
+            };
+
+            let (mut free, mut total) = (0u64, 0u64);
+            let has_space =
+                unsafe { GetDiskFreeSpaceExW(wide.as_ptr(), &mut free, &mut total, null_mut()) }
+                    != 0;
+
+            Drive {
+                root: PathBuf::from(root),
+                label,
+                space: has_space.then_some((free, total)),
+            }
+        })
+        .collect();
+
+    Ok(drives)
+}
+
+#[cfg(not(windows))]
+fn list() -> Result<Vec<Drive>> {
+    anyhow::bail!("--drives is only supported on Windows")
+}
@@ -0,0 +1,109 @@
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// A non-fatal problem hit while scanning, in a shape that can be printed as a
+/// warning or serialized into machine-readable output.
+#[derive(Serialize)]
+pub struct ScanError {
+    #[serde(serialize_with = "lossy_path")]
+    pub path: Option<PathBuf>,
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    PermissionDenied,
+    NotFound,
+    Loop,
+    Io,
+    Other,
+}
+
+impl From<ignore::Error> for ScanError {
+    fn from(err: ignore::Error) -> Self {
+        let path = error_path(&err);
+
+        let (kind, message) = if let ignore::Error::Loop { ancestor, .. } = innermost(&err) {
+            (
+                ErrorKind::Loop,
+                format!("filesystem loop back to {}", ancestor.display()),
+            )
+        } else if let Some(io_err) = err.io_error() {
+            let kind = match io_err.kind() {
+                io::ErrorKind::PermissionDenied => ErrorKind::PermissionDenied,
+                io::ErrorKind::NotFound => ErrorKind::NotFound,
+                _ => ErrorKind::Io,
+            };
+            (kind, os_message(io_err))
+        } else {
+            (ErrorKind::Other, innermost(&err).to_string())
+        };
+
+        Self {
+            path,
+            kind,
+            message,
+        }
+    }
+}
+
+impl fmt::Display for ScanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.path {
+            Some(path) => write!(f, "{}: {}", path.display(), self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Paths that aren't valid UTF-8 are written lossily, like entry paths, since
+/// serde would otherwise fail halfway through the output.
+fn lossy_path<S: Serializer>(path: &Option<PathBuf>, serializer: S) -> Result<S::Ok, S::Error> {
+    match path {
+        Some(path) => serializer.serialize_some(&path.to_string_lossy()),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// The plain OS message for `err`. walkdir wraps OS errors in its own error that
+/// repeats the path, which we already report separately.
+fn os_message(err: &io::Error) -> String {
+    let os_error = err.raw_os_error().or_else(|| {
+        err.get_ref()
+            .and_then(|inner| inner.source())
+            .and_then(|source| source.downcast_ref::<io::Error>())
+            .and_then(|source| source.raw_os_error())
+    });
+
+    match os_error {
+        Some(code) => io::Error::from_raw_os_error(code).to_string(),
+        None => err.to_string(),
+    }
+}
+
+/// Peel off the context wrappers `ignore` adds around the actual error.
+fn innermost(err: &ignore::Error) -> &ignore::Error {
+    match err {
+        ignore::Error::WithPath { err, .. }
+        | ignore::Error::WithDepth { err, .. }
+        | ignore::Error::WithLineNumber { err, .. } => innermost(err),
+        ignore::Error::Partial(errs) if errs.len() == 1 => innermost(&errs[0]),
+        _ => err,
+    }
+}
+
+fn error_path(err: &ignore::Error) -> Option<PathBuf> {
+    match err {
+        ignore::Error::WithPath { path, .. } => Some(path.clone()),
+        ignore::Error::WithDepth { err, .. } | ignore::Error::WithLineNumber { err, .. } => {
+            error_path(err)
+        }
+        ignore::Error::Partial(errs) if errs.len() == 1 => error_path(&errs[0]),
+        ignore::Error::Loop { child, .. } => Some(child.clone()),
+        _ => None,
+    }
+}
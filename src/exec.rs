@@ -0,0 +1,210 @@
+use anyhow::{Context, Result};
+use std::ffi::{OsStr, OsString};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+
+/// Keep batched command lines well below the smallest common ARG_MAX.
+const MAX_BATCH_BYTES: usize = 128 * 1024;
+
+/// A command line split into arguments, with `{}`-style placeholders that are
+/// filled in per entry. Arguments are passed to the program directly rather
+/// than through a shell, so paths never need quoting.
+pub struct CommandTemplate {
+    args: Vec<String>,
+}
+
+impl CommandTemplate {
+    pub fn parse(command: &str) -> Result<Self> {
+        let mut args = shell_words::split(command)
+            .with_context(|| format!("Invalid command '{}'", command))?;
+        if args.is_empty() {
+            anyhow::bail!("The command to execute must not be empty");
+        }
+
+        // Like fd, append the path when no placeholder is given
+        if !args[1..].iter().any(|arg| has_placeholder(arg)) {
+            args.push("{}".to_string());
+        }
+
+        Ok(Self { args })
+    }
+
+    fn command_for(&self, path: &Path) -> Command {
+        let mut command = Command::new(&self.args[0]);
+        command.args(self.args[1..].iter().map(|arg| substitute(arg, path)));
+        command
+    }
+
+    /// Build one command for as many paths as fit in a command line. Arguments
+    /// with placeholders are repeated once per path.
+    fn batch_commands(&self, paths: &[PathBuf]) -> Vec<Command> {
+        let mut commands = Vec::new();
+        let mut start = 0;
+
+        while start < paths.len() {
+            let mut end = start;
+            let mut bytes = 0;
+            while end < paths.len() && (end == start || bytes < MAX_BATCH_BYTES) {
+                bytes += paths[end].as_os_str().len() + 1;
+                end += 1;
+            }
+
+            let mut command = Command::new(&self.args[0]);
+            for arg in &self.args[1..] {
+                if has_placeholder(arg) {
+                    command.args(paths[start..end].iter().map(|path| substitute(arg, path)));
+                } else {
+                    command.arg(arg);
+                }
+            }
+            commands.push(command);
+            start = end;
+        }
+
+        commands
+    }
+}
+
+/// Run `template` once per path on up to `jobs` threads.
+/// Returns whether every command succeeded.
+pub fn run_each(template: &CommandTemplate, paths: &[PathBuf], jobs: usize) -> bool {
+    let jobs = jobs.clamp(1, paths.len().max(1));
+    let next = AtomicUsize::new(0);
+    let ok = AtomicBool::new(true);
+    // Serializes output so lines of concurrent commands don't interleave
+    let output_lock = Mutex::new(());
+
+    thread::scope(|scope| {
+        for _ in 0..jobs {
+            scope.spawn(|| {
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(path) = paths.get(index) else {
+                        break;
+                    };
+
+                    let command = template.command_for(path);
+                    let success = if jobs == 1 {
+                        run_inherited(command)
+                    } else {
+                        run_captured(command, &output_lock)
+                    };
+                    if !success {
+                        ok.store(false, Ordering::Relaxed);
+                    }
+                }
+            });
+        }
+    });
+
+    ok.into_inner()
+}
+
+/// Run `template` with all paths at once, split over several invocations when
+/// the command line would get too long. Returns whether every command succeeded.
+pub fn run_batch(template: &CommandTemplate, paths: &[PathBuf]) -> bool {
+    if paths.is_empty() {
+        return true;
+    }
+
+    // Keep going after a failed batch so every entry gets processed
+    let mut ok = true;
+    for command in template.batch_commands(paths) {
+        ok &= run_inherited(command);
+    }
+    ok
+}
+
+fn run_inherited(mut command: Command) -> bool {
+    match command.status() {
+        Ok(status) => status.success(),
+        Err(err) => {
+            report_spawn_error(&command, &err);
+            false
+        }
+    }
+}
+
+fn run_captured(mut command: Command, output_lock: &Mutex<()>) -> bool {
+    let output = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output();
+
+    let _guard = output_lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    match output {
+        Ok(output) => {
+            let _ = io::stdout().write_all(&output.stdout);
+            let _ = io::stderr().write_all(&output.stderr);
+            output.status.success()
+        }
+        Err(err) => {
+            report_spawn_error(&command, &err);
+            false
+        }
+    }
+}
+
+fn report_spawn_error(command: &Command, err: &io::Error) {
+    eprintln!(
+        "treee: failed to run '{}': {}",
+        command.get_program().to_string_lossy(),
+        err
+    );
+}
+
+/// Placeholders, longest first so `{//}` isn't read as `{/}` followed by `/}`.
+const PLACEHOLDERS: [&str; 5] = ["{//}", "{/.}", "{/}", "{.}", "{}"];
+
+fn has_placeholder(arg: &str) -> bool {
+    PLACEHOLDERS.iter().any(|placeholder| arg.contains(placeholder))
+}
+
+/// Replace the fd-style placeholders in `arg`:
+/// `{}` path, `{/}` file name, `{//}` parent directory,
+/// `{.}` path without extension, `{/.}` file name without extension.
+/// Paths go in as they are, even when they aren't valid UTF-8.
+fn substitute(arg: &str, path: &Path) -> OsString {
+    let mut out = OsString::new();
+    let mut rest = arg;
+
+    // Single pass, so braces inside substituted paths are left alone
+    while let Some(start) = rest.find('{') {
+        out.push(&rest[..start]);
+        rest = &rest[start..];
+
+        match PLACEHOLDERS.iter().find(|p| rest.starts_with(**p)) {
+            Some(placeholder) => {
+                out.push(expand(placeholder, path));
+                rest = &rest[placeholder.len()..];
+            }
+            None => {
+                out.push("{");
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push(rest);
+    out
+}
+
+fn expand(placeholder: &str, path: &Path) -> OsString {
+    let name = || path.file_name().unwrap_or(path.as_os_str()).to_os_string();
+
+    match placeholder {
+        "{/}" => name(),
+        "{//}" => path
+            .parent()
+            .map(|p| p.as_os_str().to_os_string())
+            .filter(|p| !p.is_empty())
+            .unwrap_or_else(|| ".".into()),
+        "{.}" => path.with_extension("").into_os_string(),
+        "{/.}" => path.file_stem().map(OsStr::to_os_string).unwrap_or_else(name),
+        _ => path.as_os_str().to_os_string(),
+    }
+}
@@ -0,0 +1,305 @@
+use anyhow::Result;
+use glob::Pattern;
+use std::ops::Range;
+use std::path::Path;
+use tracing::debug;
+
+use crate::cli::RegexEngine;
+
+pub enum NameRegex {
+    Default(regex::Regex),
+    #[cfg(feature = "pcre2")]
+    Pcre2(pcre2::bytes::Regex),
+}
+
+impl NameRegex {
+    pub fn new(pattern: &str, engine: RegexEngine) -> Result<Self> {
+        match engine {
+            RegexEngine::Default => Ok(Self::Default(regex::Regex::new(pattern)?)),
+            #[cfg(feature = "pcre2")]
+            RegexEngine::Pcre2 => {
+                let regex = pcre2::bytes::RegexBuilder::new()
+                    .utf(true)
+                    .jit_if_available(true)
+                    .build(pattern)?;
+                Ok(Self::Pcre2(regex))
+            }
+            #[cfg(not(feature = "pcre2"))]
+            RegexEngine::Pcre2 => {
+                anyhow::bail!("PCRE2 support is not enabled; rebuild treee with `--features pcre2`")
+            }
+        }
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        match self {
+            Self::Default(regex) => regex.is_match(text),
+            // Errors here are match-time limits (e.g. backtracking), treat as no match
+            #[cfg(feature = "pcre2")]
+            Self::Pcre2(regex) => regex.is_match(text.as_bytes()).unwrap_or(false),
+        }
+    }
+
+    /// Byte ranges of every match in `text`.
+    pub fn find_all(&self, text: &str) -> Vec<Range<usize>> {
+        match self {
+            Self::Default(regex) => regex.find_iter(text).map(|m| m.range()).collect(),
+            #[cfg(feature = "pcre2")]
+            Self::Pcre2(regex) => regex
+                .find_iter(text.as_bytes())
+                .map_while(Result::ok)
+                .map(|m| m.start()..m.end())
+                .collect(),
+        }
+    }
+}
+
+/// Finds the parts of file names that made `-P` and `--regex` select them.
+pub struct NameHighlighter {
+    /// `-P` globs translated to regexes that capture their literal runs, since
+    /// the wildcards aren't what the user asked for
+    globs: Vec<regex::Regex>,
+    regexes: Vec<NameRegex>,
+}
+
+impl NameHighlighter {
+    /// Returns `None` when there are no patterns, so nothing to highlight.
+    pub fn new(
+        file_patterns: &[String],
+        regex_patterns: &[String],
+        regex_engine: RegexEngine,
+    ) -> Result<Option<Self>> {
+        if file_patterns.is_empty() && regex_patterns.is_empty() {
+            return Ok(None);
+        }
+
+        // Patterns that can't be translated just go without highlighting
+        let globs = file_patterns
+            .iter()
+            .filter_map(|p| glob_regex(split_depth(p, '@').0))
+            .collect();
+        let regexes = regex_patterns
+            .iter()
+            .map(|p| NameRegex::new(p, regex_engine))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Some(Self { globs, regexes }))
+    }
+
+    /// Sorted, non-overlapping byte ranges of `name` to highlight.
+    pub fn ranges(&self, name: &str) -> Vec<Range<usize>> {
+        let mut ranges: Vec<Range<usize>> = self
+            .globs
+            .iter()
+            .filter_map(|glob| glob.captures(name))
+            .flat_map(|captures| {
+                captures
+                    .iter()
+                    .skip(1)
+                    .flatten()
+                    .map(|m| m.range())
+                    .collect::<Vec<_>>()
+            })
+            .chain(self.regexes.iter().flat_map(|regex| regex.find_all(name)))
+            .filter(|range| !range.is_empty())
+            .collect();
+        ranges.sort_by_key(|range| range.start);
+
+        let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+        merged
+    }
+}
+
+/// Translate a glob into an anchored regex with a capture group around each
+/// run of literal characters, e.g. `*.md` to `^.*(\.md)$`.
+fn glob_regex(glob: &str) -> Option<regex::Regex> {
+    fn flush(re: &mut String, literal: &mut String) {
+        if !literal.is_empty() {
+            re.push('(');
+            re.push_str(&regex::escape(literal));
+            re.push(')');
+            literal.clear();
+        }
+    }
+
+    let mut re = String::from("^");
+    let mut literal = String::new();
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => {
+                flush(&mut re, &mut literal);
+                re.push_str(".*");
+            }
+            '?' => {
+                flush(&mut re, &mut literal);
+                re.push('.');
+            }
+            '[' => {
+                flush(&mut re, &mut literal);
+                re.push('[');
+                if chars.next_if_eq(&'!').is_some() {
+                    re.push('^');
+                }
+                // A `]` right after the opening bracket is part of the class
+                let mut first = true;
+                loop {
+                    match chars.next()? {
+                        ']' if !first => break,
+                        c @ ('\\' | '[' | ']' | '^' | '&' | '~') => {
+                            re.push('\\');
+                            re.push(c);
+                        }
+                        c => re.push(c),
+                    }
+                    first = false;
+                }
+                re.push(']');
+            }
+            c => literal.push(c),
+        }
+    }
+    flush(&mut re, &mut literal);
+    re.push('$');
+    regex::Regex::new(&re).ok()
+}
+
+pub struct PathFilter {
+    include_patterns: Vec<Pattern>,
+    exclude_patterns: Vec<Pattern>,
+    /// `-P` patterns, optionally only matching up to a depth (`*.md@2`)
+    file_patterns: Vec<(Pattern, Option<usize>)>,
+    regex_patterns: Vec<NameRegex>,
+    /// Files matching these names are hidden below the given depth
+    depth_limits: Vec<(Pattern, usize)>,
+}
+
+impl PathFilter {
+    pub fn new(
+        include_patterns: &[String],
+        exclude_patterns: &[String],
+        file_patterns: &[String],
+        regex_patterns: &[String],
+        regex_engine: RegexEngine,
+        pattern_depths: &[String],
+    ) -> Result<Self> {
+        let include_patterns = include_patterns
+            .iter()
+            .map(|p| Pattern::new(p))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let exclude_patterns = exclude_patterns
+            .iter()
+            .map(|p| Pattern::new(p))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let file_patterns = file_patterns
+            .iter()
+            .map(|p| {
+                let (pattern, depth) = split_depth(p, '@');
+                Ok((Pattern::new(pattern)?, depth))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let regex_patterns = regex_patterns
+            .iter()
+            .map(|p| NameRegex::new(p, regex_engine))
+            .collect::<Result<Vec<_>>>()?;
+
+        let depth_limits = parse_depth_rules(pattern_depths)?;
+
+        Ok(Self {
+            include_patterns,
+            exclude_patterns,
+            file_patterns,
+            regex_patterns,
+            depth_limits,
+        })
+    }
+
+    /// Whether the entry at `path`, `depth` levels below the root, is shown.
+    pub fn should_include(&self, path: &Path, depth: usize) -> bool {
+        let path_str = path.to_string_lossy();
+        let file_name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+
+        // Check exclude patterns first
+        for pattern in &self.exclude_patterns {
+            if pattern.matches(&path_str) || pattern.matches(&file_name) {
+                debug!(path = %path_str, pattern = %pattern, "skipped: matches --exclude");
+                return false;
+            }
+        }
+
+        // For directories, always include them unless explicitly excluded
+        // This allows traversal to find matching files in subdirectories
+        if path.is_dir() {
+            return true;
+        }
+
+        // For files, check include patterns
+        if !self.include_patterns.is_empty() {
+            let included = self.include_patterns.iter().any(|pattern| {
+                pattern.matches(&path_str) || pattern.matches(&file_name)
+            });
+            if !included {
+                debug!(path = %path_str, "skipped: matches no --include");
+                return false;
+            }
+        }
+
+        // Check file patterns for files (only if there are file patterns)
+        if !self.file_patterns.is_empty()
+            && !self.file_patterns.iter().any(|(pattern, max_depth)| {
+                pattern.matches(&file_name) && max_depth.is_none_or(|max| depth <= max)
+            })
+        {
+            debug!(path = %path_str, "skipped: matches no --pattern");
+            return false;
+        }
+
+        for (pattern, max_depth) in &self.depth_limits {
+            if depth > *max_depth && pattern.matches(&file_name) {
+                debug!(path = %path_str, pattern = %pattern, "skipped: below --pattern-depth");
+                return false;
+            }
+        }
+
+        // Check regex patterns for files (only if there are regex patterns)
+        if !self.regex_patterns.is_empty()
+            && !self.regex_patterns.iter().any(|regex| regex.is_match(&file_name))
+        {
+            debug!(path = %path_str, "skipped: matches no --regex");
+            return false;
+        }
+
+        true
+    }
+}
+
+/// Parse `GLOB=DEPTH` rules as taken by --pattern-depth and --depth-for.
+pub fn parse_depth_rules(rules: &[String]) -> Result<Vec<(Pattern, usize)>> {
+    rules
+        .iter()
+        .map(|rule| match split_depth(rule, '=') {
+            (pattern, Some(depth)) => Ok((Pattern::new(pattern)?, depth)),
+            (_, None) => anyhow::bail!("Invalid depth rule '{}', expected 'GLOB=DEPTH'", rule),
+        })
+        .collect()
+}
+
+/// Split a trailing depth such as the `2` in `*.md@2` or `*.md=2` off a pattern.
+fn split_depth(pattern: &str, separator: char) -> (&str, Option<usize>) {
+    match pattern.rsplit_once(separator) {
+        Some((glob, depth)) if !glob.is_empty() => match depth.parse() {
+            Ok(depth) => (glob, Some(depth)),
+            Err(_) => (pattern, None),
+        },
+        _ => (pattern, None),
+    }
+}
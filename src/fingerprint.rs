@@ -0,0 +1,48 @@
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+
+use crate::hash;
+use crate::tree::Node;
+
+/// Hash the layout below `root` into one hex string.
+///
+/// Every entry contributes a line of its path relative to the root (with `/`
+/// separators), its type, and for files the size and optionally the content
+/// hash. Children are taken in name order rather than display order, and the
+/// root's own name is left out, so the same layout checked out anywhere gives
+/// the same fingerprint.
+pub fn compute(root: &Node, with_content: bool) -> Result<String> {
+    let mut hasher = Sha256::default();
+    add_children(&mut hasher, root, "", with_content)?;
+    Ok(hash::hex(&hasher.finalize()))
+}
+
+fn add_children(hasher: &mut Sha256, node: &Node, prefix: &str, with_content: bool) -> Result<()> {
+    let mut children: Vec<(String, &Node)> = node
+        .children
+        .iter()
+        .map(|child| (child.name(), child))
+        .collect();
+    children.sort_by(|a, b| a.0.cmp(&b.0));
+
+    for (name, child) in children {
+        let path = format!("{}{}", prefix, name);
+        // NUL can't appear in names, so records can't run into each other
+        hasher.update(path.as_bytes());
+        if child.is_dir {
+            hasher.update(b"\0d\n");
+            add_children(hasher, child, &format!("{}/", path), with_content)?;
+            continue;
+        }
+
+        hasher.update(format!("\0f\0{}", child.size).as_bytes());
+        if with_content && child.path.is_file() {
+            let digest = hash::file(&child.path)
+                .with_context(|| format!("Failed to hash '{}'", child.path.display()))?;
+            hasher.update(b"\0");
+            hasher.update(hash::hex(&digest).as_bytes());
+        }
+        hasher.update(b"\n");
+    }
+    Ok(())
+}
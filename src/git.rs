@@ -0,0 +1,166 @@
+use anyhow::{Context, Result, bail};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::cli::OwnerBy;
+use crate::tree::Node;
+
+/// How much each author wrote of each entry.
+type Tally = HashMap<PathBuf, HashMap<String, usize>>;
+
+/// When the last commit touching each file below `dir` was made, keyed by
+/// `dir` joined with the file's path, so keys match the scanned nodes. Files
+/// git doesn't track are missing. Reads the history once, newest first.
+pub fn last_commit_times(dir: &Path) -> Result<HashMap<PathBuf, SystemTime>> {
+    // `%x01` marks where each commit starts; with -z names end in NUL, and the
+    // first one follows the header's newline
+    let output = log(dir, &["--format=format:%x01%ct", "--name-only"])?;
+
+    let mut times = HashMap::new();
+    let mut current = UNIX_EPOCH;
+    for token in output.split('\0') {
+        let name = match token.strip_prefix('\x01') {
+            Some(header) => {
+                let (time, name) = header.split_once('\n').unwrap_or((header, ""));
+                let secs = time.trim().parse().unwrap_or(0);
+                current = UNIX_EPOCH + Duration::from_secs(secs);
+                name
+            }
+            None => token.trim_start_matches('\n'),
+        };
+        if !name.is_empty() {
+            times.entry(dir.join(name)).or_insert(current);
+        }
+    }
+    Ok(times)
+}
+
+/// The author with the most commits touching, or the most lines of, each file
+/// below `root`, and of each directory taken together (the root included).
+/// Lines come from blaming every file shown; commits from the history of
+/// everything below the root, shown or not.
+pub fn top_authors(root: &Node, by: OwnerBy) -> Result<HashMap<PathBuf, String>> {
+    let tally = match by {
+        OwnerBy::Commits => commits_by_author(&root.path)?,
+        OwnerBy::Lines => lines_by_author(root),
+    };
+    Ok(tally
+        .into_iter()
+        .filter_map(|(path, authors)| {
+            // Most first, then by name so ties always go the same way
+            let (author, _) = authors
+                .into_iter()
+                .max_by(|(a, a_count), (b, b_count)| a_count.cmp(b_count).then(b.cmp(a)))?;
+            Some((path, author))
+        })
+        .collect())
+}
+
+fn commits_by_author(dir: &Path) -> Result<Tally> {
+    let output = log(dir, &["--format=format:%x01%aN", "--name-only"])?;
+
+    let mut tally = Tally::new();
+    let mut author = String::new();
+    // Each directory counts a commit once, however many files in it changed
+    let mut dirs = HashSet::new();
+    fn count_dirs(author: &str, dirs: &mut HashSet<PathBuf>, tally: &mut Tally) {
+        for dir in dirs.drain() {
+            *tally.entry(dir).or_default().entry(author.to_string()).or_default() += 1;
+        }
+    }
+    for token in output.split('\0') {
+        let name = match token.strip_prefix('\x01') {
+            Some(header) => {
+                count_dirs(&author, &mut dirs, &mut tally);
+                let (name_of_author, name) = header.split_once('\n').unwrap_or((header, ""));
+                author = name_of_author.to_string();
+                name
+            }
+            None => token.trim_start_matches('\n'),
+        };
+        if name.is_empty() {
+            continue;
+        }
+        *tally.entry(dir.join(name)).or_default().entry(author.clone()).or_default() += 1;
+        dirs.insert(dir.to_path_buf());
+        let parents = Path::new(name).ancestors().skip(1);
+        dirs.extend(parents.filter(|p| !p.as_os_str().is_empty()).map(|p| dir.join(p)));
+    }
+    count_dirs(&author, &mut dirs, &mut tally);
+    Ok(tally)
+}
+
+/// Blame every file shown; untracked ones are left out.
+fn lines_by_author(root: &Node) -> Tally {
+    let mut tally = Tally::new();
+    for file in root.descendants().into_iter().filter(|node| !node.is_dir) {
+        let (Some(dir), Some(name)) = (file.path.parent(), file.path.file_name()) else {
+            continue;
+        };
+        let output = Command::new("git")
+            .current_dir(if dir.as_os_str().is_empty() { Path::new(".") } else { dir })
+            .args(["blame", "--line-porcelain", "--"])
+            .arg(name)
+            .output();
+        let Ok(output) = output else {
+            continue;
+        };
+        if !output.status.success() {
+            continue;
+        }
+
+        let mut lines: HashMap<String, usize> = HashMap::new();
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            if let Some(author) = line.strip_prefix("author ") {
+                *lines.entry(author.to_string()).or_default() += 1;
+            }
+        }
+        // Directories add up the lines of the files inside
+        let owners = file.path.ancestors().take_while(|path| path.starts_with(&root.path));
+        for path in owners {
+            let authors = tally.entry(path.to_path_buf()).or_default();
+            for (author, count) in &lines {
+                *authors.entry(author.clone()).or_default() += count;
+            }
+        }
+    }
+    tally
+}
+
+/// Run `git log` in `dir` over its own contents only, with paths relative to
+/// it and NUL-terminated.
+/// The commit checked out in the repository holding `dir`, if any.
+pub fn head(dir: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn log(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["log", "-z", "--relative", "--no-renames"])
+        .args(args)
+        .arg("--")
+        .arg(".")
+        .output()
+        .context("Failed to run `git`")?;
+    if !output.status.success() {
+        bail!(
+            "git log failed in '{}': {}",
+            dir.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
@@ -0,0 +1,54 @@
+use md5::Md5;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use crate::cli::HashAlgorithm;
+use crate::throttle;
+
+/// The SHA-256 of a file's contents, read in chunks.
+pub fn file(path: &Path) -> io::Result<[u8; 32]> {
+    let mut hasher = Sha256::new();
+    read_chunks(path, |chunk| hasher.update(chunk))?;
+    Ok(hasher.finalize().into())
+}
+
+/// The digest of a file's contents with `algorithm`.
+pub fn file_with(path: &Path, algorithm: HashAlgorithm) -> io::Result<Vec<u8>> {
+    match algorithm {
+        HashAlgorithm::Sha256 => Ok(file(path)?.to_vec()),
+        HashAlgorithm::Sha1 => digest::<Sha1>(path),
+        HashAlgorithm::Md5 => digest::<Md5>(path),
+    }
+}
+
+fn digest<D: Digest>(path: &Path) -> io::Result<Vec<u8>> {
+    let mut hasher = D::new();
+    read_chunks(path, |chunk| hasher.update(chunk))?;
+    Ok(hasher.finalize().to_vec())
+}
+
+fn read_chunks(path: &Path, mut consume: impl FnMut(&[u8])) -> io::Result<()> {
+    let mut file = File::open(path)?;
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        match file.read(&mut buf)? {
+            0 => return Ok(()),
+            n => {
+                throttle::bytes(n);
+                consume(&buf[..n]);
+            }
+        }
+    }
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        let _ = write!(out, "{:02x}", byte);
+    }
+    out
+}
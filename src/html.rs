@@ -0,0 +1,249 @@
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::cli::{HtmlTheme, ListArgs};
+use crate::kind;
+use crate::tree::{Node, Scan, format_size};
+
+const DEFAULT_TEMPLATE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>{{title}}</title>
+<style>
+{{css}}
+</style>
+</head>
+<body>
+<h1>{{title}}</h1>
+{{tree}}
+</body>
+</html>
+"#;
+
+const DEFAULT_CSS: &str = r#"body { font-family: ui-monospace, SFMono-Regular, Menlo, Consolas, monospace; margin: 2em; }
+ul.tree, ul.tree ul { list-style: none; margin: 0; padding-left: 1.5em; }
+ul.tree { padding-left: 0; }
+summary { cursor: pointer; }
+a { color: inherit; text-decoration: none; }
+a:hover { text-decoration: underline; }
+.dir > details > summary > a { color: #1f5fbf; font-weight: bold; }
+.executable > a { color: #1a7f37; }
+.symlink > a, .symlink > details > summary > a { font-style: italic; color: #0e7490; }
+.size { color: #777; }"#;
+
+/// Overrides of the default colors for `--html-theme dark`.
+const DARK_CSS: &str = r#"body { background: #1e1e1e; color: #d4d4d4; color-scheme: dark; }
+.dir > details > summary > a { color: #6cb6ff; }
+.executable > a { color: #7ee787; }
+.symlink > a, .symlink > details > summary > a { color: #56d4dd; }
+.size { color: #999; }"#;
+
+/// A `REGEX=>REPLACEMENT` rule applied to every generated link.
+struct LinkRewrite {
+    pattern: regex::Regex,
+    replacement: String,
+}
+
+impl LinkRewrite {
+    fn parse(rule: &str) -> Result<Self> {
+        let Some((pattern, replacement)) = rule.split_once("=>") else {
+            anyhow::bail!("Invalid link rewrite '{}', expected 'REGEX=>REPLACEMENT'", rule);
+        };
+        Ok(Self {
+            pattern: regex::Regex::new(pattern)?,
+            replacement: replacement.to_string(),
+        })
+    }
+}
+
+struct Renderer<'a> {
+    root: &'a Path,
+    with_size: bool,
+    base_href: Option<&'a str>,
+    link_rewrites: Vec<LinkRewrite>,
+}
+
+/// Print the scanned tree as an HTML page, using `--html-template` instead of
+/// the built-in page when given.
+pub fn print(scan: &Scan, args: &ListArgs) -> Result<()> {
+    let template = match args.html_template.as_deref() {
+        Some(path) => fs::read_to_string(path)
+            .with_context(|| format!("Failed to read HTML template '{}'", path.display()))?,
+        None => DEFAULT_TEMPLATE.to_string(),
+    };
+    if !template.contains("{{tree}}") {
+        anyhow::bail!("HTML template has no {{{{tree}}}} placeholder");
+    }
+
+    let renderer = Renderer {
+        root: &scan.root.path,
+        with_size: args.du,
+        base_href: args.base_href.as_deref(),
+        link_rewrites: args
+            .link_rewrite
+            .iter()
+            .map(|rule| LinkRewrite::parse(rule))
+            .collect::<Result<_>>()?,
+    };
+
+    let mut tree = String::from("<ul class=\"tree\">\n");
+    renderer.render_node(&scan.root, &mut tree);
+    tree.push_str("</ul>");
+
+    let title = match &args.html_title {
+        Some(title) => escape(title),
+        None => escape(&scan.root.path.to_string_lossy()),
+    };
+    let mut css = DEFAULT_CSS.to_string();
+    if args.html_theme == HtmlTheme::Dark {
+        css.push('\n');
+        css.push_str(DARK_CSS);
+    }
+    if let Some(path) = args.html_css.as_deref() {
+        let extra = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read CSS file '{}'", path.display()))?;
+        css.push('\n');
+        css.push_str(extra.trim_end());
+    }
+    let page = fill(&template, |placeholder| match placeholder {
+        "title" => Some(title.as_str()),
+        "css" => Some(css.as_str()),
+        "tree" => Some(tree.as_str()),
+        _ => None,
+    });
+
+    io::stdout().lock().write_all(page.as_bytes())?;
+    Ok(())
+}
+
+impl Renderer<'_> {
+    /// Directories become `<details>` elements, open to begin with, whose
+    /// `<summary>` toggles their contents. Each `<li>` is classed `dir` or
+    /// `file`, plus `symlink` and `executable` where they apply.
+    fn render_node(&self, node: &Node, out: &mut String) {
+        let mut class = String::from(if node.is_dir { "dir" } else { "file" });
+        if node.link_target.is_some() {
+            class.push_str(" symlink");
+        }
+        if !node.is_dir && kind::is_executable(&node.path) {
+            class.push_str(" executable");
+        }
+        let _ = write!(out, "<li class=\"{}\">", class);
+        if node.is_dir {
+            out.push_str("<details open><summary>");
+        }
+        let _ = write!(
+            out,
+            "<a href=\"{}\">{}</a>",
+            escape(&self.href(node)),
+            escape(&node.name())
+        );
+        if self.with_size {
+            let _ = write!(out, " <span class=\"size\">[{}]</span>", format_size(node.size));
+        }
+
+        if node.is_dir {
+            out.push_str("</summary>\n<ul>\n");
+            for child in &node.children {
+                self.render_node(child, out);
+            }
+            out.push_str("</ul>\n</details>");
+        }
+        out.push_str("</li>\n");
+    }
+
+    /// The link for `node`: its local path, or the base href followed by the
+    /// path relative to the root, then passed through the rewrite rules.
+    fn href(&self, node: &Node) -> String {
+        let mut href = match self.base_href {
+            Some(base) => {
+                // URLs always use forward slashes, whatever the platform separator
+                let relative = node.path.strip_prefix(self.root).unwrap_or(&node.path);
+                let relative = relative
+                    .components()
+                    .map(|part| encode_href(&part.as_os_str().to_string_lossy()))
+                    .collect::<Vec<_>>()
+                    .join("/");
+                let mut href = base.trim_end_matches('/').to_string();
+                if !relative.is_empty() {
+                    href.push('/');
+                    href.push_str(&relative);
+                }
+                if node.is_dir {
+                    href.push('/');
+                }
+                href
+            }
+            None => encode_href(&node.path.to_string_lossy()),
+        };
+
+        for rewrite in &self.link_rewrites {
+            href = rewrite
+                .pattern
+                .replace_all(&href, rewrite.replacement.as_str())
+                .into_owned();
+        }
+        href
+    }
+}
+
+/// Replace `{{name}}` placeholders in a single pass, so placeholder-like text
+/// inside substituted values (e.g. file names) is left alone. Unknown
+/// placeholders are kept verbatim.
+fn fill<'a>(template: &str, value: impl Fn(&str) -> Option<&'a str>) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let replacement = rest
+            .find("}}")
+            .and_then(|end| value(rest[2..end].trim()).map(|v| (v, end + 2)));
+        match replacement {
+            Some((replacement, len)) => {
+                out.push_str(replacement);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push_str("{{");
+                rest = &rest[2..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+pub fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Percent-encode everything in a path except unreserved characters and `/`.
+fn encode_href(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~/".contains(&byte) {
+            out.push(byte as char);
+        } else {
+            let _ = write!(out, "%{:02X}", byte);
+        }
+    }
+    out
+}
@@ -0,0 +1,70 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::io::{self, Write};
+
+use crate::cli::Field;
+use crate::errors::ScanError;
+use crate::meta::Fields;
+use crate::paths::PathStyle;
+use crate::tree::{Node, Scan};
+
+#[derive(Serialize)]
+struct JsonNode {
+    name: String,
+    path: String,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    #[serde(flatten)]
+    fields: Fields,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    children: Option<Vec<JsonNode>>,
+}
+
+/// The root object additionally carries every error hit during the scan, so
+/// consumers can tell a partial listing from a complete one.
+#[derive(Serialize)]
+struct JsonDocument<'a> {
+    #[serde(flatten)]
+    root: JsonNode,
+    errors: &'a [ScanError],
+}
+
+impl JsonNode {
+    fn new(node: &Node, fields: &[Field], paths: &PathStyle) -> Self {
+        Self {
+            name: node.name(),
+            path: paths.apply(&node.path).to_string_lossy().into_owned(),
+            kind: if node.is_dir { "directory" } else { "file" },
+            fields: Fields::read(
+                &node.path,
+                fields,
+                || node.size,
+                node.link_target.as_deref(),
+                node.mtime,
+            ),
+            children: node.is_dir.then(|| {
+                node.children
+                    .iter()
+                    .map(|child| Self::new(child, fields, paths))
+                    .collect()
+            }),
+        }
+    }
+}
+
+/// Print the scanned tree as a single JSON document on stdout.
+pub fn print(scan: &Scan, fields: &[Field], paths: &PathStyle) -> Result<()> {
+    write(&mut io::stdout().lock(), scan, fields, paths)
+}
+
+/// Write the scanned tree as a single JSON document to `out`.
+pub fn write(out: &mut impl Write, scan: &Scan, fields: &[Field], paths: &PathStyle) -> Result<()> {
+    let document = JsonDocument {
+        root: JsonNode::new(&scan.root, fields, paths),
+        errors: &scan.errors,
+    };
+
+    serde_json::to_writer_pretty(&mut *out, &document)?;
+    writeln!(out)?;
+    Ok(())
+}
@@ -0,0 +1,140 @@
+use std::fs::{File, FileType};
+use std::io::Read;
+use std::path::Path;
+
+use crate::cli::FileKind;
+
+/// Whether `file_type` is a FIFO, socket or device node. Their sizes mean
+/// nothing and reading them can block.
+pub fn is_special(file_type: FileType) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        file_type.is_fifo()
+            || file_type.is_socket()
+            || file_type.is_block_device()
+            || file_type.is_char_device()
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = file_type;
+        false
+    }
+}
+
+/// Whether `path` is a file the current platform would run: any execute bit
+/// set on Unix, an extension listed in `PATHEXT` on Windows.
+pub fn is_executable(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        path.metadata()
+            .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+    }
+
+    #[cfg(windows)]
+    {
+        let Some(extension) = path.extension() else {
+            return false;
+        };
+        let extension = format!(".{}", extension.to_string_lossy());
+        let pathext = std::env::var("PATHEXT").unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".into());
+        path.is_file()
+            && pathext
+                .split(';')
+                .any(|known| known.eq_ignore_ascii_case(&extension))
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    {
+        let _ = path;
+        false
+    }
+}
+
+/// Enough for every signature below, including the tar magic at offset 257.
+const SNIFF_LEN: usize = 512;
+
+/// Guess what kind of file `path` is from its first bytes, ignoring its name.
+/// Returns `None` for unreadable files and content we don't recognize.
+pub fn detect(path: &Path) -> Option<FileKind> {
+    // Opening a FIFO or device can block, so only sniff regular files
+    if !path.metadata().ok()?.is_file() {
+        return None;
+    }
+
+    let mut header = Vec::with_capacity(SNIFF_LEN);
+    File::open(path)
+        .ok()?
+        .take(SNIFF_LEN as u64)
+        .read_to_end(&mut header)
+        .ok()?;
+    classify(&header)
+}
+
+fn classify(header: &[u8]) -> Option<FileKind> {
+    let starts = |magic: &[u8]| header.starts_with(magic);
+    let at = |offset: usize, magic: &[u8]| header.get(offset..offset + magic.len()) == Some(magic);
+
+    // RIFF and ISO base media containers name their payload a few bytes in
+    if starts(b"RIFF") {
+        return match header.get(8..12)? {
+            b"WEBP" => Some(FileKind::Image),
+            b"WAVE" => Some(FileKind::Audio),
+            b"AVI " => Some(FileKind::Video),
+            _ => None,
+        };
+    }
+    if at(4, b"ftyp") {
+        return match header.get(8..12)? {
+            b"heic" | b"heix" | b"mif1" | b"avif" => Some(FileKind::Image),
+            b"M4A " | b"M4B " => Some(FileKind::Audio),
+            _ => Some(FileKind::Video),
+        };
+    }
+
+    let kind = if starts(b"\x89PNG\r\n\x1a\n")
+        || starts(b"\xff\xd8\xff")
+        || starts(b"GIF87a")
+        || starts(b"GIF89a")
+        || starts(b"BM")
+        || starts(b"II*\0")
+        || starts(b"MM\0*")
+        || starts(b"\0\0\x01\0")
+    {
+        FileKind::Image
+    } else if starts(b"PK\x03\x04")
+        || starts(b"PK\x05\x06")
+        || starts(b"\x1f\x8b")
+        || starts(b"BZh")
+        || starts(b"\xfd7zXZ\0")
+        || starts(b"\x28\xb5\x2f\xfd")
+        || starts(b"7z\xbc\xaf\x27\x1c")
+        || starts(b"Rar!\x1a\x07")
+        || at(257, b"ustar")
+    {
+        FileKind::Archive
+    } else if starts(b"ID3")
+        || starts(b"fLaC")
+        || starts(b"OggS")
+        || (header.len() >= 2 && header[0] == 0xff && header[1] & 0xe0 == 0xe0)
+    {
+        FileKind::Audio
+    } else if starts(b"\x1a\x45\xdf\xa3") || starts(b"FLV") || starts(b"\0\0\x01\xba") {
+        FileKind::Video
+    } else if starts(b"\x7fELF")
+        || starts(b"MZ")
+        || starts(b"\xfe\xed\xfa\xce")
+        || starts(b"\xfe\xed\xfa\xcf")
+        || starts(b"\xce\xfa\xed\xfe")
+        || starts(b"\xcf\xfa\xed\xfe")
+        || starts(b"\xca\xfe\xba\xbe")
+        || starts(b"#!")
+    {
+        FileKind::Executable
+    } else {
+        return None;
+    };
+    Some(kind)
+}
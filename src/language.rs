@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+use crate::tree::Node;
+
+pub struct Language {
+    pub name: &'static str,
+    /// The color GitHub's language bar uses
+    pub color: (u8, u8, u8),
+    extensions: &'static [&'static str],
+    /// Whole file names, for files without a telling extension
+    names: &'static [&'static str],
+    /// Interpreters named on a `#!` line, for scripts without an extension
+    interpreters: &'static [&'static str],
+}
+
+const fn language(
+    name: &'static str,
+    color: (u8, u8, u8),
+    extensions: &'static [&'static str],
+) -> Language {
+    Language {
+        name,
+        color,
+        extensions,
+        names: &[],
+        interpreters: &[],
+    }
+}
+
+const LANGUAGES: &[Language] = &[
+    language("Rust", (222, 165, 132), &["rs"]),
+    Language {
+        interpreters: &["python", "python3"],
+        ..language("Python", (53, 114, 165), &["py", "pyi", "pyw"])
+    },
+    Language {
+        interpreters: &["node", "deno", "bun"],
+        ..language("JavaScript", (241, 224, 90), &["js", "mjs", "cjs", "jsx"])
+    },
+    language("TypeScript", (49, 120, 198), &["ts", "tsx", "mts", "cts"]),
+    language("Go", (0, 173, 216), &["go"]),
+    language("C", (85, 85, 85), &["c", "h"]),
+    language("C++", (243, 75, 125), &["cc", "cpp", "cxx", "hh", "hpp", "hxx"]),
+    language("C#", (23, 134, 0), &["cs"]),
+    language("Java", (176, 114, 25), &["java"]),
+    language("Kotlin", (169, 123, 255), &["kt", "kts"]),
+    language("Swift", (240, 81, 56), &["swift"]),
+    language("Scala", (194, 45, 64), &["scala", "sc"]),
+    language("Dart", (0, 180, 171), &["dart"]),
+    language("Haskell", (94, 80, 134), &["hs"]),
+    language("Zig", (236, 145, 92), &["zig"]),
+    Language {
+        interpreters: &["ruby"],
+        ..language("Ruby", (112, 21, 22), &["rb"])
+    },
+    Language {
+        interpreters: &["perl"],
+        ..language("Perl", (2, 152, 195), &["pl", "pm"])
+    },
+    Language {
+        interpreters: &["lua"],
+        ..language("Lua", (0, 0, 128), &["lua"])
+    },
+    language("PHP", (79, 93, 149), &["php"]),
+    Language {
+        interpreters: &["sh", "bash", "zsh", "dash", "ksh", "fish"],
+        ..language("Shell", (137, 224, 81), &["sh", "bash", "zsh", "fish"])
+    },
+    language("PowerShell", (1, 36, 86), &["ps1", "psm1"]),
+    language("HTML", (227, 76, 38), &["html", "htm"]),
+    language("CSS", (86, 61, 124), &["css", "scss", "sass", "less"]),
+    language("Vue", (65, 184, 131), &["vue"]),
+    language("Svelte", (255, 62, 0), &["svelte"]),
+    language("SQL", (227, 140, 0), &["sql"]),
+    language("Nix", (126, 126, 255), &["nix"]),
+    language("Markdown", (8, 63, 161), &["md", "markdown"]),
+    language("TOML", (156, 66, 33), &["toml"]),
+    language("YAML", (203, 23, 30), &["yml", "yaml"]),
+    language("JSON", (41, 41, 41), &["json"]),
+    Language {
+        names: &["Makefile", "makefile", "GNUmakefile"],
+        ..language("Makefile", (66, 120, 25), &["mk"])
+    },
+    Language {
+        names: &["Dockerfile", "Containerfile"],
+        ..language("Dockerfile", (56, 77, 84), &["dockerfile"])
+    },
+    Language {
+        names: &["CMakeLists.txt"],
+        ..language("CMake", (218, 52, 52), &["cmake"])
+    },
+];
+
+/// The programming or markup language of the file at `path`: by its name or
+/// extension, or for extensionless scripts by the interpreter on their `#!`
+/// line. `None` for everything else, binaries and plain text included.
+pub fn detect(path: &Path) -> Option<&'static Language> {
+    let name = path.file_name()?.to_str()?;
+    if let Some(language) = LANGUAGES.iter().find(|language| language.names.contains(&name)) {
+        return Some(language);
+    }
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => {
+            let ext = ext.to_ascii_lowercase();
+            LANGUAGES.iter().find(|language| language.extensions.contains(&ext.as_str()))
+        }
+        None => {
+            let interpreter = interpreter(path)?;
+            LANGUAGES.iter().find(|language| language.interpreters.contains(&interpreter.as_str()))
+        }
+    }
+}
+
+/// How many bytes of each language every directory of the tree holds, at any
+/// depth, most first.
+pub fn tally(root: &Node) -> HashMap<PathBuf, Vec<(&'static Language, u64)>> {
+    fn visit(node: &Node, tallies: &mut HashMap<PathBuf, Vec<(&'static Language, u64)>>) {
+        let mut bytes: Vec<(&'static Language, u64)> = Vec::new();
+        for child in &node.children {
+            let languages = if child.is_dir {
+                visit(child, tallies);
+                tallies.get(&child.path).cloned().unwrap_or_default()
+            } else {
+                detect(&child.path).map(|language| (language, child.size)).into_iter().collect()
+            };
+            for (language, size) in languages {
+                match bytes.iter_mut().find(|(known, _)| known.name == language.name) {
+                    Some((_, total)) => *total += size,
+                    None => bytes.push((language, size)),
+                }
+            }
+        }
+        bytes.sort_by(|(a, a_size), (b, b_size)| b_size.cmp(a_size).then(a.name.cmp(b.name)));
+        tallies.insert(node.path.clone(), bytes);
+    }
+
+    let mut tallies = HashMap::new();
+    visit(root, &mut tallies);
+    tallies
+}
+
+/// `python3` for `#!/usr/bin/python3` and `#!/usr/bin/env python3`.
+fn interpreter(path: &Path) -> Option<String> {
+    // Opening a FIFO or device can block, so only read regular files
+    if !path.metadata().ok()?.is_file() {
+        return None;
+    }
+    let mut line = String::new();
+    // Binaries fail to read as UTF-8 here and are left alone
+    BufReader::new(File::open(path).ok()?.take(256)).read_line(&mut line).ok()?;
+    let mut words = line.strip_prefix("#!")?.split_whitespace();
+    let program = words.next()?.rsplit('/').next()?;
+    let program = if program == "env" {
+        words.find(|word| !word.starts_with('-'))?
+    } else {
+        program
+    };
+    Some(program.to_string())
+}
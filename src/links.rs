@@ -0,0 +1,122 @@
+use colored::*;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::paths::PathStyle;
+use crate::printer::TreePrinter;
+use crate::tree::Node;
+
+/// Links followed before a chain counts as a loop, as on Linux.
+const MAX_HOPS: usize = 40;
+
+/// Where a chain of links ends up.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum End {
+    /// The canonical path of what the last link points to
+    Target(PathBuf),
+    /// The last link points to nothing
+    Missing(PathBuf),
+    /// The chain never ends; keyed by where it was abandoned
+    Loop(PathBuf),
+}
+
+/// Print every symlink in the tree with the chain of links it goes through,
+/// grouped by the final target they all end up at, so links that resolve to
+/// the same file show up together however they get there.
+pub fn print(
+    root: &Node,
+    printer: &TreePrinter,
+    paths: &PathStyle,
+    use_color: bool,
+) -> io::Result<()> {
+    let mut groups: BTreeMap<End, Vec<String>> = BTreeMap::new();
+    let mut links = 0;
+    for node in root.descendants() {
+        if node.link_target.is_none() {
+            continue;
+        }
+        let (hops, end) = resolve(&node.path);
+        let mut line = paths.apply(&node.path).to_string_lossy().into_owned();
+        for hop in &hops {
+            line.push_str(&format!(" -> {}", hop.display()));
+        }
+        groups.entry(end).or_default().push(line);
+        links += 1;
+    }
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    for (end, lines) in &groups {
+        let (path, state) = match end {
+            End::Target(path) => (path, ""),
+            End::Missing(path) => (path, "missing, "),
+            End::Loop(path) => (path, "loop, "),
+        };
+        let title = path.display().to_string();
+        let title = match (use_color, end) {
+            (false, _) => title,
+            (true, End::Target(_)) => title.bold().to_string(),
+            (true, _) => title.red().bold().to_string(),
+        };
+        let count = if lines.len() == 1 { "link" } else { "links" };
+        writeln!(out, "{} ({}{} {})", title, state, lines.len(), count)?;
+        for (i, line) in lines.iter().enumerate() {
+            let line = if use_color { line.cyan().to_string() } else { line.clone() };
+            printer.write_line(&mut out, "", i == lines.len() - 1, &line)?;
+        }
+    }
+    if !groups.is_empty() {
+        writeln!(out)?;
+    }
+    let targets = if groups.len() == 1 { "target" } else { "targets" };
+    let plural = if links == 1 { "" } else { "s" };
+    writeln!(out, "{} link{} to {} {}", links, plural, groups.len(), targets)?;
+    out.flush()
+}
+
+/// Follow the link at `path` to the end: the targets of every link on the
+/// way, as written, and where that is.
+fn resolve(path: &Path) -> (Vec<PathBuf>, End) {
+    let mut hops = Vec::new();
+    // Where each link on the way really is, to tell when the chain comes back
+    let mut visited: Vec<PathBuf> = Vec::new();
+    let mut current = path.to_path_buf();
+    while let Ok(target) = fs::read_link(&current) {
+        let location = real_location(&current);
+        if let Some(start) = visited.iter().position(|link| *link == location) {
+            // Every link in the cycle ends up at the same key
+            let first = visited[start..].iter().min().cloned().unwrap_or(location);
+            return (hops, End::Loop(first));
+        }
+        if hops.len() == MAX_HOPS {
+            return (hops, End::Loop(location));
+        }
+        visited.push(location);
+        // Relative targets start from the directory holding the link
+        current = match current.parent() {
+            Some(dir) => dir.join(&target),
+            None => target.clone(),
+        };
+        hops.push(target);
+    }
+    // Links in the directories on the way are resolved here too
+    match fs::canonicalize(&current) {
+        Ok(target) => (hops, End::Target(target)),
+        Err(_) => (hops, End::Missing(std::path::absolute(&current).unwrap_or(current))),
+    }
+}
+
+/// The canonical path of the directory holding the link at `path`, joined with
+/// its name.
+fn real_location(path: &Path) -> PathBuf {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let dir = fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+    match path.file_name() {
+        Some(name) => dir.join(name),
+        None => dir,
+    }
+}
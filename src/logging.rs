@@ -0,0 +1,29 @@
+use tracing::Level;
+use tracing_subscriber::fmt::format::FmtSpan;
+
+use crate::cli::LogFormat;
+
+/// Install a stderr logger for `-v` (info), `-vv` (debug) and `-vvv` (trace).
+/// Span close events carry their timings, so `-v` already reports how long
+/// walking and rendering took.
+pub fn init(verbose: u8, format: LogFormat) {
+    let level = match verbose {
+        0 => return,
+        1 => Level::INFO,
+        2 => Level::DEBUG,
+        _ => Level::TRACE,
+    };
+
+    let builder = tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_max_level(level)
+        .with_span_events(FmtSpan::CLOSE)
+        .with_target(false);
+
+    match format {
+        LogFormat::Text => builder
+            .with_ansi(atty::is(atty::Stream::Stderr))
+            .init(),
+        LogFormat::Json => builder.json().init(),
+    }
+}
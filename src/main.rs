@@ -1,306 +1,536 @@
-use anyhow::Result;
-use clap::Parser;
-use colored::*;
-use ignore::WalkBuilder;
-use std::path::{Path, PathBuf};
-use glob::Pattern;
-
-#[derive(Parser)]
-#[command(name = "treee")]
-#[command(about = "A fast tree command with gitignore support and flexible filtering")]
-#[command(version = "0.1.0")]
-struct Args {
-    /// Directory to traverse
-    #[arg(default_value = ".")]
-    path: PathBuf,
-
-    /// Maximum depth to traverse
-    #[arg(short = 'L', long, default_value = "10")]
-    depth: usize,
-
-    /// Show hidden files
-    #[arg(short = 'a', long)]
-    all: bool,
-
-    /// Don't use colors
-    #[arg(long)]
-    no_color: bool,
-
-    /// Show directories only
-    #[arg(short = 'd', long)]
-    directories_only: bool,
-
-    /// Include paths matching these glob patterns (can be used multiple times)
-    #[arg(short = 'I', long = "include", action = clap::ArgAction::Append)]
-    include_patterns: Vec<String>,
-
-    /// Exclude paths matching these glob patterns (can be used multiple times)
-    #[arg(short = 'E', long = "exclude", action = clap::ArgAction::Append)]
-    exclude_patterns: Vec<String>,
-
-    /// File name patterns to match (glob patterns, can be used multiple times)
-    #[arg(short = 'P', long = "pattern", action = clap::ArgAction::Append)]
-    file_patterns: Vec<String>,
-
-    /// Disable gitignore rules
-    #[arg(long = "no-git-ignore")]
-    no_git_ignore: bool,
-
-    /// Show only files (opposite of --directories-only)
-    #[arg(short = 'f', long)]
-    files_only: bool,
-
-    /// Print full paths instead of tree format
-    #[arg(long)]
-    full_path: bool,
-}
-
-struct PathFilter {
-    include_patterns: Vec<Pattern>,
-    exclude_patterns: Vec<Pattern>,
-    file_patterns: Vec<Pattern>,
-}
+mod archive;
+mod cache;
+mod cli;
+mod codeowners;
+mod color;
+mod compat;
+mod diff;
+mod docinfo;
+mod dot;
+mod drives;
+mod errors;
+mod exec;
+mod filter;
+mod fingerprint;
+mod git;
+mod hash;
+mod html;
+mod json;
+mod kind;
+mod language;
+mod links;
+mod logging;
+mod manifest;
+mod markdown;
+mod media;
+mod mermaid;
+mod meta;
+mod mounts;
+mod ndjson;
+mod ondisk;
+mod org;
+mod output;
+mod owners;
+mod packages;
+mod paths;
+mod plantuml;
+mod printer;
+mod quote;
+mod resume;
+mod rst;
+mod secrets;
+mod serve;
+mod stats;
+mod suggest;
+mod table;
+mod template;
+mod term;
+mod throttle;
+mod time;
+mod tree;
+mod watch;
+mod xml;
+
+use anyhow::{Context, Result};
+use clap::{CommandFactory, Parser};
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+use cli::{Cli, ColorChoice, Command, Field, ListArgs, ScanArgs};
+use paths::PathStyle;
+use printer::{Glyphs, TreePrinter};
 
-impl PathFilter {
-    fn new(
-        include_patterns: &[String],
-        exclude_patterns: &[String],
-        file_patterns: &[String],
-    ) -> Result<Self> {
-        let include_patterns = include_patterns
-            .iter()
-            .map(|p| Pattern::new(p))
-            .collect::<Result<Vec<_>, _>>()?;
-
-        let exclude_patterns = exclude_patterns
-            .iter()
-            .map(|p| Pattern::new(p))
-            .collect::<Result<Vec<_>, _>>()?;
-
-        let file_patterns = file_patterns
-            .iter()
-            .map(|p| Pattern::new(p))
-            .collect::<Result<Vec<_>, _>>()?;
-
-        Ok(Self {
-            include_patterns,
-            exclude_patterns,
-            file_patterns,
-        })
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    logging::init(cli.verbose, cli.log_format);
+
+    let result = run(cli);
+    output::finish()?;
+    let had_errors = match result {
+        Ok(had_errors) => had_errors,
+        // Output cut short by e.g. `| head` is not worth an error message
+        Err(err) if is_broken_pipe(&err) => false,
+        Err(err) => return Err(err),
+    };
+
+    if had_errors {
+        std::process::exit(1);
     }
 
-    fn should_include(&self, path: &Path) -> bool {
-        let path_str = path.to_string_lossy();
-        let file_name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    Ok(())
+}
 
-        // Check exclude patterns first
-        for pattern in &self.exclude_patterns {
-            if pattern.matches(&path_str) || pattern.matches(&file_name) {
-                return false;
-            }
+/// Run the selected command. Returns whether any non-fatal errors occurred.
+fn run(cli: Cli) -> Result<bool> {
+    let had_errors = match cli.command {
+        None => {
+            let mut args = cli.list;
+            resolve_root(&mut args)?;
+            run_list(&args, init_output(&args)?)?
         }
-
-        // For directories, always include them unless explicitly excluded
-        // This allows traversal to find matching files in subdirectories
-        if path.is_dir() {
-            return true;
+        Some(Command::List(mut args)) => {
+            resolve_root(&mut args)?;
+            run_list(&args, init_output(&args)?)?
         }
-
-        // For files, check include patterns
-        if !self.include_patterns.is_empty() {
-            let included = self.include_patterns.iter().any(|pattern| {
-                pattern.matches(&path_str) || pattern.matches(&file_name)
-            });
-            if !included {
-                return false;
+        Some(Command::Du(mut args)) => {
+            args.du = true;
+            resolve_root(&mut args)?;
+            run_list(&args, init_output(&args)?)?
+        }
+        Some(Command::Diff(args)) => {
+            // Exit codes follow diff(1): 0 same, 1 different, 2 trouble
+            match diff::run(&args, color::init(args.color.choice()))? {
+                diff::Outcome::Same => {}
+                diff::Outcome::Different => std::process::exit(1),
+                diff::Outcome::Incomplete => std::process::exit(2),
             }
+            false
         }
-
-        // Check file patterns for files (only if there are file patterns)
-        if !self.file_patterns.is_empty() {
-            return self.file_patterns.iter().any(|pattern| {
-                pattern.matches(&file_name)
-            });
+        Some(Command::Watch(mut args)) => {
+            resolve_root(&mut args.list)?;
+            watch::run(&args, init_output(&args.list)?)?;
+            false
         }
+        Some(Command::Completions(args)) => {
+            clap_complete::generate(
+                args.shell,
+                &mut Cli::command(),
+                "treee",
+                &mut std::io::stdout(),
+            );
+            false
+        }
+    };
 
-        true
+    Ok(had_errors)
+}
+
+/// Send stdout to -o FILE if given, compressed if asked for, then settle on
+/// colors: a file or compressed output gets none unless they are forced. Sizes
+/// are written as --si or --bytes say from here on.
+fn init_output(args: &ListArgs) -> Result<bool> {
+    if let Some(style) = args.size_style() {
+        tree::set_size_style(style);
     }
+    time::set_kind(args.scan.time);
+    let mut choice = args.color.choice();
+    let path = args.output_file.as_deref();
+    let compression = args.compress.or_else(|| path.and_then(output::compression_for));
+    match (path, compression) {
+        (path, Some(compression)) => output::compress_stdout(path, compression)?,
+        (Some(path), None) => output::redirect_stdout(path)?,
+        (None, None) => {}
+    }
+    if (path.is_some() || compression.is_some()) && choice == ColorChoice::Auto {
+        choice = ColorChoice::Never;
+    }
+    Ok(color::init(choice))
 }
 
-struct TreePrinter {
-    use_color: bool,
-    full_path: bool,
+fn is_broken_pipe(err: &anyhow::Error) -> bool {
+    let kind = match err.downcast_ref::<serde_json::Error>() {
+        Some(err) => err.io_error_kind(),
+        None => err.downcast_ref::<io::Error>().map(io::Error::kind),
+    };
+    kind == Some(io::ErrorKind::BrokenPipe)
 }
 
-impl TreePrinter {
-    fn new(use_color: bool, full_path: bool) -> Self {
-        Self { use_color, full_path }
-    }
-
-    fn print_entry(&self, path: &Path, prefix: &str, is_last: bool, is_dir: bool) {
-        if self.full_path {
-            // Print full path
-            let path_str = path.to_string_lossy();
-            let formatted_path = if self.use_color {
-                if is_dir {
-                    path_str.blue().bold().to_string()
-                } else {
-                    path_str.to_string()
-                }
-            } else {
-                path_str.to_string()
-            };
-            println!("{}", formatted_path);
-        } else {
-            // Print tree format
-            let connector = if is_last { "└── " } else { "├── " };
-            let name = path.file_name().unwrap().to_string_lossy();
-
-            let formatted_name = if self.use_color {
-                if is_dir {
-                    name.blue().bold().to_string()
-                } else {
-                    name.to_string()
-                }
-            } else {
-                name.to_string()
-            };
-
-            println!("{}{}{}", prefix, connector, formatted_name);
-        }
+/// Replace the path to scan according to flags like `--repo`, and expand
+/// presets like `--overview` and `--fast` into the flags they stand for.
+fn resolve_root(args: &mut ListArgs) -> Result<()> {
+    // Drop trailing and doubled separators, so `src//` lists entries as
+    // `src/lib.rs`; `\\.\` is kept as is since it selects all drives on Windows
+    if args.path.as_os_str() != r"\\.\" {
+        args.path = args.path.components().collect();
+    }
+
+    if args.overview {
+        args.du = true;
+        args.counts = true;
+        args.scan.dirsfirst = true;
     }
 
-    fn get_child_prefix(&self, prefix: &str, is_last: bool) -> String {
-        if self.full_path {
-            String::new() // No prefix needed for full path mode
-        } else {
-            let extension = if is_last { "    " } else { "│   " };
-            format!("{}{}", prefix, extension)
+    if args.scan.fast {
+        args.scan.depth = args.scan.depth.min(tree::FAST_MAX_DEPTH);
+        args.scan.no_order_files = true;
+        args.color.no_color = true;
+    }
+
+    if args.flat {
+        args.full_path = true;
+        // -f usually keeps directories out of the walk altogether
+        args.flat_files_only = args.scan.files_only;
+        args.scan.files_only = false;
+        if args.relative_to.is_none() && !args.absolute {
+            args.relative_to = Some(None);
         }
     }
+
+    if args.octal_perms {
+        args.permissions = true;
+    }
+    if args.timefmt.is_some() {
+        args.date = true;
+    }
+
+    // Credentials are usually hidden, and kept out of git
+    if args.scan.scan_secrets_names {
+        args.scan.all = true;
+        args.scan.no_git_ignore = true;
+    }
+
+    if args.repo {
+        args.path = tree::find_repo_root(&args.path).ok_or_else(|| {
+            anyhow::anyhow!("'{}' is not inside a git repository", args.path.display())
+        })?;
+    }
+    Ok(())
 }
 
-fn main() -> Result<()> {
-    let args = Args::parse();
+/// Scan and print a single directory tree.
+/// Returns whether any non-fatal errors occurred along the way.
+pub fn run_list(args: &ListArgs, use_color: bool) -> Result<bool> {
+    if args.media_info {
+        media::ensure_enabled()?;
+    }
+    if args.doc_info {
+        docinfo::ensure_enabled()?;
+    }
+    if args.on_disk {
+        ondisk::ensure_enabled()?;
+    }
+
+    // There is no single root on Windows; `\\.\` stands for all drives
+    if args.drives || (cfg!(windows) && args.path.as_os_str() == r"\\.\") {
+        return drives::run(args, use_color);
+    }
 
     if !args.path.exists() {
         eprintln!("Error: Path '{}' does not exist", args.path.display());
         std::process::exit(1);
     }
 
-    // Validate conflicting options
-    if args.directories_only && args.files_only {
-        eprintln!("Error: Cannot use both --directories-only and --files-only");
-        std::process::exit(1);
+    let paths = PathStyle::new(
+        args.absolute,
+        args.relative_to.as_ref().map(|dir| dir.as_deref()),
+        &args.path,
+    );
+
+    if args.serve_json {
+        return serve::run(args);
     }
 
-    let use_color = !args.no_color && atty::is(atty::Stream::Stdout);
-    let printer = TreePrinter::new(use_color, args.full_path);
+    // Streamed straight from the walk, so there is no tree to build
+    if args.ndjson {
+        return ndjson::print(args, &paths);
+    }
 
-    // Create path filter
-    let path_filter = PathFilter::new(
-        &args.include_patterns,
-        &args.exclude_patterns,
-        &args.file_patterns,
-    )?;
+    // Only the tree itself is cached; --json and friends always scan
+    let cache = ((args.cache || args.refresh) && !args.no_cache && args.prints_tree())
+        .then(|| cache::Cache::new(args, use_color))
+        .flatten();
+    if let Some(cache) = &cache
+        && !args.refresh
+        && let Some(output) = cache.load()
+    {
+        io::stdout().lock().write_all(&output)?;
+        return Ok(false);
+    }
 
-    // Print the root directory (only in tree mode)
-    if !args.full_path {
-        let root_name = args.path.file_name()
-            .unwrap_or_else(|| args.path.as_os_str())
-            .to_string_lossy();
-
-        let formatted_root = if use_color {
-            root_name.blue().bold().to_string()
-        } else {
-            root_name.to_string()
-        };
-
-        println!("{}", formatted_root);
-    }
-
-    // Build the walker
-    let mut builder = WalkBuilder::new(&args.path);
-    builder
-        .max_depth(Some(args.depth))
-        .hidden(!args.all)
-        .git_ignore(!args.no_git_ignore)
-        .git_exclude(!args.no_git_ignore)
-        .git_global(!args.no_git_ignore);
-
-    let walker = builder.build();
-
-    // Collect entries and organize them
-    let mut entries: Vec<_> = walker
-        .filter_map(|entry| entry.ok())
-        .filter(|entry| {
-            let path = entry.path();
-            // Skip the root directory itself
-            if path == args.path {
-                return false;
+    let template = args.format.as_deref().map(template::Template::parse).transpose()?;
+    let fields = args.fields();
+    // The languages of directories come from their files, which -d drops as
+    // soon as the tree is built; keep them until they are tallied
+    let keep_files = args.lang_bars && args.scan.directories_only;
+    let scan_args = if keep_files {
+        Cow::Owned(ScanArgs {
+            directories_only: false,
+            ..args.scan.clone()
+        })
+    } else {
+        Cow::Borrowed(&args.scan)
+    };
+    // Files are hashed for --manifest as the walk finds them
+    let hasher = args
+        .manifest
+        .map(|algorithm| -> Result<_> {
+            let progress = !args.quiet && atty::is(atty::Stream::Stderr);
+            let resume = args
+                .resume
+                .as_deref()
+                .map(|path| resume::ResumeState::open(path, algorithm))
+                .transpose()?;
+            Ok(manifest::Hasher::start(algorithm, args.jobs(), progress, resume))
+        })
+        .transpose()?;
+    let mut scan = tree::scan_with(
+        &args.path,
+        &scan_args,
+        args.du
+            || args.size
+            || fields.contains(&Field::Size)
+            || args.total
+            || args.stats_by.is_some()
+            || args.fingerprint
+            || args.suggest_prune.is_some()
+            || template.as_ref().is_some_and(template::Template::needs_sizes)
+            || args.highlight_size.is_some()
+            || args.collapse_files
+            || args.packages
+            || args.lang_bars,
+        |entry| {
+            if let Some(hasher) = &hasher {
+                hasher.queue(entry);
             }
+        },
+    )?;
+    let lang_bars = args.lang_bars.then(|| language::tally(&scan.root));
+    if keep_files {
+        tree::prune_files(&mut scan.root);
+    }
 
-            // Apply path filter
-            if !path_filter.should_include(path) {
-                return false;
-            }
+    // Archive the same scan as JSON, on top of whatever goes to stdout
+    if let Some(path) = &args.json_out {
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create '{}'", path.display()))?;
+        let mut out = BufWriter::new(file);
+        json::write(&mut out, &scan, &fields, &paths)?;
+        out.flush()?;
+    }
 
-            // Filter directories only if requested
-            if args.directories_only && !path.is_dir() {
-                return false;
-            }
+    if args.total {
+        println!("{}\t{}", tree::format_size(scan.root.size), args.path.display());
+        return Ok(tree::report_errors(&scan.errors, args.quiet));
+    }
 
-            // Filter files only if requested
-            if args.files_only && !path.is_file() {
-                return false;
-            }
+    if args.fingerprint {
+        println!("{}", fingerprint::compute(&scan.root, args.fingerprint_content)?);
+        return Ok(tree::report_errors(&scan.errors, args.quiet));
+    }
 
-            true
-        })
-        .collect();
+    if let Some(algorithm) = args.manifest {
+        let mut digests = hasher.map(manifest::Hasher::finish).transpose()?.unwrap_or_default();
+        manifest::print(&scan.root, algorithm, &mut digests, &paths, &mut scan.errors)?;
+        return Ok(tree::report_errors(&scan.errors, args.quiet));
+    }
 
-    // Sort entries by path
-    entries.sort_by(|a, b| a.path().cmp(b.path()));
+    if let Some(baseline) = &args.baseline_owners {
+        let printer =
+            TreePrinter::new(use_color, false, false).with_glyphs(Glyphs::from_args(args));
+        let drifted = owners::run(&scan.root, baseline, &printer, use_color)?;
+        return Ok(tree::report_errors(&scan.errors, args.quiet) || drifted);
+    }
 
-    // Group entries by their parent directory
-    let mut dir_contents: std::collections::HashMap<PathBuf, Vec<_>> = std::collections::HashMap::new();
+    if args.packages {
+        let printer =
+            TreePrinter::new(use_color, false, false).with_glyphs(Glyphs::from_args(args));
+        packages::print(&scan.root, &printer, use_color)?;
+        return Ok(tree::report_errors(&scan.errors, args.quiet));
+    }
 
-    for entry in entries {
-        let path = entry.path();
-        if let Some(parent) = path.parent() {
-            dir_contents.entry(parent.to_path_buf()).or_default().push(path.to_path_buf());
-        }
+    if args.resolve_links_display {
+        let printer =
+            TreePrinter::new(use_color, false, false).with_glyphs(Glyphs::from_args(args));
+        links::print(&scan.root, &printer, &paths, use_color)?;
+        return Ok(tree::report_errors(&scan.errors, args.quiet));
     }
 
-    // Print the tree recursively
-    print_tree_recursive(&args.path, &dir_contents, &printer, "", true)?;
+    if let Some(key) = args.stats_by {
+        stats::print(&scan.root, key)?;
+        return Ok(tree::report_errors(&scan.errors, args.quiet));
+    }
 
-    Ok(())
-}
+    if let Some(target) = &args.suggest_prune {
+        suggest::print(&scan.root, target, args.prune_by)?;
+        return Ok(tree::report_errors(&scan.errors, args.quiet));
+    }
 
-fn print_tree_recursive(
-    current_dir: &Path,
-    dir_contents: &std::collections::HashMap<PathBuf, Vec<PathBuf>>,
-    printer: &TreePrinter,
-    prefix: &str,
-    _is_last: bool,
-) -> Result<()> {
-    if let Some(children) = dir_contents.get(current_dir) {
-        let mut sorted_children = children.clone();
-        sorted_children.sort();
-
-        for (i, child_path) in sorted_children.iter().enumerate() {
-            let is_last = i == sorted_children.len() - 1;
-            let is_dir = child_path.is_dir();
-
-            printer.print_entry(child_path, prefix, is_last, is_dir);
-
-            if is_dir {
-                let child_prefix = printer.get_child_prefix(prefix, is_last);
-                print_tree_recursive(child_path, dir_contents, printer, &child_prefix, is_last)?;
-            }
-        }
+    if args.exec.is_some() || args.exec_batch.is_some() {
+        let had_errors = tree::report_errors(&scan.errors, args.quiet);
+        return Ok(run_exec(args, &scan.root)? || had_errors);
     }
 
-    Ok(())
+    // Errors are part of the JSON document, so keep them off stderr
+    if args.json {
+        json::print(&scan, &fields, &paths)?;
+        return Ok(!scan.errors.is_empty());
+    }
+
+    if args.dot {
+        dot::print(&scan.root, args.du)?;
+        return Ok(tree::report_errors(&scan.errors, args.quiet));
+    }
+
+    if args.mermaid {
+        mermaid::print(&scan.root, args.du, args.mermaid_direction)?;
+        return Ok(tree::report_errors(&scan.errors, args.quiet));
+    }
+
+    if let Some(style) = args.plantuml {
+        plantuml::print(&scan.root, args.du, style)?;
+        return Ok(tree::report_errors(&scan.errors, args.quiet));
+    }
+
+    if let Some(cli::Compat::Windows) = args.compat {
+        compat::print_windows(&scan.root, &args.path)?;
+        return Ok(tree::report_errors(&scan.errors, args.quiet));
+    }
+
+    if args.csv || args.tsv {
+        let format = if args.csv { table::Format::Csv } else { table::Format::Tsv };
+        table::print(&scan.root, format, &fields, &paths)?;
+        return Ok(tree::report_errors(&scan.errors, args.quiet));
+    }
+
+    if let Some(template) = &template {
+        template::print(&scan.root, template, &paths)?;
+        return Ok(tree::report_errors(&scan.errors, args.quiet));
+    }
+
+    if let Some(style) = args.rst {
+        rst::print(&scan.root, args.du, style)?;
+        return Ok(tree::report_errors(&scan.errors, args.quiet));
+    }
+
+    if let Some(style) = args.org {
+        org::print(&scan.root, style, &paths)?;
+        return Ok(tree::report_errors(&scan.errors, args.quiet));
+    }
+
+    if let Some(style) = args.markdown {
+        markdown::print(&scan.root, args.du, style)?;
+        return Ok(tree::report_errors(&scan.errors, args.quiet));
+    }
+
+    if args.xml {
+        xml::print(&scan.root, args.du)?;
+        return Ok(tree::report_errors(&scan.errors, args.quiet));
+    }
+
+    if args.html {
+        html::print(&scan, args)?;
+        return Ok(tree::report_errors(&scan.errors, args.quiet));
+    }
+
+    let printer = tree_printer(args, &scan.root, paths, use_color)?.with_lang_bars(lang_bars);
+    let Some(cache) = cache else {
+        tracing::info_span!("render").in_scope(|| printer.print(&scan.root))?;
+        return Ok(tree::report_errors(&scan.errors, args.quiet));
+    };
+
+    let mut output = Vec::new();
+    tracing::info_span!("render").in_scope(|| printer.write(&scan.root, &mut output))?;
+    io::stdout().lock().write_all(&output)?;
+    // A partial tree would hide the errors on later runs
+    if scan.errors.is_empty()
+        && let Err(err) = cache.store(&output)
+    {
+        tracing::warn!(error = %err, "failed to update the cache");
+    }
+
+    Ok(tree::report_errors(&scan.errors, args.quiet))
+}
+
+/// The tree printer for `root`, set up from every display flag, so each way
+/// of listing a tree shows the same columns and annotations.
+pub fn tree_printer(
+    args: &ListArgs,
+    root: &tree::Node,
+    paths: PathStyle,
+    use_color: bool,
+) -> Result<TreePrinter> {
+    // Quoted and NUL-separated output is meant for other programs: flat and
+    // uncolored
+    let quoting = args.shell_quote.is_some() || args.print0;
+    let flat = args.full_path || quoting || args.grid;
+    let blame_heat = if args.blame_heat && use_color && !quoting {
+        Some(git::last_commit_times(&root.path)?)
+    } else {
+        None
+    };
+    let git_owners = args.git_owner.map(|by| git::top_authors(root, by)).transpose()?;
+    let code_owners = if args.codeowners {
+        Some(codeowners::CodeOwners::find(&root.path)?.annotate(root))
+    } else {
+        None
+    };
+    let mounts = match args.scan.proc_mounts {
+        Some(mode) => Some(mounts::labels(root, &mounts::volatile()?, mode)),
+        None => None,
+    };
+    Ok(TreePrinter::new(use_color && !quoting, flat, args.du)
+        .with_width(term::width(args.width))
+        .with_grid(args.grid)
+        .with_glyphs(Glyphs::from_args(args))
+        .with_quote(args.shell_quote)
+        .with_print0(args.print0)
+        .with_highlight(args.highlight_size)
+        .with_depth(args.show_depth)
+        .with_paths(paths)
+        .with_root_path(args.show_root_path)
+        .with_collapsed_files(args.collapse_files)
+        .with_files_only(args.flat_files_only)
+        .with_counts(args.counts)
+        .with_media_info(args.media_info)
+        .with_doc_info(args.doc_info)
+        .with_on_disk(args.on_disk)
+        .with_hyperlinks(args.hyperlinks && !quoting && term::supports_hyperlinks())
+        .with_blame_heat(blame_heat)
+        .with_git_owners(git_owners)
+        .with_code_owners(code_owners)
+        .with_mounts(mounts)
+        .with_file_sizes(args.size)
+        .with_permissions(args.permissions)
+        .with_octal_perms(args.octal_perms)
+        .with_owner_columns(args.user, args.group)
+        .with_inode_columns(args.inodes, args.device)
+        .with_link_counts(args.nlink, args.flag_hardlinks)
+        .with_dates(args.date.then(|| args.timefmt.clone()))
+        .with_max_depth(args.overview.then_some(2))
+        .with_match_highlight(filter::NameHighlighter::new(
+            &args.scan.file_patterns,
+            &args.scan.regex_patterns,
+            args.scan.regex_engine,
+        )?))
+}
+
+/// Run the --exec/--exec-batch command over every entry of the scanned tree.
+/// Returns whether any command failed.
+fn run_exec(args: &ListArgs, root: &tree::Node) -> Result<bool> {
+    let paths: Vec<_> = root
+        .descendants()
+        .into_iter()
+        .map(|node| node.path.clone())
+        .collect();
+
+    let ok = if let Some(command) = &args.exec {
+        exec::run_each(&exec::CommandTemplate::parse(command)?, &paths, args.jobs())
+    } else if let Some(command) = &args.exec_batch {
+        exec::run_batch(&exec::CommandTemplate::parse(command)?, &paths)
+    } else {
+        true
+    };
+
+    Ok(!ok)
 }
@@ -0,0 +1,258 @@
+use ignore::DirEntry;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::cli::HashAlgorithm;
+use crate::errors::{ErrorKind, ScanError};
+use crate::hash;
+use crate::paths::PathStyle;
+use crate::resume::ResumeState;
+use crate::tree::{Node, format_size};
+
+/// Paths waiting for a worker, per worker: enough to keep them all busy
+/// without queueing the whole tree when the walk is far ahead of the disks.
+const QUEUE_PER_WORKER: usize = 16;
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+const BAR_WIDTH: usize = 20;
+
+type Digests = HashMap<PathBuf, io::Result<Vec<u8>>>;
+
+/// Hashes files on a pool of threads as the walk finds them, so reading their
+/// contents overlaps with the traversal instead of waiting for it.
+pub struct Hasher {
+    algorithm: HashAlgorithm,
+    queue: Option<SyncSender<(PathBuf, fs::Metadata)>>,
+    workers: Vec<JoinHandle<()>>,
+    digests: Arc<Mutex<Digests>>,
+    progress: Arc<Progress>,
+    reporter: Option<JoinHandle<()>>,
+    resume: Option<Arc<ResumeState>>,
+}
+
+#[derive(Default)]
+struct Progress {
+    queued: AtomicU64,
+    queued_bytes: AtomicU64,
+    hashed: AtomicU64,
+    hashed_bytes: AtomicU64,
+    done: AtomicBool,
+}
+
+impl Hasher {
+    /// Start `jobs` workers, and with `show_progress` a progress bar on stderr
+    /// until `finish`. With `resume`, files it has unchanged aren't read again
+    /// and every new digest is checkpointed to it.
+    pub fn start(
+        algorithm: HashAlgorithm,
+        jobs: usize,
+        show_progress: bool,
+        resume: Option<ResumeState>,
+    ) -> Self {
+        let jobs = jobs.max(1);
+        let (queue, paths) = mpsc::sync_channel(jobs * QUEUE_PER_WORKER);
+        let paths = Arc::new(Mutex::new(paths));
+        let digests = Arc::new(Mutex::new(HashMap::new()));
+        let progress = Arc::new(Progress::default());
+        let resume = resume.map(Arc::new);
+        let workers = (0..jobs)
+            .map(|_| {
+                let (paths, digests, progress) = (paths.clone(), digests.clone(), progress.clone());
+                let resume = resume.clone();
+                thread::spawn(move || {
+                    work(&paths, &digests, &progress, resume.as_deref(), algorithm)
+                })
+            })
+            .collect();
+        let reporter = show_progress.then(|| {
+            let progress = progress.clone();
+            thread::spawn(move || report(&progress))
+        });
+        Self {
+            algorithm,
+            queue: Some(queue),
+            workers,
+            digests,
+            progress,
+            reporter,
+            resume,
+        }
+    }
+
+    /// Hash `entry` if it is a regular file, waiting for room in the queue.
+    pub fn queue(&self, entry: &DirEntry) {
+        // Links are followed like `sha256sum` does; sockets and devices have
+        // nothing to hash
+        let Ok(metadata) = fs::metadata(entry.path()) else {
+            return;
+        };
+        if !metadata.is_file() {
+            return;
+        }
+        self.progress.queued.fetch_add(1, Ordering::Relaxed);
+        self.progress.queued_bytes.fetch_add(metadata.len(), Ordering::Relaxed);
+        let known = self.resume.as_ref().and_then(|state| state.lookup(entry.path(), &metadata));
+        if let Some(digest) = known {
+            self.digests.lock().unwrap().insert(entry.path().to_path_buf(), Ok(digest));
+            self.progress.hashed.fetch_add(1, Ordering::Relaxed);
+            self.progress.hashed_bytes.fetch_add(metadata.len(), Ordering::Relaxed);
+        } else if let Some(queue) = &self.queue {
+            let _ = queue.send((entry.path().to_path_buf(), metadata));
+        }
+    }
+
+    /// Wait for every queued file to be hashed and hand over the digests,
+    /// leaving only this run's files in the --resume state.
+    pub fn finish(mut self) -> anyhow::Result<Digests> {
+        self.stop();
+        if let Some(state) = &self.resume {
+            state.compact(self.algorithm)?;
+        }
+        Ok(std::mem::take(&mut *self.digests.lock().unwrap()))
+    }
+
+    fn stop(&mut self) {
+        // Workers run out of paths once the queue is closed
+        self.queue = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+        self.progress.done.store(true, Ordering::Relaxed);
+        if let Some(reporter) = self.reporter.take() {
+            reporter.thread().unpark();
+            let _ = reporter.join();
+        }
+    }
+}
+
+impl Drop for Hasher {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn work(
+    paths: &Mutex<Receiver<(PathBuf, fs::Metadata)>>,
+    digests: &Mutex<Digests>,
+    progress: &Progress,
+    resume: Option<&ResumeState>,
+    algorithm: HashAlgorithm,
+) {
+    loop {
+        // The lock is only held while waiting for the next path
+        let next = paths.lock().unwrap().recv();
+        let Ok((path, metadata)) = next else {
+            return;
+        };
+        let digest = hash::file_with(&path, algorithm);
+        if let (Some(state), Ok(digest)) = (resume, &digest) {
+            state.record(&path, &metadata, digest);
+        }
+        digests.lock().unwrap().insert(path, digest);
+        progress.hashed.fetch_add(1, Ordering::Relaxed);
+        progress.hashed_bytes.fetch_add(metadata.len(), Ordering::Relaxed);
+    }
+}
+
+/// Redraw `[#######-------------] 312/1043 files, 1.2G/4.0G` on stderr until
+/// hashing is done, then clear it. The totals grow while the walk goes on.
+fn report(progress: &Progress) {
+    let mut err = io::stderr().lock();
+    while !progress.done.load(Ordering::Relaxed) {
+        let hashed = progress.hashed.load(Ordering::Relaxed);
+        let queued = progress.queued.load(Ordering::Relaxed);
+        let bytes = progress.hashed_bytes.load(Ordering::Relaxed);
+        let total = progress.queued_bytes.load(Ordering::Relaxed);
+        let filled = match total {
+            0 => 0,
+            _ => (bytes as u128 * BAR_WIDTH as u128 / total as u128) as usize,
+        };
+        let _ = write!(
+            err,
+            "\r\x1b[K[{}{}] {}/{} files, {}/{}",
+            "#".repeat(filled),
+            "-".repeat(BAR_WIDTH - filled),
+            hashed,
+            queued,
+            format_size(bytes),
+            format_size(total)
+        );
+        let _ = err.flush();
+        thread::park_timeout(PROGRESS_INTERVAL);
+    }
+    let _ = write!(err, "\r\x1b[K");
+    let _ = err.flush();
+}
+
+/// Print a checksum line for every regular file below the root, in tree order,
+/// taking the digests `Hasher` already computed and hashing whatever it didn't
+/// get. Files that can't be read are left out and added to `errors`.
+pub fn print(
+    root: &Node,
+    algorithm: HashAlgorithm,
+    digests: &mut Digests,
+    paths: &PathStyle,
+    errors: &mut Vec<ScanError>,
+) -> io::Result<()> {
+    let mut out = BufWriter::new(io::stdout().lock());
+    write_lines(&mut out, root, algorithm, digests, paths, errors)?;
+    out.flush()
+}
+
+fn write_lines(
+    out: &mut impl Write,
+    node: &Node,
+    algorithm: HashAlgorithm,
+    digests: &mut Digests,
+    paths: &PathStyle,
+    errors: &mut Vec<ScanError>,
+) -> io::Result<()> {
+    for child in &node.children {
+        if child.is_dir {
+            write_lines(out, child, algorithm, digests, paths, errors)?;
+            continue;
+        }
+        // Links are followed like `sha256sum` does; sockets, devices and
+        // members of --descend-archives archives have nothing to hash
+        if !child.path.is_file() {
+            continue;
+        }
+        let digest = digests
+            .remove(&child.path)
+            .unwrap_or_else(|| hash::file_with(&child.path, algorithm));
+        match digest {
+            Ok(digest) => writeln!(out, "{}", line(&hash::hex(&digest), paths, child))?,
+            Err(err) => errors.push(ScanError {
+                path: Some(child.path.clone()),
+                kind: match err.kind() {
+                    io::ErrorKind::PermissionDenied => ErrorKind::PermissionDenied,
+                    io::ErrorKind::NotFound => ErrorKind::NotFound,
+                    _ => ErrorKind::Io,
+                },
+                message: format!("failed to hash: {}", err),
+            }),
+        }
+    }
+    Ok(())
+}
+
+/// `<hash>  <path>`, with `/` separators everywhere. Like coreutils, paths with
+/// a backslash or newline are escaped and the line is marked with a leading `\`.
+fn line(digest: &str, paths: &PathStyle, node: &Node) -> String {
+    let mut path = paths.apply(&node.path).to_string_lossy().into_owned();
+    if cfg!(windows) {
+        path = path.replace('\\', "/");
+    }
+    if path.contains(['\\', '\n']) {
+        let escaped = path.replace('\\', "\\\\").replace('\n', "\\n");
+        format!("\\{}  {}", digest, escaped)
+    } else {
+        format!("{}  {}", digest, path)
+    }
+}
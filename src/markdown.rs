@@ -0,0 +1,55 @@
+use std::io::{self, BufWriter, Write};
+
+use crate::cli::MarkdownStyle;
+use crate::printer::TreePrinter;
+use crate::tree::{Node, format_size};
+
+/// Print the tree as Markdown, ready to paste into a README or PR description.
+pub fn print(root: &Node, with_size: bool, style: MarkdownStyle) -> io::Result<()> {
+    let mut out = BufWriter::new(io::stdout().lock());
+    match style {
+        MarkdownStyle::List => write_list(&mut out, root, with_size, 0)?,
+        MarkdownStyle::Fenced => {
+            writeln!(out, "```text")?;
+            TreePrinter::new(false, false, with_size).write(root, &mut out)?;
+            writeln!(out, "```")?;
+        }
+    }
+    out.flush()
+}
+
+/// One bullet per entry, indented two spaces per level. Directories get a
+/// trailing `/` so they stand out without relying on rendering.
+fn write_list(out: &mut impl Write, node: &Node, with_size: bool, depth: usize) -> io::Result<()> {
+    let mut name = node.name();
+    if node.is_dir {
+        name.push('/');
+    }
+    write!(out, "{}- {}", "  ".repeat(depth), code_span(&name))?;
+    if with_size {
+        write!(out, " ({})", format_size(node.size))?;
+    }
+    writeln!(out)?;
+
+    for child in &node.children {
+        write_list(out, child, with_size, depth + 1)?;
+    }
+    Ok(())
+}
+
+/// Inline code, so names like `__init__.py` aren't taken for emphasis. The
+/// fence is one backtick longer than any run inside the name.
+fn code_span(text: &str) -> String {
+    let longest_run = text
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    let fence = "`".repeat(longest_run + 1);
+    // Padding keeps a leading or trailing backtick from joining the fence
+    if longest_run > 0 {
+        format!("{} {} {}", fence, text, fence)
+    } else {
+        format!("{}{}{}", fence, text, fence)
+    }
+}
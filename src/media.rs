@@ -0,0 +1,527 @@
+use std::fmt;
+use std::path::Path;
+use std::time::Duration;
+
+/// What `--media-info` shows for an image, video or audio file.
+#[derive(Default)]
+pub struct MediaInfo {
+    /// Width and height in pixels
+    pub dimensions: Option<(u32, u32)>,
+    pub duration: Option<Duration>,
+}
+
+impl fmt::Display for MediaInfo {
+    /// `1920x1080`, `3:25`, or both as `1920x1080, 0:42`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some((width, height)) = self.dimensions {
+            write!(f, "{}x{}", width, height)?;
+        }
+        if let Some(duration) = self.duration {
+            if self.dimensions.is_some() {
+                f.write_str(", ")?;
+            }
+            let secs = duration.as_secs();
+            if secs >= 3600 {
+                write!(f, "{}:{:02}:{:02}", secs / 3600, secs % 3600 / 60, secs % 60)?;
+            } else {
+                write!(f, "{}:{:02}", secs / 60, secs % 60)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A minimum width and height, as given to `--min-resolution`.
+#[derive(Clone, Copy)]
+pub struct Resolution {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Resolution {
+    /// Whether `info` is at least this wide and this high.
+    pub fn admits(&self, info: Option<&MediaInfo>) -> bool {
+        info.and_then(|info| info.dimensions)
+            .is_some_and(|(width, height)| width >= self.width && height >= self.height)
+    }
+}
+
+/// Parse a resolution like `1920x1080`.
+pub fn parse_resolution(text: &str) -> Result<Resolution, String> {
+    let invalid = || format!("invalid resolution '{}', expected WIDTHxHEIGHT", text);
+    let (width, height) = text.trim().split_once(['x', 'X']).ok_or_else(invalid)?;
+    Ok(Resolution {
+        width: width.trim().parse().map_err(|_| invalid())?,
+        height: height.trim().parse().map_err(|_| invalid())?,
+    })
+}
+
+/// A calendar day, as EXIF records capture times (without a time zone).
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+}
+
+/// The days `--exif-date-between` admits, both ends included; a missing end
+/// is open.
+#[derive(Clone, Copy)]
+pub struct DateRange {
+    pub from: Option<Date>,
+    pub to: Option<Date>,
+}
+
+impl DateRange {
+    /// Whether `date` falls in the range; files without one never do.
+    pub fn admits(&self, date: Option<Date>) -> bool {
+        date.is_some_and(|date| {
+            self.from.is_none_or(|from| date >= from) && self.to.is_none_or(|to| date <= to)
+        })
+    }
+}
+
+/// Parse a range like `2023-01..2023-06`, `2023-03-14..` or `..2022`. Each
+/// end is a year, a month or a day, covering all of it, so `2023-01..2023-06`
+/// runs from January 1st to June 30th; a lone `2023-05` is all of May.
+pub fn parse_date_range(text: &str) -> Result<DateRange, String> {
+    let invalid = || {
+        format!("invalid date range '{}', expected FROM..TO, e.g. 2023-01..2023-06", text)
+    };
+    let (from, to) = text.trim().split_once("..").unwrap_or((text, text));
+    let bound = |part: &str, last: bool| -> Result<Option<Date>, String> {
+        let part = part.trim();
+        if part.is_empty() {
+            return Ok(None);
+        }
+        let mut fields = part.split('-').map(|field| field.parse::<u16>().ok());
+        let year = fields.next().flatten().ok_or_else(invalid)?;
+        let month = match fields.next() {
+            Some(month) => month.filter(|m| (1..=12).contains(m)).ok_or_else(invalid)?,
+            None if last => 12,
+            None => 1,
+        };
+        let day = match fields.next() {
+            Some(day) => day.filter(|d| (1..=31).contains(d)).ok_or_else(invalid)?,
+            // Later than any real day of the month
+            None if last => 31,
+            None => 1,
+        };
+        if fields.next().is_some() {
+            return Err(invalid());
+        }
+        Ok(Some(Date {
+            year,
+            month: month as u8,
+            day: day as u8,
+        }))
+    };
+    let range = DateRange {
+        from: bound(from, false)?,
+        to: bound(to, true)?,
+    };
+    if range.from.is_none() && range.to.is_none() {
+        return Err(invalid());
+    }
+    Ok(range)
+}
+
+/// Fail unless treee was built with the `media` feature.
+pub fn ensure_enabled() -> anyhow::Result<()> {
+    if cfg!(feature = "media") {
+        Ok(())
+    } else {
+        anyhow::bail!("Media support is not enabled; rebuild treee with `--features media`")
+    }
+}
+
+/// Read the dimensions and duration from the headers of the file at `path`,
+/// or `None` when it isn't a format treee knows.
+#[cfg(feature = "media")]
+pub fn probe(path: &Path) -> Option<MediaInfo> {
+    formats::probe(path).ok().flatten()
+}
+
+#[cfg(not(feature = "media"))]
+pub fn probe(_path: &Path) -> Option<MediaInfo> {
+    None
+}
+
+/// The day a JPEG or TIFF-based (most camera raw) photo was taken, from its
+/// EXIF `DateTimeOriginal`, falling back to the time it was digitized or last
+/// edited.
+#[cfg(feature = "media")]
+pub fn capture_date(path: &Path) -> Option<Date> {
+    formats::capture_date(path).ok().flatten()
+}
+
+#[cfg(not(feature = "media"))]
+pub fn capture_date(_path: &Path) -> Option<Date> {
+    None
+}
+
+/// Just enough of each format to find its size: PNG, GIF, BMP, WebP and JPEG
+/// images, WAV and FLAC audio, and MP4/MOV/M4A video and audio; plus the EXIF
+/// dates in JPEG and TIFF files.
+#[cfg(feature = "media")]
+mod formats {
+    use std::fs::File;
+    use std::io::{self, BufReader, Read, Seek, SeekFrom};
+    use std::path::Path;
+    use std::time::Duration;
+
+    use super::{Date, MediaInfo};
+
+    /// How much of a TIFF file is read looking for its EXIF tags, which
+    /// cameras write up front.
+    const TIFF_HEAD: u64 = 256 * 1024;
+
+    pub fn probe(path: &Path) -> io::Result<Option<MediaInfo>> {
+        let mut file = BufReader::new(File::open(path)?);
+        let mut head = [0u8; 32];
+        let len = read_up_to(&mut file, &mut head)?;
+        let head = &head[..len];
+        file.seek(SeekFrom::Start(0))?;
+
+        let dimensions = |width, height| {
+            Some(MediaInfo {
+                dimensions: Some((width, height)),
+                duration: None,
+            })
+        };
+        Ok(match head {
+            [0x89, b'P', b'N', b'G', _, _, _, _, _, _, _, _, b'I', b'H', b'D', b'R', rest @ ..]
+                if rest.len() >= 8 =>
+            {
+                dimensions(be32(&rest[0..]), be32(&rest[4..]))
+            }
+            [b'G', b'I', b'F', b'8', _, _, w0, w1, h0, h1, ..] => dimensions(
+                u16::from_le_bytes([*w0, *w1]).into(),
+                u16::from_le_bytes([*h0, *h1]).into(),
+            ),
+            [b'B', b'M', ..] if head.len() >= 26 => dimensions(
+                le32(&head[18..]),
+                (le32(&head[22..]) as i32).unsigned_abs(),
+            ),
+            [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => webp(head),
+            [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'A', b'V', b'E', ..] => wav(&mut file)?,
+            [0xFF, 0xD8, ..] => jpeg(&mut file)?,
+            [b'f', b'L', b'a', b'C', ..] => flac(&mut file)?,
+            [_, _, _, _, b'f', b't', b'y', b'p', ..] => mp4(&mut file)?,
+            _ => None,
+        })
+    }
+
+    fn webp(head: &[u8]) -> Option<MediaInfo> {
+        let (width, height) = match head.get(12..16)? {
+            b"VP8 " if head.len() >= 30 => (
+                u32::from(u16::from_le_bytes([head[26], head[27]]) & 0x3FFF),
+                u32::from(u16::from_le_bytes([head[28], head[29]]) & 0x3FFF),
+            ),
+            b"VP8L" if head.len() >= 25 => {
+                let bits = le32(&head[21..]);
+                ((bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1)
+            }
+            b"VP8X" if head.len() >= 30 => {
+                let le24 = |bytes: &[u8]| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0]);
+                (le24(&head[24..]) + 1, le24(&head[27..]) + 1)
+            }
+            _ => return None,
+        };
+        Some(MediaInfo {
+            dimensions: Some((width, height)),
+            duration: None,
+        })
+    }
+
+    /// The first start-of-frame marker holds the size; everything before it
+    /// (EXIF, thumbnails, tables) is skipped by its length.
+    fn jpeg(file: &mut (impl Read + Seek)) -> io::Result<Option<MediaInfo>> {
+        file.seek(SeekFrom::Start(2))?;
+        loop {
+            let mut marker = [0u8; 4];
+            file.read_exact(&mut marker)?;
+            if marker[0] != 0xFF {
+                return Ok(None);
+            }
+            let length = u16::from_be_bytes([marker[2], marker[3]]);
+            // SOF0 to SOF15, except DHT, JPG and DAC which share the range
+            let is_frame =
+                matches!(marker[1], 0xC0..=0xCF) && !matches!(marker[1], 0xC4 | 0xC8 | 0xCC);
+            if is_frame {
+                let mut frame = [0u8; 5];
+                file.read_exact(&mut frame)?;
+                let height = u16::from_be_bytes([frame[1], frame[2]]);
+                let width = u16::from_be_bytes([frame[3], frame[4]]);
+                return Ok(Some(MediaInfo {
+                    dimensions: Some((width.into(), height.into())),
+                    duration: None,
+                }));
+            }
+            file.seek(SeekFrom::Current(i64::from(length) - 2))?;
+        }
+    }
+
+    pub fn capture_date(path: &Path) -> io::Result<Option<Date>> {
+        let mut file = BufReader::new(File::open(path)?);
+        let mut magic = [0u8; 4];
+        file.read_exact(&mut magic)?;
+        let tiff = match magic {
+            [0xFF, 0xD8, ..] => match exif_segment(&mut file)? {
+                Some(tiff) => tiff,
+                None => return Ok(None),
+            },
+            [b'I', b'I', 42, 0] | [b'M', b'M', 0, 42] => {
+                let mut tiff = magic.to_vec();
+                file.take(TIFF_HEAD).read_to_end(&mut tiff)?;
+                tiff
+            }
+            _ => return Ok(None),
+        };
+        Ok(exif_date(&tiff))
+    }
+
+    /// The TIFF structure inside a JPEG's `Exif` APP1 segment, which comes
+    /// before the image data.
+    fn exif_segment(file: &mut (impl Read + Seek)) -> io::Result<Option<Vec<u8>>> {
+        file.seek(SeekFrom::Start(2))?;
+        loop {
+            let mut marker = [0u8; 4];
+            file.read_exact(&mut marker)?;
+            // Start of scan: no more metadata
+            if marker[0] != 0xFF || marker[1] == 0xDA {
+                return Ok(None);
+            }
+            let length = u16::from_be_bytes([marker[2], marker[3]]);
+            if marker[1] == 0xE1 && length > 8 {
+                let mut data = vec![0; usize::from(length) - 2];
+                file.read_exact(&mut data)?;
+                if data.starts_with(b"Exif\0\0") {
+                    return Ok(Some(data.split_off(6)));
+                }
+                continue;
+            }
+            file.seek(SeekFrom::Current(i64::from(length) - 2))?;
+        }
+    }
+
+    /// `DateTimeOriginal` or `DateTimeDigitized` from the EXIF directory, or
+    /// `DateTime` from the first one, as `YYYY:MM:DD HH:MM:SS`.
+    fn exif_date(tiff: &[u8]) -> Option<Date> {
+        const DATE_TIME: u16 = 0x0132;
+        const EXIF_DIRECTORY: u16 = 0x8769;
+        const DATE_TIME_ORIGINAL: u16 = 0x9003;
+        const DATE_TIME_DIGITIZED: u16 = 0x9004;
+
+        let big_endian = tiff.get(..2)? == b"MM";
+        let u16_at = |at: usize| {
+            let bytes = [*tiff.get(at)?, *tiff.get(at + 1)?];
+            Some(if big_endian { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) })
+        };
+        let u32_at = |at: usize| {
+            let bytes = tiff.get(at..at + 4)?.try_into().ok()?;
+            Some(if big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) })
+        };
+        // The 12-byte entry for `tag` in the directory at `offset`
+        let find = |offset: usize, tag: u16| {
+            let count = usize::from(u16_at(offset)?);
+            (0..count)
+                .map(|i| offset + 2 + i * 12)
+                .find(|&entry| u16_at(entry) == Some(tag))
+        };
+        let date = |entry: usize| {
+            let (len, value) = (u32_at(entry + 4)? as usize, u32_at(entry + 8)? as usize);
+            // Values of up to four bytes are stored in the entry itself
+            let at = if len <= 4 { entry + 8 } else { value };
+            parse_date(tiff.get(at..at + len.min(10))?)
+        };
+
+        let first = u32_at(4)? as usize;
+        let exif = find(first, EXIF_DIRECTORY).and_then(|entry| u32_at(entry + 8));
+        exif.and_then(|exif| {
+            let exif = exif as usize;
+            [DATE_TIME_ORIGINAL, DATE_TIME_DIGITIZED]
+                .into_iter()
+                .find_map(|tag| date(find(exif, tag)?))
+        })
+        .or_else(|| date(find(first, DATE_TIME)?))
+    }
+
+    /// `YYYY:MM:DD`; cameras without a clock write zeros or blanks.
+    fn parse_date(text: &[u8]) -> Option<Date> {
+        let text = std::str::from_utf8(text).ok()?;
+        let mut fields = text.splitn(3, ':').map(|field| field.trim().parse::<u16>().ok());
+        let (year, month, day) = (fields.next()??, fields.next()??, fields.next()??);
+        if year == 0 || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+            return None;
+        }
+        Some(Date {
+            year,
+            month: month as u8,
+            day: day as u8,
+        })
+    }
+
+    /// The duration is the size of the `data` chunk over the byte rate in `fmt `.
+    fn wav(file: &mut (impl Read + Seek)) -> io::Result<Option<MediaInfo>> {
+        file.seek(SeekFrom::Start(12))?;
+        let mut byte_rate = None;
+        loop {
+            let mut header = [0u8; 8];
+            file.read_exact(&mut header)?;
+            let size = le32(&header[4..]);
+            match &header[..4] {
+                b"fmt " => {
+                    let mut format = [0u8; 12];
+                    file.read_exact(&mut format)?;
+                    byte_rate = Some(le32(&format[8..]));
+                    file.seek(SeekFrom::Current(i64::from(size) - 12))?;
+                }
+                b"data" => {
+                    let Some(rate) = byte_rate.filter(|&rate| rate > 0) else {
+                        return Ok(None);
+                    };
+                    let seconds = f64::from(size) / f64::from(rate);
+                    return Ok(Some(MediaInfo {
+                        dimensions: None,
+                        duration: Some(Duration::from_secs_f64(seconds)),
+                    }));
+                }
+                // Chunks are padded to an even size
+                _ => {
+                    let Some(padded) = size.checked_add(size % 2) else {
+                        return Ok(None);
+                    };
+                    file.seek(SeekFrom::Current(i64::from(padded)))?;
+                }
+            }
+        }
+    }
+
+    /// STREAMINFO, always the first metadata block, has the sample rate and count.
+    fn flac(file: &mut (impl Read + Seek)) -> io::Result<Option<MediaInfo>> {
+        let mut info = [0u8; 26];
+        file.read_exact(&mut info)?;
+        let fields = &info[18..];
+        // 20 bits of sample rate, then channels, bit depth and 36 bits of sample count
+        let sample_rate = (u32::from(fields[0]) << 12)
+            | (u32::from(fields[1]) << 4)
+            | (u32::from(fields[2]) >> 4);
+        let samples = (u64::from(fields[3] & 0x0F) << 32) | u64::from(be32(&fields[4..]));
+        if sample_rate == 0 || samples == 0 {
+            return Ok(None);
+        }
+        Ok(Some(MediaInfo {
+            dimensions: None,
+            duration: Some(Duration::from_secs_f64(samples as f64 / f64::from(sample_rate))),
+        }))
+    }
+
+    /// The duration comes from `moov/mvhd` and the size from the first video
+    /// track's `moov/trak/tkhd`; `moov` may well sit at the end of the file.
+    fn mp4(file: &mut (impl Read + Seek)) -> io::Result<Option<MediaInfo>> {
+        let end = file.seek(SeekFrom::End(0))?;
+        let Some((moov, moov_end)) = find_box(file, 0, end, b"moov")? else {
+            return Ok(None);
+        };
+
+        let mut info = MediaInfo::default();
+        if let Some((mvhd, _)) = find_box(file, moov, moov_end, b"mvhd")? {
+            let mut header = [0u8; 32];
+            file.seek(SeekFrom::Start(mvhd))?;
+            file.read_exact(&mut header)?;
+            let (timescale, duration) = if header[0] == 1 {
+                (be32(&header[20..]), be64(&header[24..]))
+            } else {
+                (be32(&header[12..]), u64::from(be32(&header[16..])))
+            };
+            if timescale > 0 {
+                let seconds = duration as f64 / f64::from(timescale);
+                // Past what a Duration holds for a timescale of 1
+                info.duration = Duration::try_from_secs_f64(seconds).ok();
+            }
+        }
+
+        let mut start = moov;
+        while let Some((trak, trak_end)) = find_box(file, start, moov_end, b"trak")? {
+            start = trak_end;
+            let Some((tkhd, _)) = find_box(file, trak, trak_end, b"tkhd")? else {
+                continue;
+            };
+            let mut header = [0u8; 96];
+            file.seek(SeekFrom::Start(tkhd))?;
+            let len = read_up_to(file, &mut header)?;
+            // 16.16 fixed point; audio tracks have no size
+            let at = if header[0] == 1 { 88 } else { 76 };
+            if len < at + 8 {
+                continue;
+            }
+            let (width, height) = (be32(&header[at..]) >> 16, be32(&header[at + 4..]) >> 16);
+            if width > 0 && height > 0 {
+                info.dimensions = Some((width, height));
+                break;
+            }
+        }
+
+        Ok((info.dimensions.is_some() || info.duration.is_some()).then_some(info))
+    }
+
+    /// The first box of type `kind` between `start` and `end`, as the range of
+    /// its contents.
+    fn find_box(
+        file: &mut (impl Read + Seek),
+        start: u64,
+        end: u64,
+        kind: &[u8; 4],
+    ) -> io::Result<Option<(u64, u64)>> {
+        let mut at = start;
+        // Sizes come from the file, so a bogus one stops the search
+        while at.checked_add(8).is_some_and(|header_end| header_end <= end) {
+            let mut header = [0u8; 16];
+            file.seek(SeekFrom::Start(at))?;
+            file.read_exact(&mut header[..8])?;
+            let (size, header_len) = match be32(&header) {
+                0 => (end - at, 8),
+                1 => {
+                    file.read_exact(&mut header[8..])?;
+                    (be64(&header[8..]), 16)
+                }
+                size => (u64::from(size), 8),
+            };
+            if size < header_len {
+                return Ok(None);
+            }
+            let Some(box_end) = at.checked_add(size) else {
+                return Ok(None);
+            };
+            if &header[4..8] == kind {
+                return Ok(Some((at + header_len, box_end.min(end))));
+            }
+            at = box_end;
+        }
+        Ok(None)
+    }
+
+    fn read_up_to(file: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+        let mut len = 0;
+        while len < buf.len() {
+            match file.read(&mut buf[len..])? {
+                0 => break,
+                n => len += n,
+            }
+        }
+        Ok(len)
+    }
+
+    fn be32(bytes: &[u8]) -> u32 {
+        u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    }
+
+    fn be64(bytes: &[u8]) -> u64 {
+        (u64::from(be32(bytes)) << 32) | u64::from(be32(&bytes[4..]))
+    }
+
+    fn le32(bytes: &[u8]) -> u32 {
+        u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    }
+}
@@ -0,0 +1,63 @@
+use std::io::{self, BufWriter, Write};
+
+use crate::cli::MermaidDirection;
+use crate::tree::{Node, format_size};
+
+/// Print the tree as a Mermaid `flowchart`, with directories as rectangles and
+/// files as rounded boxes.
+pub fn print(root: &Node, with_size: bool, direction: MermaidDirection) -> io::Result<()> {
+    let mut out = BufWriter::new(io::stdout().lock());
+    let direction = match direction {
+        MermaidDirection::Td => "TD",
+        MermaidDirection::Lr => "LR",
+    };
+    writeln!(out, "flowchart {}", direction)?;
+
+    let mut next_id = 0;
+    write_node(&mut out, root, with_size, &mut next_id)?;
+    out.flush()
+}
+
+/// Write `node` and its subtree, returning the id given to `node`. Ids are
+/// numbered in tree order, so the output is stable across runs.
+fn write_node(
+    out: &mut impl Write,
+    node: &Node,
+    with_size: bool,
+    next_id: &mut usize,
+) -> io::Result<usize> {
+    let id = *next_id;
+    *next_id += 1;
+
+    let mut label = escape(&node.name());
+    if node.is_dir {
+        label.push('/');
+    }
+    if with_size {
+        label = format!("{}<br>{}", label, format_size(node.size));
+    }
+    let (open, close) = if node.is_dir { ("[", "]") } else { ("(", ")") };
+    writeln!(out, "    n{}{}\"{}\"{}", id, open, label, close)?;
+
+    for child in &node.children {
+        let child_id = write_node(out, child, with_size, next_id)?;
+        writeln!(out, "    n{} --> n{}", id, child_id)?;
+    }
+    Ok(id)
+}
+
+/// Mermaid labels take HTML, and quotes end the label, so both are written
+/// as entity codes.
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("#quot;"),
+            '<' => out.push_str("#lt;"),
+            '>' => out.push_str("#gt;"),
+            '#' => out.push_str("#35;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
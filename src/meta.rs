@@ -0,0 +1,423 @@
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+
+use crate::cli::Field;
+use crate::time;
+
+/// Optional metadata attached to entries in machine output. Only the fields
+/// asked for are read, so leaving out e.g. `mtime` spares a stat per entry.
+#[derive(Serialize, Default)]
+pub struct Fields {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mtime: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link_target: Option<String>,
+    /// Whether the link target exists, so dangling links can be told apart
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link_target_exists: Option<bool>,
+}
+
+impl Fields {
+    /// Read `fields` for the entry at `path`. The size and link target are the
+    /// caller's, who usually has them at hand already (and directories report
+    /// the total of their contents); `size` is only called when asked for. A
+    /// known `mtime` (e.g. from --dir-mtime newest) is used instead of the entry's.
+    pub fn read(
+        path: &Path,
+        fields: &[Field],
+        size: impl FnOnce() -> u64,
+        link_target: Option<&Path>,
+        mtime: Option<SystemTime>,
+    ) -> Self {
+        let mut out = Fields {
+            size: fields.contains(&Field::Size).then(size),
+            ..Default::default()
+        };
+
+        if fields.contains(&Field::Target)
+            && let Some(target) = link_target
+        {
+            out.link_target = Some(target.to_string_lossy().into_owned());
+            // `exists` follows the link, resolving relative targets from its directory
+            out.link_target_exists = Some(path.exists());
+        }
+
+        let wants_stat = fields
+            .iter()
+            .any(|field| matches!(field, Field::Mtime | Field::Mode | Field::Owner));
+        if !wants_stat {
+            return out;
+        }
+        // Describe links themselves, like `ls -l`
+        let Ok(metadata) = fs::symlink_metadata(path) else {
+            return out;
+        };
+
+        if fields.contains(&Field::Mtime) {
+            out.mtime = mtime.or_else(|| time::of(path, &metadata)).map(time::iso8601);
+        }
+        if fields.contains(&Field::Mode) {
+            out.mode = mode(&metadata);
+        }
+        if fields.contains(&Field::Owner) {
+            out.owner = owner(&metadata);
+        }
+        out
+    }
+}
+
+#[cfg(unix)]
+fn mode(metadata: &fs::Metadata) -> Option<String> {
+    use std::os::unix::fs::PermissionsExt;
+    Some(format!("{:04o}", metadata.permissions().mode() & 0o7777))
+}
+
+#[cfg(not(unix))]
+fn mode(_metadata: &fs::Metadata) -> Option<String> {
+    None
+}
+
+/// The type and permissions of the entry at `path` (not following links), as
+/// `ls -l` writes them: `drwxr-xr-x`. Windows has no permission bits, so there
+/// the type is followed by the read-only, hidden, system and archive
+/// attributes instead: `-R--A`. Question marks when it can't be read.
+pub fn permissions(path: &Path) -> String {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) => permissions_string(&metadata),
+        Err(_) => "?".repeat(if cfg!(windows) { 5 } else { 10 }),
+    }
+}
+
+/// The permission bits of the entry at `path` (not following links) in octal,
+/// like `0755`. Where there are none, the same attributes as `permissions`.
+pub fn octal_permissions(path: &Path) -> String {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) => mode(&metadata).unwrap_or_else(|| permissions_string(&metadata)),
+        Err(_) => "?".repeat(if cfg!(windows) { 5 } else { 4 }),
+    }
+}
+
+#[cfg(unix)]
+fn permissions_string(metadata: &fs::Metadata) -> String {
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+
+    let file_type = metadata.file_type();
+    let kind = if file_type.is_dir() {
+        'd'
+    } else if file_type.is_symlink() {
+        'l'
+    } else if file_type.is_char_device() {
+        'c'
+    } else if file_type.is_block_device() {
+        'b'
+    } else if file_type.is_fifo() {
+        'p'
+    } else if file_type.is_socket() {
+        's'
+    } else {
+        '-'
+    };
+
+    let mode = metadata.permissions().mode();
+    let mut text = String::from(kind);
+    // Owner, group, others; setuid, setgid and sticky take the place of `x`
+    for (shift, special, letter) in [(6, 0o4000, 's'), (3, 0o2000, 's'), (0, 0o1000, 't')] {
+        let bits = mode >> shift;
+        text.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        text.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        text.push(match (mode & special != 0, bits & 0o1 != 0) {
+            (true, true) => letter,
+            (true, false) => letter.to_ascii_uppercase(),
+            (false, true) => 'x',
+            (false, false) => '-',
+        });
+    }
+    text
+}
+
+#[cfg(windows)]
+fn permissions_string(metadata: &fs::Metadata) -> String {
+    use std::os::windows::fs::MetadataExt;
+    use windows_sys::Win32::Storage::FileSystem::{
+        FILE_ATTRIBUTE_ARCHIVE, FILE_ATTRIBUTE_HIDDEN, FILE_ATTRIBUTE_READONLY,
+        FILE_ATTRIBUTE_SYSTEM,
+    };
+
+    let file_type = metadata.file_type();
+    let kind = if file_type.is_dir() {
+        'd'
+    } else if file_type.is_symlink() {
+        'l'
+    } else {
+        '-'
+    };
+
+    let attributes = metadata.file_attributes();
+    let mut text = String::from(kind);
+    for (attribute, letter) in [
+        (FILE_ATTRIBUTE_READONLY, 'R'),
+        (FILE_ATTRIBUTE_HIDDEN, 'H'),
+        (FILE_ATTRIBUTE_SYSTEM, 'S'),
+        (FILE_ATTRIBUTE_ARCHIVE, 'A'),
+    ] {
+        text.push(if attributes & attribute != 0 { letter } else { '-' });
+    }
+    text
+}
+
+#[cfg(unix)]
+fn owner(metadata: &fs::Metadata) -> Option<String> {
+    use std::os::unix::fs::MetadataExt;
+    Some(user_name(metadata.uid()))
+}
+
+#[cfg(not(unix))]
+fn owner(_metadata: &fs::Metadata) -> Option<String> {
+    None
+}
+
+/// The user and group owning the entry at `path` (not following links), by
+/// name: from the user database on Unix, falling back to the uid and gid, and
+/// as `DOMAIN\name` on Windows, falling back to the SID. `None` when the
+/// entry can't be read.
+#[cfg(unix)]
+pub fn user_and_group(path: &Path) -> Option<(String, String)> {
+    use std::os::unix::fs::MetadataExt;
+    let metadata = fs::symlink_metadata(path).ok()?;
+    Some((user_name(metadata.uid()), group_name(metadata.gid())))
+}
+
+#[cfg(windows)]
+pub fn user_and_group(path: &Path) -> Option<(String, String)> {
+    use std::os::windows::ffi::OsStrExt;
+    use std::ptr::null_mut;
+    use windows_sys::Win32::Foundation::LocalFree;
+    use windows_sys::Win32::Security::Authorization::{GetNamedSecurityInfoW, SE_FILE_OBJECT};
+    use windows_sys::Win32::Security::{GROUP_SECURITY_INFORMATION, OWNER_SECURITY_INFORMATION};
+
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    let (mut owner, mut group, mut descriptor) = (null_mut(), null_mut(), null_mut());
+    // SAFETY: `wide` is NUL-terminated, and the SIDs point into `descriptor`,
+    // which is only freed once they have been looked up
+    unsafe {
+        let status = GetNamedSecurityInfoW(
+            wide.as_ptr(),
+            SE_FILE_OBJECT,
+            OWNER_SECURITY_INFORMATION | GROUP_SECURITY_INFORMATION,
+            &mut owner,
+            &mut group,
+            null_mut(),
+            null_mut(),
+            &mut descriptor,
+        );
+        if status != 0 {
+            return None;
+        }
+        let names = (account_name(owner), account_name(group));
+        LocalFree(descriptor);
+        Some(names)
+    }
+}
+
+/// The inode and device numbers of the entry at `path` (not following links),
+/// like `tree --inodes --device`; the file index and volume serial number on
+/// Windows. Hard links share both.
+#[cfg(unix)]
+pub fn inode_and_device(path: &Path) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    let metadata = fs::symlink_metadata(path).ok()?;
+    Some((metadata.ino(), metadata.dev()))
+}
+
+#[cfg(windows)]
+pub fn inode_and_device(path: &Path) -> Option<(u64, u64)> {
+    let info = file_information(path)?;
+    let index = (u64::from(info.nFileIndexHigh) << 32) | u64::from(info.nFileIndexLow);
+    Some((index, u64::from(info.dwVolumeSerialNumber)))
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn inode_and_device(_path: &Path) -> Option<(u64, u64)> {
+    None
+}
+
+/// How many names (hard links) the entry at `path` has, not following links.
+#[cfg(unix)]
+pub fn link_count(path: &Path) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(fs::symlink_metadata(path).ok()?.nlink())
+}
+
+#[cfg(windows)]
+pub fn link_count(path: &Path) -> Option<u64> {
+    Some(u64::from(file_information(path)?.nNumberOfLinks))
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn link_count(_path: &Path) -> Option<u64> {
+    None
+}
+
+/// What `GetFileInformationByHandle` knows about the entry at `path` itself.
+#[cfg(windows)]
+fn file_information(
+    path: &Path,
+) -> Option<windows_sys::Win32::Storage::FileSystem::BY_HANDLE_FILE_INFORMATION> {
+    use std::os::windows::fs::OpenOptionsExt;
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::Storage::FileSystem::{
+        FILE_FLAG_BACKUP_SEMANTICS, FILE_FLAG_OPEN_REPARSE_POINT, FILE_READ_ATTRIBUTES,
+        GetFileInformationByHandle,
+    };
+
+    // Directories only open with backup semantics; links are described themselves
+    let file = fs::OpenOptions::new()
+        .access_mode(FILE_READ_ATTRIBUTES)
+        .custom_flags(FILE_FLAG_BACKUP_SEMANTICS | FILE_FLAG_OPEN_REPARSE_POINT)
+        .open(path)
+        .ok()?;
+    // SAFETY: `info` is a plain struct that is filled in on success
+    let mut info = unsafe { std::mem::zeroed() };
+    if unsafe { GetFileInformationByHandle(file.as_raw_handle(), &mut info) } == 0 {
+        return None;
+    }
+    Some(info)
+}
+
+/// `DOMAIN\name` for the account behind `sid`, or the SID itself (`S-1-5-…`)
+/// for accounts that no longer exist or can't be looked up.
+///
+/// # Safety
+///
+/// `sid` must point to a valid SID.
+#[cfg(windows)]
+unsafe fn account_name(sid: windows_sys::Win32::Security::PSID) -> String {
+    use windows_sys::Win32::Foundation::LocalFree;
+    use windows_sys::Win32::Security::Authorization::ConvertSidToStringSidW;
+    use windows_sys::Win32::Security::LookupAccountSidW;
+
+    let mut name = [0u16; 256];
+    let mut domain = [0u16; 256];
+    let (mut name_len, mut domain_len) = (name.len() as u32, domain.len() as u32);
+    let mut kind = 0;
+    // SAFETY: the lengths are those of the buffers, which the call fills in
+    let found = unsafe {
+        LookupAccountSidW(
+            std::ptr::null(),
+            sid,
+            name.as_mut_ptr(),
+            &mut name_len,
+            domain.as_mut_ptr(),
+            &mut domain_len,
+            &mut kind,
+        )
+    } != 0;
+    if found {
+        let name = String::from_utf16_lossy(&name[..name_len as usize]);
+        return match domain_len {
+            0 => name,
+            len => format!("{}\\{}", String::from_utf16_lossy(&domain[..len as usize]), name),
+        };
+    }
+
+    let mut text = std::ptr::null_mut();
+    // SAFETY: on success `text` is a NUL-terminated string to free with LocalFree
+    unsafe {
+        if ConvertSidToStringSidW(sid, &mut text) == 0 {
+            return "?".to_string();
+        }
+        let len = (0..).take_while(|&i| *text.add(i) != 0).count();
+        let sid = String::from_utf16_lossy(std::slice::from_raw_parts(text, len));
+        LocalFree(text.cast());
+        sid
+    }
+}
+
+/// The login name for `uid`, or the number itself when it has none. Lookups
+/// are cached, since a tree usually has very few distinct owners.
+#[cfg(unix)]
+fn user_name(uid: u32) -> String {
+    use std::collections::HashMap;
+    use std::sync::{Mutex, OnceLock};
+
+    static CACHE: OnceLock<Mutex<HashMap<u32, String>>> = OnceLock::new();
+    let mut cache = CACHE.get_or_init(Default::default).lock().unwrap();
+    cache
+        .entry(uid)
+        .or_insert_with(|| lookup_user(uid).unwrap_or_else(|| uid.to_string()))
+        .clone()
+}
+
+/// The name of group `gid`, or the number itself, like `user_name`.
+#[cfg(unix)]
+fn group_name(gid: u32) -> String {
+    use std::collections::HashMap;
+    use std::sync::{Mutex, OnceLock};
+
+    static CACHE: OnceLock<Mutex<HashMap<u32, String>>> = OnceLock::new();
+    let mut cache = CACHE.get_or_init(Default::default).lock().unwrap();
+    cache
+        .entry(gid)
+        .or_insert_with(|| lookup_group(gid).unwrap_or_else(|| gid.to_string()))
+        .clone()
+}
+
+#[cfg(unix)]
+fn lookup_user(uid: u32) -> Option<String> {
+    use std::ffi::CStr;
+
+    let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut buf = vec![0 as libc::c_char; 1024];
+    let mut result = std::ptr::null_mut();
+    loop {
+        // SAFETY: every pointer refers to a live local of the right size, and
+        // `passwd` is only read after a successful call filled it in
+        let status = unsafe {
+            libc::getpwuid_r(uid, &mut passwd, buf.as_mut_ptr(), buf.len(), &mut result)
+        };
+        if status == libc::ERANGE && buf.len() < 1 << 20 {
+            buf.resize(buf.len() * 2, 0);
+            continue;
+        }
+        if status != 0 || result.is_null() {
+            return None;
+        }
+        // SAFETY: on success `pw_name` points to a NUL-terminated string in `buf`
+        let name = unsafe { CStr::from_ptr(passwd.pw_name) };
+        return Some(name.to_string_lossy().into_owned());
+    }
+}
+
+#[cfg(unix)]
+fn lookup_group(gid: u32) -> Option<String> {
+    use std::ffi::CStr;
+
+    let mut group: libc::group = unsafe { std::mem::zeroed() };
+    let mut buf = vec![0 as libc::c_char; 1024];
+    let mut result = std::ptr::null_mut();
+    loop {
+        // SAFETY: as in `lookup_user`
+        let status = unsafe {
+            libc::getgrgid_r(gid, &mut group, buf.as_mut_ptr(), buf.len(), &mut result)
+        };
+        // Groups list their members in `buf` too, so it may take more room
+        if status == libc::ERANGE && buf.len() < 1 << 20 {
+            buf.resize(buf.len() * 2, 0);
+            continue;
+        }
+        if status != 0 || result.is_null() {
+            return None;
+        }
+        // SAFETY: on success `gr_name` points to a NUL-terminated string in `buf`
+        let name = unsafe { CStr::from_ptr(group.gr_name) };
+        return Some(name.to_string_lossy().into_owned());
+    }
+}
@@ -0,0 +1,86 @@
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::cli::MountMode;
+use crate::tree::Node;
+
+/// Filesystems whose contents live in memory, or in image layers below the
+/// container's writable one.
+const VOLATILE: &[&str] = &["tmpfs", "overlay"];
+
+/// The tmpfs and overlay mounts in /proc/self/mountinfo, by mount point, with
+/// their filesystem type. Mounts hidden under a later one are left out.
+pub fn volatile() -> Result<HashMap<PathBuf, String>> {
+    let text = fs::read_to_string("/proc/self/mountinfo")
+        .context("Failed to read /proc/self/mountinfo (--proc-mounts only works on Linux)")?;
+    let mut mounts: HashMap<PathBuf, String> = text.lines().filter_map(parse_line).collect();
+    mounts.retain(|_, fstype| VOLATILE.contains(&fstype.as_str()));
+    Ok(mounts)
+}
+
+/// The type of the volatile mount at `path`, if it is one.
+pub fn find<'a>(mounts: &'a HashMap<PathBuf, String>, path: &Path) -> Option<&'a str> {
+    if mounts.is_empty() {
+        return None;
+    }
+    let path = std::path::absolute(path).ok()?;
+    mounts.get(&path).map(String::as_str)
+}
+
+/// What to write after each mount shown in the tree: `tmpfs mount`, and
+/// whether --du left it out.
+pub fn labels(
+    root: &Node,
+    mounts: &HashMap<PathBuf, String>,
+    mode: MountMode,
+) -> HashMap<PathBuf, String> {
+    root.descendants()
+        .into_iter()
+        .filter_map(|node| {
+            let fstype = find(mounts, &node.path)?;
+            let label = match mode {
+                MountMode::Exclude => format!("{} mount, not counted", fstype),
+                MountMode::Annotate => format!("{} mount", fstype),
+            };
+            Some((node.path.clone(), label))
+        })
+        .collect()
+}
+
+/// `36 35 98:0 /mnt1 /mnt/parent rw,noatime master:1 - ext3 /dev/root rw` is
+/// `/mnt/parent`, of type `ext3`.
+fn parse_line(line: &str) -> Option<(PathBuf, String)> {
+    let (mount, filesystem) = line.split_once(" - ")?;
+    let point = mount.split(' ').nth(4)?;
+    let fstype = filesystem.split(' ').next()?;
+    Some((PathBuf::from(unescape(point)), fstype.to_string()))
+}
+
+/// Mount points write spaces, tabs, newlines and backslashes as octal escapes
+/// like `\040`.
+fn unescape(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escape = bytes.get(i + 1..i + 4).filter(|digits| {
+            bytes[i] == b'\\' && digits.iter().all(|digit| (b'0'..=b'7').contains(digit))
+        });
+        match escape {
+            Some(digits) => {
+                let value = digits
+                    .iter()
+                    .fold(0u32, |value, digit| value * 8 + u32::from(digit - b'0'));
+                out.push(value as u8);
+                i += 4;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
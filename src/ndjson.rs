@@ -0,0 +1,107 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::cell::RefCell;
+use std::io::{self, BufWriter, Write};
+
+use crate::cli::ListArgs;
+use crate::errors::ScanError;
+use crate::meta::Fields;
+use crate::paths::PathStyle;
+use crate::tree;
+
+/// One line per walked entry. Directories carry no total size, since their
+/// contents haven't been seen yet when they are written.
+#[derive(Serialize)]
+struct EntryLine {
+    name: String,
+    path: String,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    depth: usize,
+    #[serde(flatten)]
+    fields: Fields,
+}
+
+/// Errors are written in line with the entries, tagged `"type": "error"`.
+#[derive(Serialize)]
+struct ErrorLine<'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    #[serde(flatten)]
+    error: &'a ScanError,
+}
+
+/// Write one JSON object per entry as the walk reaches it, without building
+/// the tree first. Returns whether any errors were written.
+///
+/// With the filters that leave out directories holding no matches, each
+/// directory's line waits until an entry below it is written, so at most the
+/// current directory's ancestors are held back.
+pub fn print(args: &ListArgs, paths: &PathStyle) -> Result<bool> {
+    // Shared by the entry and the error callback
+    let out = RefCell::new(BufWriter::new(io::stdout().lock()));
+    let mut had_errors = false;
+    let fields = args.fields();
+    let prune = tree::prunes_empty_dirs(&args.scan);
+    // The depths and lines of directories nothing has been written below yet
+    let mut pending: Vec<(usize, Vec<u8>)> = Vec::new();
+
+    tree::walk(
+        &args.path,
+        &args.scan,
+        true,
+        |entry| {
+            let is_dir = entry.path().is_dir();
+            let link_target = entry
+                .path_is_symlink()
+                .then(|| std::fs::read_link(entry.path()).ok())
+                .flatten();
+            let mut fields = Fields::read(
+                entry.path(),
+                &fields,
+                || entry.metadata().map(|m| m.len()).unwrap_or(0),
+                link_target.as_deref(),
+                None,
+            );
+            if is_dir {
+                fields.size = None;
+            }
+            let line = EntryLine {
+                name: entry.file_name().to_string_lossy().into_owned(),
+                path: paths.apply(entry.path()).to_string_lossy().into_owned(),
+                kind: if is_dir { "directory" } else { "file" },
+                depth: entry.depth(),
+                fields,
+            };
+            let mut text = serde_json::to_vec(&line)?;
+            text.push(b'\n');
+
+            // The walk is depth first, so siblings and their ancestors' siblings
+            // end whatever directories are still pending below them
+            let depth = entry.depth();
+            while pending.last().is_some_and(|(pending_depth, _)| *pending_depth >= depth) {
+                pending.pop();
+            }
+            if prune && is_dir && depth > 0 {
+                pending.push((depth, text));
+                return Ok(());
+            }
+            let mut out = out.borrow_mut();
+            for (_, line) in pending.drain(..) {
+                out.write_all(&line)?;
+            }
+            out.write_all(&text)?;
+            Ok(())
+        },
+        |error| {
+            had_errors = true;
+            let mut out = out.borrow_mut();
+            serde_json::to_writer(&mut *out, &ErrorLine { kind: "error", error: &error })?;
+            out.write_all(b"\n")?;
+            Ok(())
+        },
+    )?;
+
+    out.into_inner().flush()?;
+    Ok(had_errors)
+}
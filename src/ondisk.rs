@@ -0,0 +1,214 @@
+use std::fmt;
+use std::path::Path;
+
+use crate::tree::format_size;
+
+/// What `--on-disk` shows for a file on a compressing filesystem.
+pub struct DiskUsage {
+    /// The file's size as read
+    pub apparent: u64,
+    /// The space its data takes on disk, after compression
+    pub on_disk: u64,
+}
+
+impl fmt::Display for DiskUsage {
+    /// `1.2M on disk, 3.4x`; just the size for files that are all holes or
+    /// inline metadata.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} on disk", format_size(self.on_disk))?;
+        if self.on_disk > 0 {
+            write!(f, ", {:.1}x", self.apparent as f64 / self.on_disk as f64)?;
+        }
+        Ok(())
+    }
+}
+
+/// Fail unless treee was built with the `fs-compression` feature.
+pub fn ensure_enabled() -> anyhow::Result<()> {
+    if cfg!(feature = "fs-compression") {
+        Ok(())
+    } else {
+        anyhow::bail!(
+            "Filesystem compression support is not enabled; rebuild treee with \
+             `--features fs-compression`"
+        )
+    }
+}
+
+/// How much of the disk the regular file at `path` takes, if it lives on btrfs
+/// or ZFS. Other filesystems don't compress, so their files get `None`.
+#[cfg(all(feature = "fs-compression", target_os = "linux"))]
+pub fn probe(path: &Path) -> Option<DiskUsage> {
+    linux::probe(path)
+}
+
+#[cfg(not(all(feature = "fs-compression", target_os = "linux")))]
+pub fn probe(_path: &Path) -> Option<DiskUsage> {
+    None
+}
+
+#[cfg(all(feature = "fs-compression", target_os = "linux"))]
+mod linux {
+    use std::collections::HashSet;
+    use std::ffi::CString;
+    use std::fs::{self, File};
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::fs::MetadataExt;
+    use std::os::unix::io::AsRawFd;
+    use std::path::Path;
+
+    use super::DiskUsage;
+
+    const BTRFS_SUPER_MAGIC: u64 = 0x9123_683e;
+    const ZFS_SUPER_MAGIC: u64 = 0x2fc1_2fc1;
+
+    /// `_IOWR(0x94, 17, struct btrfs_ioctl_search_args_v2)`
+    const BTRFS_IOC_TREE_SEARCH_V2: u32 = 0xc070_9411;
+    const BTRFS_EXTENT_DATA_KEY: u32 = 108;
+    /// Extents whose data sits in the item itself rather than on disk
+    const BTRFS_FILE_EXTENT_INLINE: u8 = 0;
+    /// `generation`, `ram_bytes`, `compression`, `encryption`,
+    /// `other_encoding` and `type` come before inline data
+    const FILE_EXTENT_HEADER: usize = 21;
+    const SEARCH_HEADER: usize = 32;
+    const BUF_SIZE: usize = 64 * 1024;
+
+    #[repr(C)]
+    struct SearchKey {
+        tree_id: u64,
+        min_objectid: u64,
+        max_objectid: u64,
+        min_offset: u64,
+        max_offset: u64,
+        min_transid: u64,
+        max_transid: u64,
+        min_type: u32,
+        max_type: u32,
+        nr_items: u32,
+        unused: u32,
+        unused1: u64,
+        unused2: u64,
+        unused3: u64,
+        unused4: u64,
+    }
+
+    #[repr(C)]
+    struct SearchArgs {
+        key: SearchKey,
+        buf_size: u64,
+        buf: [u8; BUF_SIZE],
+    }
+
+    pub fn probe(path: &Path) -> Option<DiskUsage> {
+        let metadata = fs::symlink_metadata(path).ok()?;
+        if !metadata.is_file() {
+            return None;
+        }
+        let apparent = metadata.len();
+        match filesystem(path)? {
+            // Block counts already reflect compression on ZFS
+            ZFS_SUPER_MAGIC => Some(DiskUsage {
+                apparent,
+                on_disk: metadata.blocks() * 512,
+            }),
+            // but on btrfs they count the data uncompressed; the extents say
+            // what it takes (reading them needs root, like `compsize`)
+            BTRFS_SUPER_MAGIC => Some(DiskUsage {
+                apparent,
+                on_disk: btrfs_extents(path, metadata.ino())?,
+            }),
+            _ => None,
+        }
+    }
+
+    fn filesystem(path: &Path) -> Option<u64> {
+        let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+        // SAFETY: `stat` is a plain struct that `statfs` fills in on success
+        let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+        if unsafe { libc::statfs(path.as_ptr(), &mut stat) } != 0 {
+            return None;
+        }
+        // Signed on some targets, where the magic numbers come out negative
+        Some(stat.f_type as u64 & 0xffff_ffff)
+    }
+
+    /// The bytes on disk of the extents of inode `ino`, each shared extent
+    /// counted once, as `compsize` does.
+    fn btrfs_extents(path: &Path, ino: u64) -> Option<u64> {
+        let file = File::open(path).ok()?;
+        let mut args = Box::new(SearchArgs {
+            key: SearchKey {
+                // The subvolume holding the file
+                tree_id: 0,
+                min_objectid: ino,
+                max_objectid: ino,
+                min_offset: 0,
+                max_offset: u64::MAX,
+                min_transid: 0,
+                max_transid: u64::MAX,
+                min_type: BTRFS_EXTENT_DATA_KEY,
+                max_type: BTRFS_EXTENT_DATA_KEY,
+                nr_items: 0,
+                unused: 0,
+                unused1: 0,
+                unused2: 0,
+                unused3: 0,
+                unused4: 0,
+            },
+            buf_size: BUF_SIZE as u64,
+            buf: [0; BUF_SIZE],
+        });
+
+        let mut total = 0;
+        let mut seen = HashSet::new();
+        loop {
+            args.key.nr_items = u32::MAX;
+            // The request is an int on musl
+            let request = BTRFS_IOC_TREE_SEARCH_V2 as libc::Ioctl;
+            // SAFETY: `args` is laid out as `struct btrfs_ioctl_search_args_v2`
+            // with `buf_size` bytes of buffer after it
+            let status = unsafe { libc::ioctl(file.as_raw_fd(), request, &mut *args) };
+            if status != 0 {
+                return None;
+            }
+            if args.key.nr_items == 0 {
+                return Some(total);
+            }
+
+            let mut at = 0;
+            let mut last_offset = 0;
+            for _ in 0..args.key.nr_items {
+                let header = args.buf.get(at..at + SEARCH_HEADER)?;
+                let offset = u64::from_ne_bytes(header[16..24].try_into().ok()?);
+                let kind = u32::from_ne_bytes(header[24..28].try_into().ok()?);
+                let len = u32::from_ne_bytes(header[28..32].try_into().ok()?) as usize;
+                let item = args.buf.get(at + SEARCH_HEADER..at + SEARCH_HEADER + len)?;
+                at += SEARCH_HEADER + len;
+                last_offset = offset;
+                if kind == BTRFS_EXTENT_DATA_KEY {
+                    total += extent_bytes(item, &mut seen)?;
+                }
+            }
+            match last_offset.checked_add(1) {
+                Some(next) => args.key.min_offset = next,
+                None => return Some(total),
+            }
+        }
+    }
+
+    /// What one `btrfs_file_extent_item` takes on disk.
+    fn extent_bytes(item: &[u8], seen: &mut HashSet<u64>) -> Option<u64> {
+        let kind = *item.get(20)?;
+        if kind == BTRFS_FILE_EXTENT_INLINE {
+            return Some(item.len().saturating_sub(FILE_EXTENT_HEADER) as u64);
+        }
+        let field = |at: usize| Some(u64::from_le_bytes(item.get(at..at + 8)?.try_into().ok()?));
+        let disk_bytenr = field(FILE_EXTENT_HEADER)?;
+        let disk_num_bytes = field(FILE_EXTENT_HEADER + 8)?;
+        // Holes have no extent at all
+        if disk_bytenr == 0 || !seen.insert(disk_bytenr) {
+            return Some(0);
+        }
+        Some(disk_num_bytes)
+    }
+}
@@ -0,0 +1,61 @@
+use std::io::{self, BufWriter, Write};
+
+use crate::cli::OrgStyle;
+use crate::paths::PathStyle;
+use crate::tree::Node;
+
+/// Print the tree as org-mode, with every file linked so it opens from Emacs.
+pub fn print(root: &Node, style: OrgStyle, paths: &PathStyle) -> io::Result<()> {
+    let mut out = BufWriter::new(io::stdout().lock());
+    match style {
+        OrgStyle::Headings => write_heading(&mut out, root, paths, 1)?,
+        OrgStyle::List => write_item(&mut out, root, paths, 0)?,
+    }
+    out.flush()
+}
+
+/// A heading per directory. Lines after a heading belong to it, so a
+/// directory's files are listed before its subdirectories' headings.
+fn write_heading(
+    out: &mut impl Write,
+    dir: &Node,
+    paths: &PathStyle,
+    level: usize,
+) -> io::Result<()> {
+    writeln!(out, "{} {}/", "*".repeat(level), dir.name())?;
+    for file in dir.children.iter().filter(|child| !child.is_dir) {
+        writeln!(out, "- {}", link(file, paths))?;
+    }
+    for subdir in dir.children.iter().filter(|child| child.is_dir) {
+        write_heading(out, subdir, paths, level + 1)?;
+    }
+    Ok(())
+}
+
+fn write_item(out: &mut impl Write, node: &Node, paths: &PathStyle, depth: usize) -> io::Result<()> {
+    let indent = "  ".repeat(depth);
+    if node.is_dir {
+        writeln!(out, "{}- {}/", indent, node.name())?;
+        for child in &node.children {
+            write_item(out, child, paths, depth + 1)?;
+        }
+        Ok(())
+    } else {
+        writeln!(out, "{}- {}", indent, link(node, paths))
+    }
+}
+
+/// `[[file:PATH][NAME]]`, with the brackets and backslashes that would end
+/// the link early escaped.
+fn link(node: &Node, paths: &PathStyle) -> String {
+    let escape = |text: &str| {
+        text.replace('\\', "\\\\")
+            .replace('[', "\\[")
+            .replace(']', "\\]")
+    };
+    format!(
+        "[[file:{}][{}]]",
+        escape(&paths.apply(&node.path).to_string_lossy()),
+        escape(&node.name())
+    )
+}
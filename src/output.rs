@@ -0,0 +1,144 @@
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::thread::{self, JoinHandle};
+
+use crate::cli::Compression;
+
+/// The thread turning the piped stdout into the compressed file, until
+/// `finish`.
+static COMPRESSOR: Mutex<Option<JoinHandle<io::Result<()>>>> = Mutex::new(None);
+
+/// Point stdout at a new file at `path` for the rest of the process, so every
+/// output mode writes there without having to know about -o.
+pub fn redirect_stdout(path: &Path) -> Result<()> {
+    let file = create(path)?;
+    redirect(file).with_context(|| format!("Failed to write to '{}'", path.display()))
+}
+
+/// The compression a file name asks for: `.gz` for gzip, `.zst` for zstd.
+pub fn compression_for(path: &Path) -> Option<Compression> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    match ext.as_str() {
+        "gz" | "gzip" => Some(Compression::Gzip),
+        "zst" | "zstd" => Some(Compression::Zstd),
+        _ => None,
+    }
+}
+
+/// Like `redirect_stdout`, but compress everything written to stdout on its
+/// way to `path`, or to the original stdout when there is no path. Call
+/// `finish` before exiting, or the file is cut short.
+pub fn compress_stdout(path: Option<&Path>, compression: Compression) -> Result<()> {
+    let sink = match path {
+        Some(path) => create(path)?,
+        None => stdout_file().context("Failed to duplicate stdout")?,
+    };
+    let (reader, writer) = io::pipe().context("Failed to create a pipe for compression")?;
+
+    let compressor = thread::spawn(move || compress(reader, sink, compression));
+    *COMPRESSOR.lock().unwrap() = Some(compressor);
+    redirect(pipe_file(writer)).context("Failed to redirect stdout")
+}
+
+/// Close the compressed stdout and wait until all of it has been written out.
+/// Does nothing unless `compress_stdout` was called.
+pub fn finish() -> Result<()> {
+    let Some(compressor) = COMPRESSOR.lock().unwrap().take() else {
+        return Ok(());
+    };
+    io::stdout().flush()?;
+    close_stdout();
+
+    compressor
+        .join()
+        .unwrap_or_else(|_| Err(io::Error::other("compressor panicked")))
+        .context("Failed to write compressed output")
+}
+
+/// Compress all of `input` into `output`, at each format's default level.
+fn compress(mut input: impl io::Read, output: File, compression: Compression) -> io::Result<()> {
+    let output = io::BufWriter::new(output);
+    let mut output = match compression {
+        Compression::Gzip => {
+            let mut encoder = flate2::write::GzEncoder::new(output, flate2::Compression::default());
+            io::copy(&mut input, &mut encoder)?;
+            encoder.finish()?
+        }
+        Compression::Zstd => {
+            let mut encoder = zstd::Encoder::new(output, 0)?;
+            io::copy(&mut input, &mut encoder)?;
+            encoder.finish()?
+        }
+    };
+    output.flush()
+}
+
+fn create(path: &Path) -> Result<File> {
+    File::create(path).with_context(|| format!("Failed to create '{}'", path.display()))
+}
+
+#[cfg(unix)]
+fn redirect(file: File) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    // The duplicate stays open once `file` is dropped
+    if unsafe { libc::dup2(file.as_raw_fd(), libc::STDOUT_FILENO) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn redirect(file: File) -> io::Result<()> {
+    use std::os::windows::io::IntoRawHandle;
+    use windows_sys::Win32::System::Console::{STD_OUTPUT_HANDLE, SetStdHandle};
+
+    // Standard library writes look the handle up every time; it is never closed
+    let handle = file.into_raw_handle();
+    if unsafe { SetStdHandle(STD_OUTPUT_HANDLE, handle) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn stdout_file() -> io::Result<File> {
+    use std::os::unix::io::AsFd;
+    Ok(File::from(io::stdout().as_fd().try_clone_to_owned()?))
+}
+
+#[cfg(windows)]
+fn stdout_file() -> io::Result<File> {
+    use std::os::windows::io::AsHandle;
+    Ok(File::from(io::stdout().as_handle().try_clone_to_owned()?))
+}
+
+#[cfg(unix)]
+fn pipe_file(writer: io::PipeWriter) -> File {
+    File::from(std::os::unix::io::OwnedFd::from(writer))
+}
+
+#[cfg(windows)]
+fn pipe_file(writer: io::PipeWriter) -> File {
+    File::from(std::os::windows::io::OwnedHandle::from(writer))
+}
+
+/// Close the pipe behind stdout, so the compressor sees the end of its input.
+#[cfg(unix)]
+fn close_stdout() {
+    // SAFETY: nothing else owns the descriptor, and stdout isn't written after
+    unsafe { libc::close(libc::STDOUT_FILENO) };
+}
+
+#[cfg(windows)]
+fn close_stdout() {
+    use std::os::windows::io::{FromRawHandle, OwnedHandle};
+    use windows_sys::Win32::System::Console::{GetStdHandle, STD_OUTPUT_HANDLE};
+
+    // SAFETY: `redirect` gave the handle up to be stdout alone, and stdout
+    // isn't written after
+    unsafe { drop(OwnedHandle::from_raw_handle(GetStdHandle(STD_OUTPUT_HANDLE))) };
+}
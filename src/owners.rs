@@ -0,0 +1,150 @@
+use anyhow::{Context, Result};
+use colored::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::cli::Field;
+use crate::meta::Fields;
+use crate::printer::TreePrinter;
+use crate::tree::Node;
+
+/// An entry of a baseline recorded with `treee -J --fields owner,mode`. Only
+/// the names are used to place entries, so it may have been recorded with any
+/// path style, or on another machine.
+#[derive(Deserialize)]
+struct Recorded {
+    name: String,
+    owner: Option<String>,
+    mode: Option<String>,
+    #[serde(default)]
+    children: Vec<Recorded>,
+}
+
+/// The owner and mode an entry had in the baseline, if recorded.
+type Expected = (Option<String>, Option<String>);
+
+struct DriftNode {
+    name: String,
+    is_dir: bool,
+    /// What changed, e.g. `owner root -> alice`; `None` for directories that
+    /// are only shown because something inside them drifted
+    drift: Option<String>,
+    children: Vec<DriftNode>,
+}
+
+/// Compare the owner and permissions of every scanned entry with the baseline
+/// in `path` and print the ones that changed, in a tree. Entries the baseline
+/// doesn't know are left alone, as are fields it didn't record. Returns
+/// whether anything drifted.
+pub fn run(root: &Node, path: &Path, printer: &TreePrinter, use_color: bool) -> Result<bool> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read baseline '{}'", path.display()))?;
+    let recorded: Recorded = serde_json::from_str(&text).with_context(|| {
+        format!("'{}' is not a baseline written by `treee -J --fields owner,mode`", path.display())
+    })?;
+
+    let mut baseline = HashMap::new();
+    flatten(&recorded.children, Path::new(""), &mut baseline);
+
+    let mut checked = 0;
+    let mut drifted = 0;
+    let children = build_children(root, &root.path, &baseline, &mut checked, &mut drifted);
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    let title = root.path.display().to_string();
+    writeln!(out, "{}", if use_color { title.bold().to_string() } else { title })?;
+    write_children(&children, printer, "", use_color, &mut out)?;
+    writeln!(out, "\n{} of {} entries drifted from {}", drifted, checked, path.display())?;
+    out.flush()?;
+
+    Ok(drifted > 0)
+}
+
+fn flatten(entries: &[Recorded], parent: &Path, out: &mut HashMap<PathBuf, Expected>) {
+    for entry in entries {
+        let path = parent.join(&entry.name);
+        flatten(&entry.children, &path, out);
+        out.insert(path, (entry.owner.clone(), entry.mode.clone()));
+    }
+}
+
+fn build_children(
+    node: &Node,
+    root: &Path,
+    baseline: &HashMap<PathBuf, Expected>,
+    checked: &mut usize,
+    drifted: &mut usize,
+) -> Vec<DriftNode> {
+    node.children
+        .iter()
+        .filter_map(|child| {
+            let relative = child.path.strip_prefix(root).ok()?;
+            let drift = baseline.get(relative).and_then(|expected| {
+                *checked += 1;
+                let drift = compare(child, expected);
+                *drifted += usize::from(drift.is_some());
+                drift
+            });
+            let children = build_children(child, root, baseline, checked, drifted);
+
+            // Only keep unchanged entries when they lead to a change
+            if drift.is_none() && children.is_empty() {
+                return None;
+            }
+            Some(DriftNode {
+                name: child.name(),
+                is_dir: child.is_dir,
+                drift,
+                children,
+            })
+        })
+        .collect()
+}
+
+/// `owner root -> alice, mode 0644 -> 0666` for the fields that changed.
+fn compare(node: &Node, (owner, mode): &Expected) -> Option<String> {
+    let current = Fields::read(&node.path, &[Field::Owner, Field::Mode], || 0, None, None);
+    let changes: Vec<String> = [("owner", owner, current.owner), ("mode", mode, current.mode)]
+        .into_iter()
+        .filter_map(|(label, expected, current)| {
+            let (expected, current) = (expected.as_ref()?, current?);
+            (*expected != current).then(|| format!("{} {} -> {}", label, expected, current))
+        })
+        .collect();
+    (!changes.is_empty()).then(|| changes.join(", "))
+}
+
+fn write_children(
+    children: &[DriftNode],
+    printer: &TreePrinter,
+    prefix: &str,
+    use_color: bool,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    for (i, child) in children.iter().enumerate() {
+        let is_last = i == children.len() - 1;
+
+        let text = match &child.drift {
+            Some(drift) => format!("~ {} ({})", child.name, drift),
+            None => child.name.clone(),
+        };
+        let text = if use_color {
+            match child.drift {
+                Some(_) => text.yellow().to_string(),
+                None if child.is_dir => text.blue().bold().to_string(),
+                None => text,
+            }
+        } else {
+            text
+        };
+
+        printer.write_line(out, prefix, is_last, &text)?;
+
+        let child_prefix = printer.get_child_prefix(prefix, is_last);
+        write_children(&child.children, printer, &child_prefix, use_color, out)?;
+    }
+    Ok(())
+}
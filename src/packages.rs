@@ -0,0 +1,119 @@
+use colored::*;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::printer::TreePrinter;
+use crate::tree::{Node, format_size};
+
+/// Files that make the directory holding them the root of a package.
+const MANIFESTS: &[&str] = &["Cargo.toml", "package.json", "go.mod", "pyproject.toml"];
+
+struct Package {
+    /// Relative to the package (or root) above
+    name: String,
+    manifests: Vec<&'static str>,
+    /// Of the files belonging to this package rather than one inside it
+    files: usize,
+    size: u64,
+    children: Vec<Package>,
+}
+
+/// Print the tree collapsed to the package roots in it, each with the manifests
+/// that make it one and the number and size of its files; files of a package
+/// nested inside another only count towards the inner one.
+pub fn print(root: &Node, printer: &TreePrinter, use_color: bool) -> io::Result<()> {
+    let mut top = Package {
+        name: root.path.display().to_string(),
+        manifests: manifests(root),
+        files: 0,
+        size: 0,
+        children: Vec::new(),
+    };
+    collect(root, &root.path, &mut top);
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    writeln!(out, "{}", label(&top, use_color))?;
+    write_children(&top.children, printer, "", use_color, &mut out)?;
+    let count = count(&top) - usize::from(top.manifests.is_empty());
+    writeln!(out, "\n{} package{}", count, if count == 1 { "" } else { "s" })?;
+    out.flush()
+}
+
+/// Add the contents of `node` to `package`, starting a new package at every
+/// directory with a manifest.
+fn collect(node: &Node, base: &Path, package: &mut Package) {
+    for child in &node.children {
+        if !child.is_dir {
+            package.files += 1;
+            package.size += child.size;
+            continue;
+        }
+        let manifests = manifests(child);
+        if manifests.is_empty() {
+            collect(child, base, package);
+            continue;
+        }
+        let name = child.path.strip_prefix(base).unwrap_or(&child.path);
+        let mut nested = Package {
+            name: name.display().to_string(),
+            manifests,
+            files: 0,
+            size: 0,
+            children: Vec::new(),
+        };
+        collect(child, &child.path, &mut nested);
+        package.children.push(nested);
+    }
+}
+
+fn manifests(dir: &Node) -> Vec<&'static str> {
+    MANIFESTS
+        .iter()
+        .copied()
+        .filter(|&manifest| {
+            dir.children.iter().any(|child| !child.is_dir && child.name() == manifest)
+        })
+        .collect()
+}
+
+fn count(package: &Package) -> usize {
+    1 + package.children.iter().map(count).sum::<usize>()
+}
+
+/// `crates/core [Cargo.toml] (42 files, 130K)`
+fn label(package: &Package, use_color: bool) -> String {
+    let name = if use_color {
+        package.name.blue().bold().to_string()
+    } else {
+        package.name.clone()
+    };
+    let manifests = if package.manifests.is_empty() {
+        String::new()
+    } else {
+        format!(" [{}]", package.manifests.join(", "))
+    };
+    let files = if package.files == 1 { "file" } else { "files" };
+    let stats = format!(" ({} {}, {})", package.files, files, format_size(package.size));
+    if use_color {
+        format!("{}{}{}", name, manifests.green(), stats.dimmed())
+    } else {
+        format!("{}{}{}", name, manifests, stats)
+    }
+}
+
+fn write_children(
+    children: &[Package],
+    printer: &TreePrinter,
+    prefix: &str,
+    use_color: bool,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    for (i, child) in children.iter().enumerate() {
+        let is_last = i == children.len() - 1;
+        printer.write_line(out, prefix, is_last, &label(child, use_color))?;
+
+        let child_prefix = printer.get_child_prefix(prefix, is_last);
+        write_children(&child.children, printer, &child_prefix, use_color, out)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,94 @@
+use std::borrow::Cow;
+use std::path::{Component, Path, PathBuf};
+
+/// How entry paths are written in full-path, quoted and JSON output.
+#[derive(Clone, Default)]
+pub enum PathStyle {
+    /// The scanned path joined with each entry's path below it, as walked
+    #[default]
+    AsGiven,
+    /// Relative to an absolute base directory
+    RelativeTo(PathBuf),
+    /// Below the canonicalized root, so `..` and symlinks leading up to the
+    /// root are resolved while entries keep their own names
+    Absolute { root: PathBuf, canonical: PathBuf },
+}
+
+impl PathStyle {
+    /// The style for `--absolute` or `--relative-to`; the latter given without
+    /// a directory makes paths relative to the scan root.
+    pub fn new(absolute: bool, relative_to: Option<Option<&Path>>, root: &Path) -> Self {
+        if absolute {
+            return match std::fs::canonicalize(root) {
+                Ok(canonical) => Self::Absolute {
+                    root: root.to_path_buf(),
+                    canonical,
+                },
+                Err(_) => Self::AsGiven,
+            };
+        }
+
+        let Some(base) = relative_to else {
+            return Self::AsGiven;
+        };
+        match std::path::absolute(base.unwrap_or(root)) {
+            Ok(base) => Self::RelativeTo(base),
+            Err(_) => Self::AsGiven,
+        }
+    }
+
+    pub fn apply<'a>(&'a self, path: &'a Path) -> Cow<'a, Path> {
+        match self {
+            Self::AsGiven => Cow::Borrowed(path),
+            Self::RelativeTo(base) => match std::path::absolute(path) {
+                Ok(path) => Cow::Owned(relative_path(&path, base)),
+                Err(_) => Cow::Borrowed(path),
+            },
+            Self::Absolute { root, canonical } => match path.strip_prefix(root) {
+                Ok(relative) if relative.as_os_str().is_empty() => Cow::Borrowed(canonical),
+                Ok(relative) => Cow::Owned(canonical.join(relative)),
+                Err(_) => Cow::Borrowed(path),
+            },
+        }
+    }
+}
+
+/// `path` relative to `base`, going up with `..` where needed. Both must be
+/// absolute; paths on different Windows drives stay absolute.
+fn relative_path(path: &Path, base: &Path) -> PathBuf {
+    let path: Vec<Component> = normalize(path);
+    let base: Vec<Component> = normalize(base);
+    if path.first() != base.first() {
+        return path.iter().collect();
+    }
+
+    let common = path
+        .iter()
+        .zip(&base)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let relative: PathBuf = std::iter::repeat_n(Component::ParentDir, base.len() - common)
+        .chain(path[common..].iter().copied())
+        .collect();
+
+    if relative.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        relative
+    }
+}
+
+/// Drop `.` components and resolve `..` lexically, without touching the disk.
+fn normalize(path: &Path) -> Vec<Component<'_>> {
+    let mut parts = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if matches!(parts.last(), Some(Component::Normal(_))) => {
+                parts.pop();
+            }
+            _ => parts.push(component),
+        }
+    }
+    parts
+}
@@ -0,0 +1,37 @@
+use std::io::{self, BufWriter, Write};
+
+use crate::cli::PlantUmlStyle;
+use crate::tree::{Node, format_size};
+
+/// Print the tree in PlantUML's outline syntax, shared by work breakdown
+/// structures and mind maps: one `*` per level, with `_` dropping the box
+/// around files so directories stand out.
+pub fn print(root: &Node, with_size: bool, style: PlantUmlStyle) -> io::Result<()> {
+    let mut out = BufWriter::new(io::stdout().lock());
+    let kind = match style {
+        PlantUmlStyle::Wbs => "wbs",
+        PlantUmlStyle::Mindmap => "mindmap",
+    };
+    writeln!(out, "@start{}", kind)?;
+    write_node(&mut out, root, with_size, 1)?;
+    writeln!(out, "@end{}", kind)?;
+    out.flush()
+}
+
+fn write_node(out: &mut impl Write, node: &Node, with_size: bool, level: usize) -> io::Result<()> {
+    let marker = if node.is_dir { "" } else { "_" };
+    // Line breaks would end the entry early
+    let mut label = node.name().replace(['\n', '\r'], " ");
+    if node.is_dir {
+        label.push('/');
+    }
+    if with_size {
+        label = format!("{} ({})", label, format_size(node.size));
+    }
+    writeln!(out, "{}{} {}", "*".repeat(level), marker, label)?;
+
+    for child in &node.children {
+        write_node(out, child, with_size, level + 1)?;
+    }
+    Ok(())
+}
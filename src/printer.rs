@@ -0,0 +1,874 @@
+use colored::*;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use unicode_width::UnicodeWidthStr;
+
+use crate::cli::{Charset, ListArgs, QuoteStyle};
+use crate::docinfo;
+use crate::filter::NameHighlighter;
+use crate::kind;
+use crate::language::Language;
+use crate::media;
+use crate::meta;
+use crate::ondisk;
+use crate::paths::PathStyle;
+use crate::quote;
+use crate::term;
+use crate::time;
+use crate::tree::{self, Node, SizeStyle, format_size};
+
+/// The strings branches are drawn with. Lines line up as long as all four have
+/// the same width.
+pub struct Glyphs {
+    pub branch: String,
+    pub last: String,
+    pub vertical: String,
+    pub indent: String,
+}
+
+impl Glyphs {
+    pub fn new(charset: Charset) -> Self {
+        let (branch, last, vertical) = match charset {
+            Charset::Utf8 => ("├── ", "└── ", "│   "),
+            Charset::Ascii => ("|-- ", "`-- ", "|   "),
+            Charset::Rounded => ("├── ", "╰── ", "│   "),
+            Charset::Heavy => ("┣━━ ", "┗━━ ", "┃   "),
+        };
+        Self {
+            branch: branch.to_string(),
+            last: last.to_string(),
+            vertical: vertical.to_string(),
+            indent: "    ".to_string(),
+        }
+    }
+
+    /// The --charset glyphs with any --glyph-* overrides applied.
+    pub fn from_args(args: &ListArgs) -> Self {
+        let mut glyphs = Self::new(args.charset);
+        for (glyph, custom) in [
+            (&mut glyphs.branch, &args.glyph_branch),
+            (&mut glyphs.last, &args.glyph_last),
+            (&mut glyphs.vertical, &args.glyph_vertical),
+            (&mut glyphs.indent, &args.glyph_indent),
+        ] {
+            if let Some(custom) = custom {
+                glyph.clone_from(custom);
+            }
+        }
+        glyphs
+    }
+}
+
+pub struct TreePrinter {
+    use_color: bool,
+    full_path: bool,
+    show_size: bool,
+    width: Option<usize>,
+    quote: Option<QuoteStyle>,
+    highlight_size: Option<u64>,
+    show_depth: bool,
+    paths: PathStyle,
+    root_path: bool,
+    collapse_files: bool,
+    /// Directories are walked but not written
+    files_only: bool,
+    highlighter: Option<NameHighlighter>,
+    grid: bool,
+    counts: bool,
+    max_depth: Option<usize>,
+    glyphs: Glyphs,
+    print0: bool,
+    media_info: bool,
+    doc_info: bool,
+    on_disk: bool,
+    hyperlinks: bool,
+    blame_heat: Option<HashMap<PathBuf, SystemTime>>,
+    git_owners: Option<HashMap<PathBuf, String>>,
+    code_owners: Option<HashMap<PathBuf, String>>,
+    mounts: Option<HashMap<PathBuf, String>>,
+    file_sizes: bool,
+    permissions: bool,
+    octal_perms: bool,
+    user: bool,
+    group: bool,
+    inodes: bool,
+    device: bool,
+    nlink: bool,
+    flag_hardlinks: bool,
+    /// With -D, in the --timefmt format if given
+    dates: Option<Option<String>>,
+    lang_bars: Option<HashMap<PathBuf, Vec<(&'static Language, u64)>>>,
+}
+
+impl TreePrinter {
+    pub fn new(use_color: bool, full_path: bool, show_size: bool) -> Self {
+        Self {
+            use_color,
+            full_path,
+            show_size,
+            width: None,
+            quote: None,
+            highlight_size: None,
+            show_depth: false,
+            paths: PathStyle::default(),
+            root_path: false,
+            collapse_files: false,
+            highlighter: None,
+            files_only: false,
+            grid: false,
+            counts: false,
+            max_depth: None,
+            glyphs: Glyphs::new(Charset::Utf8),
+            print0: false,
+            media_info: false,
+            doc_info: false,
+            on_disk: false,
+            hyperlinks: false,
+            blame_heat: None,
+            git_owners: None,
+            code_owners: None,
+            mounts: None,
+            file_sizes: false,
+            permissions: false,
+            octal_perms: false,
+            user: false,
+            group: false,
+            inodes: false,
+            device: false,
+            nlink: false,
+            flag_hardlinks: false,
+            dates: None,
+            lang_bars: None,
+        }
+    }
+
+    /// Quote full paths for the given shell.
+    pub fn with_quote(mut self, quote: Option<QuoteStyle>) -> Self {
+        self.quote = quote;
+        self
+    }
+
+    /// In full-path mode, write bare paths each ended by a NUL byte.
+    pub fn with_print0(mut self, print0: bool) -> Self {
+        self.print0 = print0;
+        self
+    }
+
+    /// Highlight entries larger than `size` bytes.
+    pub fn with_highlight(mut self, size: Option<u64>) -> Self {
+        self.highlight_size = size;
+        self
+    }
+
+    /// Start every line with the entry's depth below the root.
+    pub fn with_depth(mut self, show_depth: bool) -> Self {
+        self.show_depth = show_depth;
+        self
+    }
+
+    /// How paths are written in full-path mode.
+    pub fn with_paths(mut self, paths: PathStyle) -> Self {
+        self.paths = paths;
+        self
+    }
+
+    /// Label the root line with the whole path rather than its last component.
+    pub fn with_root_path(mut self, root_path: bool) -> Self {
+        self.root_path = root_path;
+        self
+    }
+
+    /// Leave files out and summarize them after their directory instead.
+    pub fn with_collapsed_files(mut self, collapse_files: bool) -> Self {
+        self.collapse_files = collapse_files;
+        self
+    }
+
+    /// Write only the files at every depth, leaving out the directories that
+    /// hold them.
+    pub fn with_files_only(mut self, files_only: bool) -> Self {
+        self.files_only = files_only;
+        self
+    }
+
+    /// Highlight the parts of file names matched by `-P` and `--regex`.
+    pub fn with_match_highlight(mut self, highlighter: Option<NameHighlighter>) -> Self {
+        self.highlighter = highlighter;
+        self
+    }
+
+    /// Lay full paths out in columns instead of one per line.
+    pub fn with_grid(mut self, grid: bool) -> Self {
+        self.grid = grid;
+        self
+    }
+
+    /// Follow directory names with the number of directories and files below them.
+    pub fn with_counts(mut self, counts: bool) -> Self {
+        self.counts = counts;
+        self
+    }
+
+    /// Only show entries up to `max_depth` below the root, while sizes and
+    /// counts still include everything scanned.
+    pub fn with_max_depth(mut self, max_depth: Option<usize>) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Draw branches with `glyphs`.
+    pub fn with_glyphs(mut self, glyphs: Glyphs) -> Self {
+        self.glyphs = glyphs;
+        self
+    }
+
+    /// Follow media files with their dimensions and duration.
+    pub fn with_media_info(mut self, media_info: bool) -> Self {
+        self.media_info = media_info;
+        self
+    }
+
+    /// Follow PDFs and Office files with their page, sheet or slide counts.
+    pub fn with_doc_info(mut self, doc_info: bool) -> Self {
+        self.doc_info = doc_info;
+        self
+    }
+
+    /// Follow files on btrfs and ZFS with the space they take after compression.
+    pub fn with_on_disk(mut self, on_disk: bool) -> Self {
+        self.on_disk = on_disk;
+        self
+    }
+
+    /// Make names clickable with OSC 8 hyperlinks to their files.
+    pub fn with_hyperlinks(mut self, hyperlinks: bool) -> Self {
+        self.hyperlinks = hyperlinks;
+        self
+    }
+
+    /// Color tracked files by the time of their last commit, from
+    /// `git::last_commit_times`.
+    pub fn with_blame_heat(mut self, times: Option<HashMap<PathBuf, SystemTime>>) -> Self {
+        self.blame_heat = times;
+        self
+    }
+
+    /// Follow entries with their main author, from `git::top_authors`.
+    pub fn with_git_owners(mut self, owners: Option<HashMap<PathBuf, String>>) -> Self {
+        self.git_owners = owners;
+        self
+    }
+
+    /// Follow entries with their CODEOWNERS owners, from `CodeOwners::annotate`.
+    pub fn with_code_owners(mut self, owners: Option<HashMap<PathBuf, String>>) -> Self {
+        self.code_owners = owners;
+        self
+    }
+
+    /// Follow mount points with their type, from `mounts::labels`.
+    pub fn with_mounts(mut self, mounts: Option<HashMap<PathBuf, String>>) -> Self {
+        self.mounts = mounts;
+        self
+    }
+
+    /// Show the sizes of files, but not directories (which --du covers).
+    pub fn with_file_sizes(mut self, file_sizes: bool) -> Self {
+        self.file_sizes = file_sizes;
+        self
+    }
+
+    /// Show the type and permissions of entries before their names.
+    pub fn with_permissions(mut self, permissions: bool) -> Self {
+        self.permissions = permissions;
+        self
+    }
+
+    /// With permissions shown, write them as octal numbers.
+    pub fn with_octal_perms(mut self, octal_perms: bool) -> Self {
+        self.octal_perms = octal_perms;
+        self
+    }
+
+    /// Show the user and group owning entries before their names.
+    pub fn with_owner_columns(mut self, user: bool, group: bool) -> Self {
+        self.user = user;
+        self.group = group;
+        self
+    }
+
+    /// Show the inode and device numbers of entries before their names.
+    pub fn with_inode_columns(mut self, inodes: bool, device: bool) -> Self {
+        self.inodes = inodes;
+        self.device = device;
+        self
+    }
+
+    /// Show the hard link count of entries before their names, and with
+    /// `flag_hardlinks` mark files that have more than one after them.
+    pub fn with_link_counts(mut self, nlink: bool, flag_hardlinks: bool) -> Self {
+        self.nlink = nlink;
+        self.flag_hardlinks = flag_hardlinks;
+        self
+    }
+
+    /// Show when entries were last modified before their names, formatted with
+    /// `time::strftime` or else like `tree -D`.
+    pub fn with_dates(mut self, dates: Option<Option<String>>) -> Self {
+        self.dates = dates;
+        self
+    }
+
+    /// Follow directories with the mix of languages in them by bytes, from
+    /// `language::tally`.
+    pub fn with_lang_bars(
+        mut self,
+        lang_bars: Option<HashMap<PathBuf, Vec<(&'static Language, u64)>>>,
+    ) -> Self {
+        self.lang_bars = lang_bars;
+        self
+    }
+
+    /// Truncate names so tree lines fit in `width` columns.
+    pub fn with_width(mut self, width: Option<usize>) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Print the tree to stdout, buffered.
+    pub fn print(&self, root: &Node) -> io::Result<()> {
+        let mut out = BufWriter::new(io::stdout().lock());
+        self.write(root, &mut out)?;
+        out.flush()
+    }
+
+    /// Write the root line (tree mode only) followed by all of its descendants.
+    pub fn write(&self, root: &Node, out: &mut dyn Write) -> io::Result<()> {
+        if self.grid {
+            return self.write_grid(root, out);
+        }
+
+        if !self.full_path {
+            let size = self.info_text(root);
+            let depth = self.depth_column(0);
+            let root_name = if self.root_path {
+                // As given, unless --absolute asked for canonical paths
+                match self.paths {
+                    PathStyle::Absolute { .. } => self.paths.apply(&root.path),
+                    _ => Cow::Borrowed(root.path.as_path()),
+                }
+                .to_string_lossy()
+                .into_owned()
+            } else {
+                root.name()
+            };
+            let summary = self.file_summary(root);
+            let root_name = self.fit(root_name, depth.len() + size.width() + summary.width());
+            writeln!(out, "{}{}{}{}", depth, size, self.paint(root_name, root), summary)?;
+        }
+
+        self.write_children(root, "", 1, out)
+    }
+
+    /// Write the descendants of `node`, which sits at `depth - 1` below the root.
+    pub fn write_children(
+        &self,
+        node: &Node,
+        prefix: &str,
+        depth: usize,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        if self.max_depth.is_some_and(|max| depth > max) {
+            return Ok(());
+        }
+
+        let children: Vec<&Node> = node
+            .children
+            .iter()
+            .filter(|child| child.is_dir || !self.collapse_files)
+            .collect();
+
+        for (i, child) in children.iter().enumerate() {
+            let is_last = i == children.len() - 1;
+
+            if !(self.files_only && child.is_dir) {
+                self.write_entry(child, prefix, is_last, depth, out)?;
+            }
+
+            if child.is_dir {
+                let child_prefix = self.get_child_prefix(prefix, is_last);
+                self.write_children(child, &child_prefix, depth + 1, out)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_entry(
+        &self,
+        node: &Node,
+        prefix: &str,
+        is_last: bool,
+        depth: usize,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        let size = self.info_text(node);
+        let depth = self.depth_column(depth);
+        let summary = self.file_summary(node);
+
+        if self.full_path && self.print0 {
+            write!(out, "{}\0", self.full_path_text(node))
+        } else if self.full_path {
+            writeln!(out, "{}{}{}{}", depth, size, self.paint(self.full_path_text(node), node), summary)
+        } else {
+            // Print tree format
+            // Like `tree`, links show where they point; like `tree -F`,
+            // executables get a trailing `*`
+            let marker = if let Some(target) = &node.link_target {
+                self.link_marker(target, node)
+            } else if !node.is_dir && kind::is_executable(&node.path) {
+                "*".to_string()
+            } else {
+                String::new()
+            };
+            let connector = self.glyphs.branch.width().max(self.glyphs.last.width());
+            let used = depth.len() + prefix.width() + connector + size.width() + summary.width();
+            let name = self.fit(node.name(), used + marker.width());
+            // The depth goes first so it stays in the same column on every line
+            self.write_line(
+                out,
+                &format!("{}{}", depth, prefix),
+                is_last,
+                &format!("{}{}{}{}", size, self.paint(name, node), marker, summary),
+            )
+        }
+    }
+
+    /// `" -> target"` after the link `node`, the target colored like what it
+    /// points to, or red when that doesn't exist.
+    fn link_marker(&self, target: &Path, node: &Node) -> String {
+        let arrow = format!(" -> {}", target.display());
+        if !self.use_color {
+            arrow
+        } else if node.is_broken_link() {
+            arrow.red().to_string()
+        } else if node.is_dir {
+            format!(" -> {}", target.display().to_string().blue().bold())
+        } else if kind::is_executable(&node.path) {
+            format!(" -> {}", target.display().to_string().green().bold())
+        } else {
+            arrow
+        }
+    }
+
+    /// The path of `node` as written in full-path mode, quoted if asked to.
+    fn full_path_text(&self, node: &Node) -> String {
+        let path = self.paths.apply(&node.path);
+        match self.quote {
+            Some(style) => quote::quote(path.as_os_str(), style).into_owned(),
+            None => path.to_string_lossy().into_owned(),
+        }
+    }
+
+    /// Write every descendant's path in as few rows as fit the width, filling
+    /// columns top to bottom like `ls -C`.
+    fn write_grid(&self, root: &Node, out: &mut dyn Write) -> io::Result<()> {
+        const GAP: usize = 2;
+
+        let cells: Vec<(String, usize)> = root
+            .descendants()
+            .into_iter()
+            .filter(|node| node.is_dir || !self.collapse_files)
+            .map(|node| {
+                let size = self.info_text(node);
+                let path = self.full_path_text(node);
+                let summary = self.file_summary(node);
+                let width = size.width() + path.width() + summary.width();
+                let painted = format!("{}{}{}", size, self.paint(path, node), summary);
+                (painted, width)
+            })
+            .collect();
+        if cells.is_empty() {
+            return Ok(());
+        }
+
+        // Like ls, assume 80 columns when the width is unknown
+        let width = self.width.unwrap_or(80);
+        let mut rows = 1;
+        let column_widths = loop {
+            let columns = cells.len().div_ceil(rows);
+            let widths: Vec<usize> = (0..columns)
+                .map(|column| {
+                    cells[column * rows..((column + 1) * rows).min(cells.len())]
+                        .iter()
+                        .map(|(_, width)| *width)
+                        .max()
+                        .unwrap_or(0)
+                })
+                .collect();
+            let total = widths.iter().sum::<usize>() + GAP * (columns - 1);
+            if total <= width || columns == 1 {
+                break widths;
+            }
+            rows += 1;
+        };
+
+        for row in 0..rows {
+            let mut line = String::new();
+            for (column, column_width) in column_widths.iter().enumerate() {
+                let Some((text, text_width)) = cells.get(column * rows + row) else {
+                    break;
+                };
+                line.push_str(text);
+                // No padding after the last cell of the row
+                if cells.get((column + 1) * rows + row).is_some() {
+                    line.push_str(&" ".repeat(column_width - text_width + GAP));
+                }
+            }
+            writeln!(out, "{}", line)?;
+        }
+        Ok(())
+    }
+
+    /// Write already formatted text behind the branch connector for `prefix`.
+    pub fn write_line(
+        &self,
+        out: &mut dyn Write,
+        prefix: &str,
+        is_last: bool,
+        text: &str,
+    ) -> io::Result<()> {
+        let connector = if is_last {
+            &self.glyphs.last
+        } else {
+            &self.glyphs.branch
+        };
+        writeln!(out, "{}{}{}", prefix, connector, text)
+    }
+
+    pub fn get_child_prefix(&self, prefix: &str, is_last: bool) -> String {
+        if self.full_path {
+            String::new() // No prefix needed for full path mode
+        } else {
+            let extension = if is_last {
+                &self.glyphs.indent
+            } else {
+                &self.glyphs.vertical
+            };
+            format!("{}{}", prefix, extension)
+        }
+    }
+
+    /// Color an entry's name, and link it to its file with --hyperlinks.
+    fn paint(&self, text: String, node: &Node) -> String {
+        let painted = self.colorize(text, node);
+        if self.hyperlinks {
+            term::hyperlink(&painted, &node.path)
+        } else {
+            painted
+        }
+    }
+
+    /// Color an entry's name: directories blue, executables green, links cyan
+    /// and broken links red, tracked files by commit age with --blame-heat, or
+    /// when over the highlight threshold, files yellow and directories bold
+    /// red. Pattern matches in file names are bold red, like `grep --color`.
+    fn colorize(&self, text: String, node: &Node) -> String {
+        if !self.use_color {
+            return text;
+        }
+
+        let matches = match &self.highlighter {
+            Some(highlighter) if !node.is_dir => self.match_ranges(highlighter, &text, node),
+            _ => Vec::new(),
+        };
+        if matches.is_empty() {
+            return self.style(&text, node).to_string();
+        }
+
+        let mut out = String::new();
+        let mut end = 0;
+        for range in matches {
+            out.push_str(&self.style(&text[end..range.start], node).to_string());
+            out.push_str(&text[range.clone()].red().bold().to_string());
+            end = range.end;
+        }
+        out.push_str(&self.style(&text[end..], node).to_string());
+        out
+    }
+
+    fn style(&self, text: &str, node: &Node) -> ColoredString {
+        if node.is_broken_link() {
+            return text.red().bold();
+        }
+        if node.link_target.is_some() {
+            return text.cyan().bold();
+        }
+        if let Some(times) = &self.blame_heat
+            && !node.is_dir
+            && let Some(time) = times.get(&node.path)
+        {
+            return heat(text, *time);
+        }
+
+        let oversized = self.highlight_size.is_some_and(|limit| node.size > limit);
+        match (node.is_dir, oversized) {
+            (true, true) => text.red().bold(),
+            (true, false) => text.blue().bold(),
+            (false, true) => text.yellow(),
+            (false, false) if kind::is_executable(&node.path) => text.green().bold(),
+            (false, false) => text.normal(),
+        }
+    }
+
+    /// Matched ranges of the file name within `text`, which is either the full
+    /// path ending in it or the name itself, possibly truncated.
+    fn match_ranges(
+        &self,
+        highlighter: &NameHighlighter,
+        text: &str,
+        node: &Node,
+    ) -> Vec<std::ops::Range<usize>> {
+        let name = node.name();
+        let (offset, visible) = if let Some(start) = text.strip_suffix(name.as_str()) {
+            (start.len(), name.len())
+        } else if let Some(kept) = text.strip_suffix('…')
+            && name.starts_with(kept)
+        {
+            (0, kept.len())
+        } else {
+            return Vec::new();
+        };
+
+        highlighter
+            .ranges(&name)
+            .into_iter()
+            .filter(|range| range.start < visible)
+            .map(|range| offset + range.start..offset + range.end.min(visible))
+            .collect()
+    }
+
+    /// Truncate `name` to what is left of the width after `used` columns.
+    fn fit(&self, name: String, used: usize) -> String {
+        match self.width {
+            Some(width) => term::truncate(&name, width.saturating_sub(used).max(1)),
+            None => name,
+        }
+    }
+
+    /// `" (N dirs, M files)"` for everything below a directory with --counts,
+    /// then `" (N files, SIZE)"` for the files directly in it when they are
+    /// collapsed; `" [1920x1080]"` or `" [12 pages]"` after files with --media-info
+    /// or --doc-info, `" [1.2M on disk, 3.4x]"` with --on-disk and `" [2 links]"`
+    /// with --flag-hardlinks.
+    fn file_summary(&self, node: &Node) -> String {
+        let mut summary = if node.is_dir {
+            self.dir_summary(node)
+        } else {
+            let media = || media::probe(&node.path).map(|info| info.to_string());
+            let doc = || docinfo::probe(&node.path).map(|info| info.to_string());
+            let info = self.media_info.then(media).flatten();
+            let info = info.or_else(|| self.doc_info.then(doc).flatten());
+            let mut summary = info.map(|info| format!(" [{}]", info)).unwrap_or_default();
+            if self.on_disk && let Some(usage) = ondisk::probe(&node.path) {
+                summary.push_str(&format!(" [{}]", usage));
+            }
+            // Directories always have several, for `.` and `..`
+            if self.flag_hardlinks
+                && let Some(count) = meta::link_count(&node.path).filter(|&count| count > 1)
+            {
+                summary.push_str(&format!(" [{} links]", count));
+            }
+            summary
+        };
+        for labels in [&self.git_owners, &self.code_owners, &self.mounts] {
+            if let Some(label) = labels.as_ref().and_then(|labels| labels.get(&node.path)) {
+                summary.push_str(&format!(" [{}]", label));
+            }
+        }
+        summary
+    }
+
+    fn dir_summary(&self, node: &Node) -> String {
+        let mut summary = String::new();
+        if self.counts {
+            let descendants = node.descendants();
+            let dirs = descendants.iter().filter(|n| n.is_dir).count();
+            let files = descendants.len() - dirs;
+            summary = format!(
+                " ({} {}, {} {})",
+                dirs,
+                if dirs == 1 { "dir" } else { "dirs" },
+                files,
+                if files == 1 { "file" } else { "files" }
+            );
+        }
+        if self.collapse_files {
+            summary.push_str(&self.collapsed_summary(node));
+        }
+        summary.push_str(&self.lang_bar(node));
+        summary
+    }
+
+    /// `" [██████▓▓▒░ Rust 64%, TOML 21%, Shell 9%]"`: the languages of the
+    /// files below a directory by bytes, like GitHub's language bar. Each
+    /// language gets its own color, or a lighter shade down the ranks without;
+    /// nothing for directories without any.
+    fn lang_bar(&self, node: &Node) -> String {
+        const CELLS: u64 = 10;
+        const SHADES: [char; 4] = ['█', '▓', '▒', '░'];
+
+        let Some(bytes) = self.lang_bars.as_ref().and_then(|bars| bars.get(&node.path)) else {
+            return String::new();
+        };
+        let total: u64 = bytes.iter().map(|(_, size)| size).sum();
+        if total == 0 {
+            return String::new();
+        }
+
+        // Cells go to where each language's running total ends, so they always
+        // add up to the whole bar
+        let mut bar = String::new();
+        let mut done = 0;
+        let mut cells = 0;
+        for (rank, (language, size)) in bytes.iter().enumerate() {
+            done += size;
+            let end = (done * CELLS + total / 2) / total;
+            let count = (end - cells) as usize;
+            cells = end;
+            if count == 0 {
+                continue;
+            }
+            if self.use_color {
+                let (r, g, b) = language.color;
+                bar.push_str(&SHADES[0].to_string().repeat(count).truecolor(r, g, b).to_string());
+            } else {
+                bar.push_str(&SHADES[rank.min(SHADES.len() - 1)].to_string().repeat(count));
+            }
+        }
+        let top: Vec<String> = bytes
+            .iter()
+            .take(3)
+            .map(|(language, size)| format!("{} {}%", language.name, size * 100 / total))
+            .collect();
+        format!(" [{} {}]", bar, top.join(", "))
+    }
+
+    fn collapsed_summary(&self, node: &Node) -> String {
+        let files: Vec<&Node> = node.children.iter().filter(|c| !c.is_dir).collect();
+        match files.len() {
+            0 => String::new(),
+            count => format!(
+                " ({} {}, {})",
+                count,
+                if count == 1 { "file" } else { "files" },
+                format_size(files.iter().map(|file| file.size).sum())
+            ),
+        }
+    }
+
+    fn depth_column(&self, depth: usize) -> String {
+        if self.show_depth {
+            format!("{:>2}  ", depth)
+        } else {
+            String::new()
+        }
+    }
+
+    /// The bracketed columns before `node`'s name, like `tree -p -s` writes
+    /// `[-rw-r--r--  4.0K]`: the inode and device numbers with --inodes and
+    /// --device, the mode with -p (`0644` with --octal-perms), the link count
+    /// with --nlink, the user and group with -u and -g, the size of every
+    /// entry with --du or of files with -s, then the modification time with -D.
+    fn info_text(&self, node: &Node) -> String {
+        let mut columns = Vec::new();
+        if self.inodes || self.device {
+            let numbers = meta::inode_and_device(&node.path);
+            // Right-aligned like `tree --inodes --device`
+            if self.inodes {
+                columns.push(match numbers {
+                    Some((inode, _)) => format!("{:>7}", inode),
+                    None => format!("{:>7}", "?"),
+                });
+            }
+            if self.device {
+                columns.push(match numbers {
+                    Some((_, device)) => format!("{:>3}", device),
+                    None => format!("{:>3}", "?"),
+                });
+            }
+        }
+        if self.permissions {
+            columns.push(if self.octal_perms {
+                meta::octal_permissions(&node.path)
+            } else {
+                meta::permissions(&node.path)
+            });
+        }
+        if self.nlink {
+            let count = meta::link_count(&node.path);
+            columns.push(format!("{:>3}", count.map_or("?".to_string(), |n| n.to_string())));
+        }
+        if self.user || self.group {
+            let (user, group) = meta::user_and_group(&node.path)
+                .unwrap_or_else(|| ("?".to_string(), "?".to_string()));
+            // Padded like `tree -u -g`, so sizes after them line up
+            if self.user {
+                columns.push(format!("{:<8}", user));
+            }
+            if self.group {
+                columns.push(format!("{:<8}", group));
+            }
+        }
+        if self.show_size || (self.file_sizes && !node.is_dir) {
+            columns.push(self.size_column(node.size));
+        } else if self.file_sizes {
+            // Blank for directories, so the columns after it line up
+            columns.push(" ".repeat(self.size_width()));
+        }
+        if let Some(format) = &self.dates {
+            // Directories with --dir-mtime newest carry the time to show
+            let mtime = node
+                .mtime
+                .or_else(|| time::of_path(&node.path));
+            columns.push(match (mtime, format) {
+                (Some(mtime), Some(format)) => time::strftime(mtime, format),
+                (Some(mtime), None) => time::tree_date(mtime),
+                (None, _) => "?".to_string(),
+            });
+        }
+        if columns.is_empty() {
+            String::new()
+        } else {
+            format!("[{}]  ", columns.join(" "))
+        }
+    }
+
+    fn size_column(&self, size: u64) -> String {
+        format!("{:>width$}", format_size(size), width = self.size_width())
+    }
+
+    fn size_width(&self) -> usize {
+        // Wide enough for most exact sizes too, as `tree -s` does
+        if tree::size_style() == Some(SizeStyle::Bytes) { 11 } else { 5 }
+    }
+}
+
+/// From red for files committed to this week, through yellow (this month),
+/// green (half a year) and cyan (a year), to gray for anything older.
+fn heat(text: &str, committed: SystemTime) -> ColoredString {
+    const DAY: u64 = 24 * 60 * 60;
+    let age = SystemTime::now().duration_since(committed).unwrap_or_default();
+    if age < Duration::from_secs(7 * DAY) {
+        text.red().bold()
+    } else if age < Duration::from_secs(30 * DAY) {
+        text.yellow()
+    } else if age < Duration::from_secs(182 * DAY) {
+        text.green()
+    } else if age < Duration::from_secs(365 * DAY) {
+        text.cyan()
+    } else {
+        text.bright_black()
+    }
+}
@@ -0,0 +1,137 @@
+use std::borrow::Cow;
+use std::ffi::OsStr;
+use std::fmt::Write as _;
+
+use crate::cli::QuoteStyle;
+
+/// Quote `text` so the target shell reads it back as exactly one word,
+/// leaving plain names untouched.
+pub fn quote(text: &OsStr, style: QuoteStyle) -> Cow<'_, str> {
+    match text.to_str() {
+        Some(text) => quote_str(text, style),
+        None => Cow::Owned(quote_escaped(&pieces(text), style)),
+    }
+}
+
+fn quote_str(text: &str, style: QuoteStyle) -> Cow<'_, str> {
+    match style {
+        QuoteStyle::Posix => shell_words::quote(text),
+        QuoteStyle::Powershell => {
+            let is_plain = !text.is_empty()
+                && !text.starts_with('-')
+                && text
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-_./\\:".contains(c));
+            if is_plain {
+                return Cow::Borrowed(text);
+            }
+
+            // Single-quoted strings are literal; quotes (including the
+            // typographic ones PowerShell also accepts) are escaped by doubling
+            let mut quoted = String::with_capacity(text.len() + 2);
+            quoted.push('\'');
+            for c in text.chars() {
+                if matches!(c, '\'' | '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}') {
+                    quoted.push(c);
+                }
+                quoted.push(c);
+            }
+            quoted.push('\'');
+            Cow::Owned(quoted)
+        }
+    }
+}
+
+/// Part of a name that isn't valid Unicode throughout.
+enum Piece {
+    Char(char),
+    /// A byte outside UTF-8, on Unix
+    #[cfg(unix)]
+    Byte(u8),
+    /// An unpaired UTF-16 surrogate, on Windows
+    #[cfg(windows)]
+    Surrogate(u16),
+}
+
+#[cfg(unix)]
+fn pieces(text: &OsStr) -> Vec<Piece> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut pieces = Vec::new();
+    for chunk in text.as_bytes().utf8_chunks() {
+        pieces.extend(chunk.valid().chars().map(Piece::Char));
+        pieces.extend(chunk.invalid().iter().copied().map(Piece::Byte));
+    }
+    pieces
+}
+
+#[cfg(windows)]
+fn pieces(text: &OsStr) -> Vec<Piece> {
+    use std::os::windows::ffi::OsStrExt;
+
+    char::decode_utf16(text.encode_wide())
+        .map(|unit| match unit {
+            Ok(c) => Piece::Char(c),
+            Err(err) => Piece::Surrogate(err.unpaired_surrogate()),
+        })
+        .collect()
+}
+
+#[cfg(not(any(unix, windows)))]
+fn pieces(text: &OsStr) -> Vec<Piece> {
+    text.to_string_lossy().chars().map(Piece::Char).collect()
+}
+
+/// Quote a name that single quotes can't carry, in the form of string that
+/// takes escapes: `$'...'` for POSIX shells and `"..."` for PowerShell.
+fn quote_escaped(pieces: &[Piece], style: QuoteStyle) -> String {
+    let mut quoted = String::new();
+    match style {
+        QuoteStyle::Posix => {
+            quoted.push_str("$'");
+            for piece in pieces {
+                match *piece {
+                    Piece::Char(c @ ('\\' | '\'')) => {
+                        quoted.push('\\');
+                        quoted.push(c);
+                    }
+                    Piece::Char(c) if c.is_ascii_control() => {
+                        let _ = write!(quoted, "\\x{:02x}", c as u8);
+                    }
+                    Piece::Char(c) => quoted.push(c),
+                    #[cfg(unix)]
+                    Piece::Byte(byte) => {
+                        let _ = write!(quoted, "\\x{:02x}", byte);
+                    }
+                    #[cfg(windows)]
+                    Piece::Surrogate(unit) => {
+                        let _ = write!(quoted, "\\u{:04x}", unit);
+                    }
+                }
+            }
+            quoted.push('\'');
+        }
+        QuoteStyle::Powershell => {
+            quoted.push('"');
+            for piece in pieces {
+                match *piece {
+                    // Including the typographic double quotes PowerShell accepts
+                    Piece::Char(c @ ('"' | '`' | '$' | '\u{201C}' | '\u{201D}' | '\u{201E}')) => {
+                        quoted.push('`');
+                        quoted.push(c);
+                    }
+                    Piece::Char(c) => quoted.push(c),
+                    // PowerShell strings can't hold bytes outside UTF-8
+                    #[cfg(unix)]
+                    Piece::Byte(_) => quoted.push_str("`u{FFFD}"),
+                    #[cfg(windows)]
+                    Piece::Surrogate(unit) => {
+                        let _ = write!(quoted, "`u{{{:04X}}}", unit);
+                    }
+                }
+            }
+            quoted.push('"');
+        }
+    }
+    quoted
+}
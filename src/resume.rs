@@ -0,0 +1,186 @@
+use anyhow::{Context, Result, bail};
+use clap::ValueEnum;
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+use crate::cli::HashAlgorithm;
+use crate::hash;
+
+/// The first line of a state file, followed by the algorithm.
+const HEADER: &str = "# treee resume state,";
+
+/// A file's digest, and what the file looked like when it was taken.
+#[derive(Clone)]
+struct Entry {
+    size: u64,
+    mtime: u128,
+    digest: Vec<u8>,
+}
+
+/// Digests checkpointed for --resume: every file hashed is appended to the
+/// state file at once, so a scan cut short can pick up where it stopped, and
+/// files that kept their size and mtime since aren't read again.
+///
+/// The file holds one `<hex digest>\t<size>\t<mtime ns>\t<absolute path>` line
+/// per file, with backslashes, tabs and newlines in paths escaped.
+pub struct ResumeState {
+    path: PathBuf,
+    /// From earlier runs
+    known: HashMap<PathBuf, Entry>,
+    /// Reused or hashed in this one, which is all the state keeps at the end
+    current: Mutex<HashMap<PathBuf, Entry>>,
+    file: Mutex<File>,
+}
+
+impl ResumeState {
+    /// Load the state at `path`, or start one when it doesn't exist yet.
+    pub fn open(path: &Path, algorithm: HashAlgorithm) -> Result<Self> {
+        let header = format!("{} {}\n", HEADER, name(algorithm));
+        let (known, cut_off) = match fs::read_to_string(path) {
+            Ok(text) => {
+                let Some(entries) = text.strip_prefix(&header) else {
+                    bail!(
+                        "'{}' is not a --resume state for --manifest={}",
+                        path.display(),
+                        name(algorithm)
+                    );
+                };
+                // A line cut off by an interruption is just hashed again
+                (entries.lines().filter_map(parse_line).collect(), !text.ends_with('\n'))
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                fs::write(path, &header)
+                    .with_context(|| format!("Failed to create '{}'", path.display()))?;
+                (HashMap::new(), false)
+            }
+            Err(err) => {
+                return Err(err).with_context(|| format!("Failed to read '{}'", path.display()));
+            }
+        };
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open '{}'", path.display()))?;
+        // Start on a line of its own after one that was cut off
+        if cut_off {
+            file.write_all(b"\n")?;
+        }
+        Ok(Self {
+            path: path.to_path_buf(),
+            known,
+            current: Mutex::new(HashMap::new()),
+            file: Mutex::new(file),
+        })
+    }
+
+    /// The digest recorded for the file at `path`, if it hasn't changed since.
+    pub fn lookup(&self, path: &Path, metadata: &fs::Metadata) -> Option<Vec<u8>> {
+        let key = std::path::absolute(path).ok()?;
+        let entry = self.known.get(&key)?;
+        if entry.size != metadata.len() || entry.mtime != mtime(metadata)? {
+            return None;
+        }
+        self.current.lock().unwrap().insert(key, entry.clone());
+        Some(entry.digest.clone())
+    }
+
+    /// Checkpoint the digest of the file at `path`, which looked like
+    /// `metadata` before it was read.
+    pub fn record(&self, path: &Path, metadata: &fs::Metadata, digest: &[u8]) {
+        let (Ok(key), Some(mtime)) = (std::path::absolute(path), mtime(metadata)) else {
+            return;
+        };
+        let entry = Entry {
+            size: metadata.len(),
+            mtime,
+            digest: digest.to_vec(),
+        };
+        // One write per line, so an interruption cuts off at most the last
+        let _ = self.file.lock().unwrap().write_all(line(&key, &entry).as_bytes());
+        self.current.lock().unwrap().insert(key, entry);
+    }
+
+    /// Rewrite the state with only the files of this run, dropping those that
+    /// are gone or changed.
+    pub fn compact(&self, algorithm: HashAlgorithm) -> Result<()> {
+        // Named after the whole file, so a state file called `*.tmp` isn't its
+        // own temporary file
+        let mut temp = self.path.clone().into_os_string();
+        temp.push(format!(".tmp-{}", std::process::id()));
+        let temp = PathBuf::from(temp);
+        let write = || -> io::Result<()> {
+            let mut out = BufWriter::new(File::create(&temp)?);
+            writeln!(out, "{} {}", HEADER, name(algorithm))?;
+            let current = self.current.lock().unwrap();
+            let mut paths: Vec<_> = current.keys().collect();
+            paths.sort();
+            for path in paths {
+                out.write_all(line(path, &current[path]).as_bytes())?;
+            }
+            out.into_inner()?.sync_all()?;
+            fs::rename(&temp, &self.path)
+        };
+        write().with_context(|| format!("Failed to write '{}'", self.path.display()))
+    }
+}
+
+fn name(algorithm: HashAlgorithm) -> String {
+    algorithm
+        .to_possible_value()
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default()
+}
+
+fn mtime(metadata: &fs::Metadata) -> Option<u128> {
+    Some(metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?.as_nanos())
+}
+
+fn line(path: &Path, entry: &Entry) -> String {
+    let path = path
+        .to_string_lossy()
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n");
+    format!("{}\t{}\t{}\t{}\n", hash::hex(&entry.digest), entry.size, entry.mtime, path)
+}
+
+fn parse_line(line: &str) -> Option<(PathBuf, Entry)> {
+    let mut fields = line.splitn(4, '\t');
+    let digest = unhex(fields.next()?)?;
+    let size = fields.next()?.parse().ok()?;
+    let mtime = fields.next()?.parse().ok()?;
+    let path = unescape(fields.next()?)?;
+    Some((PathBuf::from(path), Entry { size, mtime, digest }))
+}
+
+fn unhex(text: &str) -> Option<Vec<u8>> {
+    if text.is_empty() || !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn unescape(text: &str) -> Option<String> {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        out.push(match chars.next()? {
+            '\\' => '\\',
+            't' => '\t',
+            'n' => '\n',
+            _ => return None,
+        });
+    }
+    Some(out)
+}
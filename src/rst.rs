@@ -0,0 +1,64 @@
+use std::io::{self, BufWriter, Write};
+
+use crate::cli::RstStyle;
+use crate::printer::TreePrinter;
+use crate::tree::{Node, format_size};
+
+/// Print the tree as reStructuredText, ready to include in Sphinx docs.
+pub fn print(root: &Node, with_size: bool, style: RstStyle) -> io::Result<()> {
+    let mut out = BufWriter::new(io::stdout().lock());
+    match style {
+        RstStyle::List => write_list(&mut out, root, with_size, 0)?,
+        RstStyle::Literal => {
+            let mut tree = Vec::new();
+            TreePrinter::new(false, false, with_size).write(root, &mut tree)?;
+            writeln!(out, "::")?;
+            writeln!(out)?;
+            for line in String::from_utf8_lossy(&tree).lines() {
+                writeln!(out, "    {}", line)?;
+            }
+        }
+    }
+    out.flush()
+}
+
+/// One bullet per entry. reStructuredText wants a nested list set off from
+/// its parent item by blank lines, and indented to the item's text.
+fn write_list(out: &mut impl Write, node: &Node, with_size: bool, depth: usize) -> io::Result<()> {
+    let indent = "  ".repeat(depth);
+    let mut name = node.name();
+    if node.is_dir {
+        name.push('/');
+    }
+    write!(out, "{}- {}", indent, literal(&name))?;
+    if with_size {
+        write!(out, " ({})", format_size(node.size))?;
+    }
+    writeln!(out)?;
+
+    if !node.children.is_empty() {
+        writeln!(out)?;
+        for child in &node.children {
+            write_list(out, child, with_size, depth + 1)?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// An inline literal, so names aren't read as markup. Those containing a
+/// backtick can't be one, and get their markup characters escaped instead.
+fn literal(text: &str) -> String {
+    if !text.contains('`') {
+        return format!("``{}``", text);
+    }
+
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '`' | '*' | '_' | '|') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
@@ -0,0 +1,57 @@
+use std::path::Path;
+
+/// Names that are credentials whatever their contents.
+const NAMES: &[&str] = &[
+    "id_rsa",
+    "id_dsa",
+    "id_ecdsa",
+    "id_ed25519",
+    ".env",
+    ".netrc",
+    "_netrc",
+    ".pgpass",
+    ".htpasswd",
+    ".git-credentials",
+    ".pypirc",
+    "credentials",
+    "credentials.json",
+    "secrets.json",
+    "secrets.yml",
+    "secrets.yaml",
+];
+
+/// Extensions of key stores, certificates with private keys and password
+/// databases.
+const EXTENSIONS: &[&str] = &["pem", "key", "p12", "pfx", "jks", "keystore", "kdbx", "ppk"];
+
+/// `.env.*` files that are templates to copy rather than the real thing.
+const ENV_TEMPLATES: &[&str] = &["example", "sample", "template", "dist"];
+
+/// Whether a file called `name` probably holds credentials: SSH private keys,
+/// `.env` files, key stores and the like.
+pub fn looks_like_secret(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    if NAMES.contains(&name.as_str()) {
+        return true;
+    }
+    if let Some(suffix) = name.strip_prefix(".env.") {
+        return !ENV_TEMPLATES.contains(&suffix);
+    }
+    Path::new(&name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| EXTENSIONS.contains(&ext))
+}
+
+/// Whether users other than the owner may read the file at `path`, following
+/// links. Always false where there are no Unix permissions to go by.
+#[cfg(unix)]
+pub fn is_exposed(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(path).is_ok_and(|meta| meta.permissions().mode() & 0o044 != 0)
+}
+
+#[cfg(not(unix))]
+pub fn is_exposed(_path: &Path) -> bool {
+    false
+}
@@ -0,0 +1,176 @@
+use anyhow::Result;
+use glob::Pattern;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::cli::ListArgs;
+use crate::tree::{self, Node};
+use crate::watch;
+
+/// How often the tree is checked for changes between queries.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// One line read from stdin, e.g. `{"id": 1, "method": "children", "path": "src"}`.
+/// The `id` is echoed back as is, so answers can be matched to queries.
+#[derive(Deserialize)]
+struct Query {
+    #[serde(default)]
+    id: Value,
+    #[serde(flatten)]
+    request: Request,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "method", rename_all = "lowercase")]
+enum Request {
+    /// The entries directly inside `path`
+    Children {
+        #[serde(default)]
+        path: String,
+    },
+    /// Every entry whose name matches the glob `pattern`
+    Search { pattern: String },
+}
+
+#[derive(Serialize)]
+struct Answer<'a> {
+    id: &'a Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    entries: Option<Vec<Entry>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Entry {
+    name: String,
+    /// Relative to the root, with `/` separators, as queries take them
+    path: String,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size: Option<u64>,
+}
+
+/// Sent unprompted whenever the tree changed, so clients know to query again.
+#[derive(Serialize)]
+struct Event {
+    event: &'static str,
+}
+
+/// Keep the scanned tree in memory and answer newline-delimited JSON queries
+/// from stdin on stdout until stdin is closed, rescanning in the background
+/// whenever entries are added, removed or modified.
+pub fn run(args: &ListArgs) -> Result<bool> {
+    let root = args.path.clone();
+    let scan = tree::scan(&root, &args.scan, args.du)?;
+    let tree = Arc::new(Mutex::new(scan.root));
+
+    {
+        let tree = Arc::clone(&tree);
+        let args = args.clone();
+        thread::spawn(move || watch_tree(&args, &tree));
+    }
+
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let (id, result) = match serde_json::from_str::<Query>(&line) {
+            Ok(query) => {
+                let result = answer(&tree.lock().unwrap(), &root, &query.request, args.du);
+                (query.id, result)
+            }
+            Err(err) => (Value::Null, Err(format!("Invalid query: {}", err))),
+        };
+        let (entries, error) = match result {
+            Ok(entries) => (Some(entries), None),
+            Err(error) => (None, Some(error)),
+        };
+        send(&Answer { id: &id, entries, error })?;
+    }
+    Ok(false)
+}
+
+fn answer(tree: &Node, root: &Path, request: &Request, du: bool) -> Result<Vec<Entry>, String> {
+    match request {
+        Request::Children { path } => {
+            let node = find(tree, path).ok_or_else(|| format!("'{}' is not in the tree", path))?;
+            Ok(node.children.iter().map(|child| entry(child, root, du)).collect())
+        }
+        Request::Search { pattern } => {
+            let pattern = Pattern::new(pattern)
+                .map_err(|err| format!("Invalid pattern '{}': {}", pattern, err))?;
+            Ok(tree
+                .descendants()
+                .into_iter()
+                .filter(|node| pattern.matches(&node.name()))
+                .map(|node| entry(node, root, du))
+                .collect())
+        }
+    }
+}
+
+/// The node at `path` below the root; empty or `.` is the root itself.
+fn find<'a>(tree: &'a Node, path: &str) -> Option<&'a Node> {
+    path.split(['/', '\\'])
+        .filter(|part| !part.is_empty() && *part != ".")
+        .try_fold(tree, |node, part| {
+            node.children.iter().find(|child| child.name() == part)
+        })
+}
+
+fn entry(node: &Node, root: &Path, du: bool) -> Entry {
+    let relative = node.path.strip_prefix(root).unwrap_or(&node.path);
+    let path = relative
+        .components()
+        .map(|part| part.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    Entry {
+        name: node.name(),
+        path,
+        kind: if node.is_dir { "directory" } else { "file" },
+        size: du.then_some(node.size),
+    }
+}
+
+/// Rescan into `tree` whenever a poll finds the entries changed. Runs until
+/// the process exits; failed polls keep the last good tree.
+fn watch_tree(args: &ListArgs, tree: &Mutex<Node>) {
+    let mut last = watch::snapshot(&args.path, &args.scan).ok();
+    loop {
+        thread::sleep(POLL_INTERVAL);
+        let Ok(current) = watch::snapshot(&args.path, &args.scan) else {
+            continue;
+        };
+        if last.as_ref() == Some(&current) {
+            continue;
+        }
+        let Ok(scan) = tree::scan(&args.path, &args.scan, args.du) else {
+            continue;
+        };
+        *tree.lock().unwrap() = scan.root;
+        last = Some(current);
+        if send(&Event { event: "changed" }).is_err() {
+            return;
+        }
+    }
+}
+
+/// Write one JSON line to stdout. Answers and events come from different
+/// threads, so each line is written under the stdout lock in one go.
+fn send(message: &impl Serialize) -> io::Result<()> {
+    let mut line = serde_json::to_vec(message)?;
+    line.push(b'\n');
+    let mut out = io::stdout().lock();
+    out.write_all(&line)?;
+    out.flush()
+}
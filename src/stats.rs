@@ -0,0 +1,77 @@
+use std::io::{self, BufWriter, Write};
+
+use crate::cli::StatsKey;
+use crate::tree::{Node, format_size};
+
+#[derive(Default, Clone)]
+struct Row {
+    dirs: usize,
+    files: usize,
+    /// Bytes of the files in this group; directory sizes already include
+    /// their contents, so adding them would count everything twice
+    size: u64,
+}
+
+impl Row {
+    fn add(&mut self, node: &Node) {
+        if node.is_dir {
+            self.dirs += 1;
+        } else {
+            self.files += 1;
+            self.size += node.size;
+        }
+    }
+}
+
+/// Print a table of entry counts and sizes per group, with a total row.
+pub fn print(root: &Node, key: StatsKey) -> io::Result<()> {
+    let rows = match key {
+        StatsKey::Depth => by_depth(root),
+    };
+
+    let mut total = Row::default();
+    for (_, row) in &rows {
+        total.dirs += row.dirs;
+        total.files += row.files;
+        total.size += row.size;
+    }
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    writeln!(out, "{:>8}  {:>8}  {:>8}  {:>6}", "depth", "dirs", "files", "size")?;
+    for (label, row) in &rows {
+        write_row(&mut out, label, row)?;
+    }
+    write_row(&mut out, "total", &total)?;
+    out.flush()
+}
+
+/// One row per level below the root, from 1 to the deepest entry.
+fn by_depth(root: &Node) -> Vec<(String, Row)> {
+    fn visit(node: &Node, depth: usize, rows: &mut Vec<Row>) {
+        for child in &node.children {
+            if rows.len() < depth {
+                rows.resize(depth, Row::default());
+            }
+            rows[depth - 1].add(child);
+            visit(child, depth + 1, rows);
+        }
+    }
+
+    let mut rows = Vec::new();
+    visit(root, 1, &mut rows);
+    rows.into_iter()
+        .enumerate()
+        .map(|(i, row)| ((i + 1).to_string(), row))
+        .collect()
+}
+
+fn write_row(out: &mut impl Write, label: &str, row: &Row) -> io::Result<()> {
+    writeln!(
+        out,
+        "{:>8}  {:>8}  {:>8}  {:>6}",
+        label,
+        row.dirs,
+        row.files,
+        format_size(row.size)
+    )
+}
@@ -0,0 +1,132 @@
+use anyhow::Result;
+use std::io::{self, BufWriter, Write};
+
+use crate::cli::PruneMetric;
+use crate::tree::{self, Node, format_size};
+
+/// How much of `metric` leaving out `node` would save.
+fn measure(node: &Node, metric: PruneMetric) -> u64 {
+    match metric {
+        PruneMetric::Entries => 1 + node.descendants().len() as u64,
+        PruneMetric::Size => node.size,
+    }
+}
+
+fn format_amount(amount: u64, metric: PruneMetric) -> String {
+    match metric {
+        PruneMetric::Entries => amount.to_string(),
+        PruneMetric::Size => format_size(amount),
+    }
+}
+
+/// Parse `TARGET` into an absolute amount, resolving percentages against `total`.
+fn parse_target(target: &str, total: u64, metric: PruneMetric) -> Result<u64> {
+    if let Some(percent) = target.trim().strip_suffix('%') {
+        let percent: f64 = percent
+            .trim()
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid percentage '{}'", target))?;
+        return Ok((total as f64 * percent / 100.0) as u64);
+    }
+
+    match metric {
+        PruneMetric::Entries => target
+            .trim()
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid entry count '{}'", target)),
+        PruneMetric::Size => tree::parse_size(target).map_err(anyhow::Error::msg),
+    }
+}
+
+/// Print the fewest directories whose exclusion brings the scan within
+/// `target`, and what leaving them out would save.
+///
+/// Any directory saves at most as much as its top-level ancestor, so the
+/// fewest directories are always found among the root's children, largest
+/// first. Each pick is then narrowed to its largest subdirectory for as long
+/// as the target is still met, so the suggestion is as specific as possible.
+pub fn print(root: &Node, target: &str, metric: PruneMetric) -> Result<()> {
+    let total = measure(root, metric) - if metric == PruneMetric::Entries { 1 } else { 0 };
+    let limit = parse_target(target, total, metric)?;
+    let unit = match metric {
+        PruneMetric::Entries => " entries",
+        PruneMetric::Size => "",
+    };
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    if total <= limit {
+        writeln!(
+            out,
+            "Already within the target: {}{} (target {})",
+            format_amount(total, metric),
+            unit,
+            format_amount(limit, metric)
+        )?;
+        return Ok(out.flush()?);
+    }
+
+    let mut candidates: Vec<&Node> = root.children.iter().filter(|c| c.is_dir).collect();
+    candidates.sort_by_key(|dir| std::cmp::Reverse(measure(dir, metric)));
+
+    let mut chosen = Vec::new();
+    let mut removed = 0;
+    for dir in candidates {
+        if total - removed <= limit {
+            break;
+        }
+        removed += measure(dir, metric);
+        chosen.push(dir);
+    }
+
+    if total - removed > limit {
+        writeln!(
+            out,
+            "Excluding every directory still leaves {}{} in files directly under the root (target {})",
+            format_amount(total - removed, metric),
+            unit,
+            format_amount(limit, metric)
+        )?;
+        return Ok(out.flush()?);
+    }
+
+    for pick in &mut chosen {
+        while let Some(child) = pick
+            .children
+            .iter()
+            .filter(|c| c.is_dir)
+            .max_by_key(|c| measure(c, metric))
+        {
+            let narrowed = removed - measure(pick, metric) + measure(child, metric);
+            if total - narrowed > limit {
+                break;
+            }
+            removed = narrowed;
+            *pick = child;
+        }
+    }
+
+    writeln!(
+        out,
+        "Excluding {} {} removes {}% of {} ({} -> {}, target {}):",
+        chosen.len(),
+        if chosen.len() == 1 { "directory" } else { "directories" },
+        removed * 100 / total.max(1),
+        match metric {
+            PruneMetric::Entries => "entries",
+            PruneMetric::Size => "the size",
+        },
+        format_amount(total, metric),
+        format_amount(total - removed, metric),
+        format_amount(limit, metric)
+    )?;
+    for dir in chosen {
+        let relative = dir.path.strip_prefix(&root.path).unwrap_or(&dir.path);
+        writeln!(
+            out,
+            "  {:>8}  {}/",
+            format_amount(measure(dir, metric), metric),
+            relative.display()
+        )?;
+    }
+    Ok(out.flush()?)
+}
@@ -0,0 +1,109 @@
+use std::borrow::Cow;
+use std::io::{self, BufWriter, Write};
+
+use crate::cli::Field;
+use crate::meta::Fields;
+use crate::paths::PathStyle;
+use crate::tree::{self, Node};
+
+#[derive(Clone, Copy)]
+pub enum Format {
+    Csv,
+    Tsv,
+}
+
+impl Format {
+    fn separator(self) -> char {
+        match self {
+            Self::Csv => ',',
+            Self::Tsv => '\t',
+        }
+    }
+
+    /// RFC 4180 quoting for CSV; TSV has no quoting, so escape instead.
+    fn field(self, text: &str) -> Cow<'_, str> {
+        match self {
+            Self::Csv if text.contains([',', '"', '\n', '\r']) => {
+                Cow::Owned(format!("\"{}\"", text.replace('"', "\"\"")))
+            }
+            Self::Tsv if text.contains(['\t', '\n', '\r', '\\']) => Cow::Owned(
+                text.replace('\\', "\\\\")
+                    .replace('\t', "\\t")
+                    .replace('\n', "\\n")
+                    .replace('\r', "\\r"),
+            ),
+            _ => Cow::Borrowed(text),
+        }
+    }
+}
+
+/// Print a header and one row per entry below the root, in tree order, with a
+/// column for each of `fields` after the path, depth and type.
+pub fn print(root: &Node, format: Format, fields: &[Field], paths: &PathStyle) -> io::Result<()> {
+    let mut out = BufWriter::new(io::stdout().lock());
+    let sep = format.separator();
+    write!(out, "path{sep}depth{sep}type")?;
+    for field in fields {
+        write!(out, "{sep}{}", column(*field))?;
+    }
+    writeln!(out)?;
+    write_rows(&mut out, root, 1, format, fields, paths)?;
+    out.flush()
+}
+
+fn column(field: Field) -> &'static str {
+    match field {
+        Field::Size => "size",
+        Field::Mtime => "mtime",
+        Field::Mode => "mode",
+        Field::Owner => "owner",
+        Field::Target => "target",
+    }
+}
+
+fn write_rows(
+    out: &mut impl Write,
+    node: &Node,
+    depth: usize,
+    format: Format,
+    fields: &[Field],
+    paths: &PathStyle,
+) -> io::Result<()> {
+    let sep = format.separator();
+    for child in &node.children {
+        let path = paths.apply(&child.path);
+        write!(
+            out,
+            "{}{sep}{}{sep}{}",
+            format.field(&path.to_string_lossy()),
+            depth,
+            if child.is_dir { "directory" } else { "file" },
+        )?;
+
+        // Cells stay empty when a field can't be read, or doesn't apply
+        let meta = Fields::read(
+            &child.path,
+            fields,
+            || child.size,
+            child.link_target.as_deref(),
+            child.mtime,
+        );
+        for field in fields {
+            let cell = match field {
+                // Exact unless another style was asked for
+                Field::Size => meta.size.map(|size| match tree::size_style() {
+                    Some(_) => tree::format_size(size),
+                    None => size.to_string(),
+                }),
+                Field::Mtime => meta.mtime.clone(),
+                Field::Mode => meta.mode.clone(),
+                Field::Owner => meta.owner.clone(),
+                Field::Target => meta.link_target.clone(),
+            };
+            write!(out, "{sep}{}", format.field(&cell.unwrap_or_default()))?;
+        }
+        writeln!(out)?;
+        write_rows(out, child, depth + 1, format, fields, paths)?;
+    }
+    Ok(())
+}
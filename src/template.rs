@@ -0,0 +1,129 @@
+use anyhow::{Result, bail};
+use std::io::{self, BufWriter, Write};
+
+use crate::paths::PathStyle;
+use crate::time;
+use crate::tree::Node;
+
+/// One piece of a --format template.
+enum Piece {
+    Text(String),
+    Path,
+    Name,
+    Size,
+    Mtime,
+    Depth,
+}
+
+/// A printf-style line template: `%p` path, `%n` name, `%s` size in bytes,
+/// `%m` ISO 8601 mtime, `%d` depth and `%%` a percent sign, plus `\t`, `\n`
+/// and `\\` escapes for shells that don't expand them.
+pub struct Template {
+    pieces: Vec<Piece>,
+}
+
+impl Template {
+    pub fn parse(template: &str) -> Result<Self> {
+        let mut pieces = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(ch) = chars.next() {
+            let piece = match ch {
+                '%' => match chars.next() {
+                    Some('p') => Piece::Path,
+                    Some('n') => Piece::Name,
+                    Some('s') => Piece::Size,
+                    Some('m') => Piece::Mtime,
+                    Some('d') => Piece::Depth,
+                    Some('%') => {
+                        text.push('%');
+                        continue;
+                    }
+                    Some(other) => bail!("Unknown placeholder '%{}' in --format", other),
+                    None => bail!("--format ends in a lone '%'; use '%%' for a percent sign"),
+                },
+                // Anything else after a backslash is kept as is
+                '\\' => {
+                    let escaped = match chars.peek() {
+                        Some('t') => '\t',
+                        Some('n') => '\n',
+                        Some('\\') => '\\',
+                        _ => {
+                            text.push('\\');
+                            continue;
+                        }
+                    };
+                    chars.next();
+                    text.push(escaped);
+                    continue;
+                }
+                ch => {
+                    text.push(ch);
+                    continue;
+                }
+            };
+            if !text.is_empty() {
+                pieces.push(Piece::Text(std::mem::take(&mut text)));
+            }
+            pieces.push(piece);
+        }
+        if !text.is_empty() {
+            pieces.push(Piece::Text(text));
+        }
+        Ok(Self { pieces })
+    }
+
+    /// Whether `%s` is used, so the scan has to read sizes.
+    pub fn needs_sizes(&self) -> bool {
+        self.pieces.iter().any(|piece| matches!(piece, Piece::Size))
+    }
+
+    fn render(
+        &self,
+        out: &mut impl Write,
+        node: &Node,
+        depth: usize,
+        paths: &PathStyle,
+    ) -> io::Result<()> {
+        for piece in &self.pieces {
+            match piece {
+                Piece::Text(text) => out.write_all(text.as_bytes())?,
+                Piece::Path => write!(out, "{}", paths.apply(&node.path).to_string_lossy())?,
+                Piece::Name => write!(out, "{}", node.name())?,
+                Piece::Size => write!(out, "{}", node.size)?,
+                Piece::Mtime => {
+                    // Empty when the time can't be read
+                    let mtime = node
+                        .mtime
+                        .or_else(|| time::of_path(&node.path))
+                        .map(time::iso8601)
+                        .unwrap_or_default();
+                    out.write_all(mtime.as_bytes())?;
+                }
+                Piece::Depth => write!(out, "{}", depth)?,
+            }
+        }
+        writeln!(out)
+    }
+}
+
+/// Print one line per entry below the root, in tree order.
+pub fn print(root: &Node, template: &Template, paths: &PathStyle) -> io::Result<()> {
+    let mut out = BufWriter::new(io::stdout().lock());
+    write_lines(&mut out, root, 1, template, paths)?;
+    out.flush()
+}
+
+fn write_lines(
+    out: &mut impl Write,
+    node: &Node,
+    depth: usize,
+    template: &Template,
+    paths: &PathStyle,
+) -> io::Result<()> {
+    for child in &node.children {
+        template.render(out, child, depth, paths)?;
+        write_lines(out, child, depth + 1, template, paths)?;
+    }
+    Ok(())
+}
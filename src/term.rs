@@ -0,0 +1,77 @@
+use std::env;
+use std::fmt::Write as _;
+use std::path::Path;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// Resolve the output width: an explicit `--width` wins (0 meaning unlimited),
+/// then the size of the terminal on stdout, then `$COLUMNS`.
+pub fn width(requested: Option<usize>) -> Option<usize> {
+    if let Some(width) = requested {
+        return (width > 0).then_some(width);
+    }
+
+    if let Some((terminal_size::Width(columns), _)) = terminal_size::terminal_size() {
+        return Some(columns as usize);
+    }
+
+    env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.trim().parse().ok())
+        .filter(|&columns| columns > 0)
+}
+
+/// Shorten `text` to at most `max` display columns, marking the cut with an ellipsis.
+pub fn truncate(text: &str, max: usize) -> String {
+    if text.width() <= max {
+        return text.to_string();
+    }
+    if max == 0 {
+        return String::new();
+    }
+
+    let mut out = String::new();
+    let mut used = 0;
+    for ch in text.chars() {
+        let ch_width = ch.width().unwrap_or(0);
+        // Leave one column for the ellipsis
+        if used + ch_width > max - 1 {
+            break;
+        }
+        out.push(ch);
+        used += ch_width;
+    }
+    out.push('…');
+    out
+}
+
+/// Whether stdout is a terminal that may render OSC 8 hyperlinks. There is no
+/// way to ask, and terminals without support just show the text.
+pub fn supports_hyperlinks() -> bool {
+    atty::is(atty::Stream::Stdout) && !env::var("TERM").is_ok_and(|term| term == "dumb")
+}
+
+/// Wrap already formatted `text` in an OSC 8 hyperlink to the `file://` URL of
+/// `path`.
+pub fn hyperlink(text: &str, path: &Path) -> String {
+    let Ok(path) = std::path::absolute(path) else {
+        return text.to_string();
+    };
+    let path = path.to_string_lossy().replace('\\', "/");
+
+    let mut url = String::from("file://");
+    // Windows paths like C:/x need a slash of their own before the drive
+    if !path.starts_with('/') {
+        url.push('/');
+    }
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'.' | b'_' | b'~' | b':' => {
+                url.push(byte as char)
+            }
+            _ => {
+                let _ = write!(url, "%{:02X}", byte);
+            }
+        }
+    }
+    format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, text)
+}
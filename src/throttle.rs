@@ -0,0 +1,139 @@
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::tree;
+
+/// A --throttle limit, per second.
+#[derive(Clone, Copy)]
+pub enum Rate {
+    /// Entries walked
+    Entries(u64),
+    /// Bytes read from files, e.g. to hash them
+    Bytes(u64),
+}
+
+/// `500` or `500/s` entries a second; `20M/s`, `20MB/s` or `512K` bytes.
+pub fn parse_rate(text: &str) -> Result<Rate, String> {
+    let amount = text.trim();
+    let amount = amount.strip_suffix("/s").or_else(|| amount.strip_suffix("/S")).unwrap_or(amount);
+    let rate = if amount.chars().all(|c| c.is_ascii_digit()) {
+        let entries = amount.parse().map_err(|_| format!("invalid rate '{}'", text))?;
+        Rate::Entries(entries)
+    } else {
+        Rate::Bytes(tree::parse_size(amount)?)
+    };
+    match rate {
+        Rate::Entries(0) | Rate::Bytes(0) => Err(format!("rate '{}' must be above zero", text)),
+        rate => Ok(rate),
+    }
+}
+
+/// Spaces out work so that it averages `per_second` units.
+struct Limiter {
+    per_second: f64,
+    /// When the next unit may start
+    next: Mutex<Instant>,
+}
+
+impl Limiter {
+    fn new(per_second: u64) -> Self {
+        Self {
+            per_second: per_second as f64,
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Wait for the turn of `amount` units, shared by every thread.
+    fn take(&self, amount: u64) {
+        let now = Instant::now();
+        let wait = {
+            let mut next = self.next.lock().unwrap();
+            // Time left unused earlier isn't made up for in a burst
+            let start = (*next).max(now);
+            *next = start + Duration::from_secs_f64(amount as f64 / self.per_second);
+            start - now
+        };
+        if !wait.is_zero() {
+            thread::sleep(wait);
+        }
+    }
+}
+
+static ENTRIES: OnceLock<Limiter> = OnceLock::new();
+static BYTES: OnceLock<Limiter> = OnceLock::new();
+static NICE: OnceLock<()> = OnceLock::new();
+
+/// Limit the walk and file reads to `rates` for the rest of the process,
+/// the last rate of each kind winning like any repeated option; the limits
+/// of the first call hold.
+pub fn set_rates(rates: &[Rate]) {
+    for rate in rates.iter().rev() {
+        let _ = match *rate {
+            Rate::Entries(per_second) => ENTRIES.set(Limiter::new(per_second)),
+            Rate::Bytes(per_second) => BYTES.set(Limiter::new(per_second)),
+        };
+    }
+}
+
+/// Wait before walking on to the next entry, with an entry rate set.
+pub fn entry() {
+    if let Some(limiter) = ENTRIES.get() {
+        limiter.take(1);
+    }
+}
+
+/// Wait before reading on past `bytes` more, with a byte rate set.
+pub fn bytes(bytes: usize) {
+    if let Some(limiter) = BYTES.get() {
+        limiter.take(bytes as u64);
+    }
+}
+
+/// Run the rest of the process at the lowest CPU priority, and on Linux the
+/// lowest best-effort I/O priority, like `nice -n 19 ionice -c 2 -n 7`
+/// (background mode on Windows, which lowers both). Best effort: a priority
+/// the system refuses is left as it was.
+pub fn lower_priority() {
+    NICE.get_or_init(|| {
+        let lowered = lower_process_priority();
+        tracing::debug!(lowered, "lowered priority");
+    });
+}
+
+#[cfg(unix)]
+fn lower_process_priority() -> bool {
+    // SAFETY: plain calls about the calling process only
+    unsafe {
+        // Raising the niceness past the maximum just stops at it. -1 is also
+        // a valid new niceness, so only errno tells a failure apart
+        errno::set_errno(errno::Errno(0));
+        let niced = libc::nice(19) != -1 || errno::errno().0 == 0;
+        #[cfg(target_os = "linux")]
+        {
+            const IOPRIO_WHO_PROCESS: libc::c_long = 1;
+            const IOPRIO_CLASS_BE: libc::c_long = 2;
+            const IOPRIO_CLASS_SHIFT: libc::c_long = 13;
+            let lowest = (IOPRIO_CLASS_BE << IOPRIO_CLASS_SHIFT) | 7;
+            let ioniced =
+                libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, 0 as libc::c_long, lowest);
+            niced && ioniced == 0
+        }
+        #[cfg(not(target_os = "linux"))]
+        niced
+    }
+}
+
+#[cfg(windows)]
+fn lower_process_priority() -> bool {
+    use windows_sys::Win32::System::Threading::{
+        GetCurrentProcess, PROCESS_MODE_BACKGROUND_BEGIN, SetPriorityClass,
+    };
+    // SAFETY: the pseudo handle of the current process needs no cleanup
+    unsafe { SetPriorityClass(GetCurrentProcess(), PROCESS_MODE_BACKGROUND_BEGIN) != 0 }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn lower_process_priority() -> bool {
+    false
+}
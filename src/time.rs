@@ -0,0 +1,250 @@
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::cli::TimeKind;
+
+static KIND: OnceLock<TimeKind> = OnceLock::new();
+
+/// Pick which timestamp of entries `of` reads for the rest of the process.
+/// Later calls are ignored.
+pub fn set_kind(kind: TimeKind) {
+    let _ = KIND.set(kind);
+}
+
+/// The timestamp picked with `set_kind` of the entry at `path`, modification
+/// time unless another was asked for. `None` where the platform or filesystem
+/// doesn't keep it.
+pub fn of(path: &Path, metadata: &fs::Metadata) -> Option<SystemTime> {
+    match KIND.get().copied().unwrap_or(TimeKind::Mtime) {
+        TimeKind::Mtime => metadata.modified().ok(),
+        TimeKind::Atime => metadata.accessed().ok(),
+        TimeKind::Created => metadata.created().ok(),
+        TimeKind::Ctime => changed(path, metadata),
+    }
+}
+
+/// Read the entry at `path` itself, links included, for `of`.
+pub fn of_path(path: &Path) -> Option<SystemTime> {
+    of(path, &fs::symlink_metadata(path).ok()?)
+}
+
+#[cfg(unix)]
+fn changed(_path: &Path, metadata: &fs::Metadata) -> Option<SystemTime> {
+    use std::os::unix::fs::MetadataExt;
+    use std::time::Duration;
+    let secs = u64::try_from(metadata.ctime()).ok()?;
+    Some(UNIX_EPOCH + Duration::new(secs, metadata.ctime_nsec() as u32))
+}
+
+/// The change time isn't part of std's metadata on Windows, but the file's
+/// basic information has it.
+#[cfg(windows)]
+fn changed(path: &Path, _metadata: &fs::Metadata) -> Option<SystemTime> {
+    use std::os::windows::fs::OpenOptionsExt;
+    use std::os::windows::io::AsRawHandle;
+    use std::time::Duration;
+    use windows_sys::Win32::Storage::FileSystem::{
+        FILE_BASIC_INFO, FILE_FLAG_BACKUP_SEMANTICS, FILE_FLAG_OPEN_REPARSE_POINT,
+        FILE_READ_ATTRIBUTES, FileBasicInfo, GetFileInformationByHandleEx,
+    };
+
+    // Directories only open with backup semantics; links are described themselves
+    let file = fs::OpenOptions::new()
+        .access_mode(FILE_READ_ATTRIBUTES)
+        .custom_flags(FILE_FLAG_BACKUP_SEMANTICS | FILE_FLAG_OPEN_REPARSE_POINT)
+        .open(path)
+        .ok()?;
+    // SAFETY: `info` is a plain struct of the size passed, filled in on success
+    let mut info: FILE_BASIC_INFO = unsafe { std::mem::zeroed() };
+    let ok = unsafe {
+        GetFileInformationByHandleEx(
+            file.as_raw_handle(),
+            FileBasicInfo,
+            (&mut info as *mut FILE_BASIC_INFO).cast(),
+            std::mem::size_of::<FILE_BASIC_INFO>() as u32,
+        )
+    };
+    if ok == 0 {
+        return None;
+    }
+    // 100-nanosecond intervals since 1601, 11644473600 seconds before the epoch
+    let ticks = u64::try_from(info.ChangeTime).ok()?;
+    let since_epoch = (ticks / 10_000_000).checked_sub(11_644_473_600)?;
+    Some(UNIX_EPOCH + Duration::new(since_epoch, (ticks % 10_000_000) as u32 * 100))
+}
+
+#[cfg(not(any(unix, windows)))]
+fn changed(_path: &Path, _metadata: &fs::Metadata) -> Option<SystemTime> {
+    None
+}
+
+/// Format `time` as an ISO 8601 UTC timestamp, e.g. `2024-05-01T09:30:00Z`.
+/// Times before the epoch are clamped to it.
+pub fn iso8601(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let rem = secs % 86_400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+/// The proleptic Gregorian date `days` days after 1970-01-01, after Howard
+/// Hinnant's `civil_from_days`.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+const MONTHS: [&str; 12] = [
+    "January", "February", "March", "April", "May", "June", "July", "August", "September",
+    "October", "November", "December",
+];
+const WEEKDAYS: [&str; 7] = [
+    "Sunday", "Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday",
+];
+
+/// Format `time` the way `tree -D` does without --timefmt: `Oct 14 09:30`, or
+/// `Oct 14  2023` for times more than six months ago or in the future.
+pub fn tree_date(time: SystemTime) -> String {
+    const SIX_MONTHS: u64 = 182 * 86_400;
+    let recent = SystemTime::now()
+        .duration_since(time)
+        .is_ok_and(|age| age.as_secs() < SIX_MONTHS);
+    strftime(time, if recent { "%b %e %H:%M" } else { "%b %e  %Y" })
+}
+
+/// Format `time` like strftime(3), in local time on Unix and UTC elsewhere.
+/// Supports the common conversions (`%Y %m %d %H %M %S %b %a %e %j %z %Z %s`
+/// and friends, and the `%F %T %R %D %c` shorthands); others are left as is.
+pub fn strftime(time: SystemTime, format: &str) -> String {
+    let secs = match time.duration_since(UNIX_EPOCH) {
+        Ok(after) => after.as_secs() as i64,
+        Err(before) => -(before.duration().as_secs() as i64),
+    };
+    let (offset, zone) = local_zone(secs).unwrap_or((0, "UTC".to_string()));
+    let local = secs + offset;
+    let days = local.div_euclid(86_400);
+    let rem = local.rem_euclid(86_400);
+    let (year, month, day) = civil_from_days(days);
+    let (hour, minute, second) = (rem / 3600, rem % 3600 / 60, rem % 60);
+    let weekday = (days + 4).rem_euclid(7) as usize;
+    let day_of_year = days - days_from_civil(year, 1, 1) + 1;
+    let hour12 = if hour % 12 == 0 { 12 } else { hour % 12 };
+
+    let mut out = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        let Some(spec) = chars.next() else {
+            out.push('%');
+            break;
+        };
+        let text = match spec {
+            'Y' => year.to_string(),
+            'C' => format!("{:02}", year.div_euclid(100)),
+            'y' => format!("{:02}", year.rem_euclid(100)),
+            'm' => format!("{:02}", month),
+            'd' => format!("{:02}", day),
+            'e' => format!("{:>2}", day),
+            'j' => format!("{:03}", day_of_year),
+            'H' => format!("{:02}", hour),
+            'I' => format!("{:02}", hour12),
+            'M' => format!("{:02}", minute),
+            'S' => format!("{:02}", second),
+            'p' => (if hour < 12 { "AM" } else { "PM" }).to_string(),
+            'b' | 'h' => MONTHS[month as usize - 1][..3].to_string(),
+            'B' => MONTHS[month as usize - 1].to_string(),
+            'a' => WEEKDAYS[weekday][..3].to_string(),
+            'A' => WEEKDAYS[weekday].to_string(),
+            'u' => (if weekday == 0 { 7 } else { weekday }).to_string(),
+            'w' => weekday.to_string(),
+            's' => secs.to_string(),
+            'z' => {
+                let sign = if offset < 0 { '-' } else { '+' };
+                let minutes = offset.abs() / 60;
+                format!("{}{:02}{:02}", sign, minutes / 60, minutes % 60)
+            }
+            'Z' => zone.clone(),
+            'F' => format!("{}-{:02}-{:02}", year, month, day),
+            'T' => format!("{:02}:{:02}:{:02}", hour, minute, second),
+            'R' => format!("{:02}:{:02}", hour, minute),
+            'D' => format!("{:02}/{:02}/{:02}", month, day, year.rem_euclid(100)),
+            'c' => format!(
+                "{} {} {:>2} {:02}:{:02}:{:02} {}",
+                &WEEKDAYS[weekday][..3],
+                &MONTHS[month as usize - 1][..3],
+                day,
+                hour,
+                minute,
+                second,
+                year
+            ),
+            'n' => "\n".to_string(),
+            't' => "\t".to_string(),
+            '%' => "%".to_string(),
+            other => format!("%{}", other),
+        };
+        out.push_str(&text);
+    }
+    out
+}
+
+/// The inverse of `civil_from_days`.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = i64::from((month + 9) % 12);
+    let doy = (153 * mp + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// The offset from UTC in seconds and the abbreviated name of the local time
+/// zone at `secs` after the epoch.
+#[cfg(unix)]
+fn local_zone(secs: i64) -> Option<(i64, String)> {
+    use std::ffi::CStr;
+
+    let time = secs as libc::time_t;
+    // SAFETY: `tm` is a plain struct that `localtime_r` fills in on success
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&time, &mut tm) }.is_null() {
+        return None;
+    }
+    let zone = if tm.tm_zone.is_null() {
+        String::new()
+    } else {
+        // SAFETY: a non-null `tm_zone` points to a static NUL-terminated name
+        unsafe { CStr::from_ptr(tm.tm_zone) }.to_string_lossy().into_owned()
+    };
+    Some((tm.tm_gmtoff as i64, zone))
+}
+
+#[cfg(not(unix))]
+fn local_zone(_secs: i64) -> Option<(i64, String)> {
+    None
+}
@@ -0,0 +1,961 @@
+use anyhow::Result;
+use glob::Pattern;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::{DirEntry, WalkBuilder, WalkState};
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::SystemTime;
+use tracing::{debug, info, info_span, trace};
+
+use crate::archive;
+use crate::cli::{DirMtime, MountMode, ScanArgs};
+use crate::codeowners::CodeOwners;
+use crate::errors::{ErrorKind, ScanError};
+use crate::filter::{self, PathFilter};
+use crate::kind;
+use crate::media;
+use crate::mounts;
+use crate::secrets;
+use crate::throttle;
+use crate::time;
+
+/// The deepest level --fast descends to.
+pub const FAST_MAX_DEPTH: usize = 3;
+
+/// How many entries --fast collects before it stops walking.
+const FAST_MAX_ENTRIES: usize = 1000;
+
+/// A directory entry together with the entries displayed beneath it.
+pub struct Node {
+    pub path: PathBuf,
+    pub is_dir: bool,
+    /// File size in bytes, or the cumulative size of all children for directories.
+    /// Only populated when sizes were requested.
+    pub size: u64,
+    /// Where the entry points, if it is a symlink
+    pub link_target: Option<PathBuf>,
+    /// Modification time (or whichever --time picks), or for directories with
+    /// `--dir-mtime newest` the newest one below them. Only populated when
+    /// needed for sorting or display.
+    pub mtime: Option<SystemTime>,
+    pub children: Vec<Node>,
+}
+
+/// The result of scanning a directory: the filtered tree plus any non-fatal
+/// errors (unreadable directories, I/O failures) hit along the way.
+pub struct Scan {
+    pub root: Node,
+    pub errors: Vec<ScanError>,
+}
+
+impl Node {
+    /// The last path component. Roots like `..` or `../..` have none, so they
+    /// are named after the directory they resolve to; `.` (or `./`) stays `.`
+    /// like in tree(1), and `/` stays `/`.
+    pub fn name(&self) -> String {
+        if let Some(name) = self.path.file_name() {
+            return name.to_string_lossy().into_owned();
+        }
+        if self.path.components().all(|part| part == Component::CurDir) {
+            return ".".to_string();
+        }
+
+        std::fs::canonicalize(&self.path)
+            .ok()
+            .and_then(|path| path.file_name().map(|name| name.to_string_lossy().into_owned()))
+            .unwrap_or_else(|| self.path.to_string_lossy().into_owned())
+    }
+
+    /// Whether this is a symlink whose target doesn't exist.
+    pub fn is_broken_link(&self) -> bool {
+        self.link_target.is_some() && !self.path.exists()
+    }
+
+    /// Every node below this one, in display (depth-first) order.
+    pub fn descendants(&self) -> Vec<&Node> {
+        fn walk<'a>(node: &'a Node, out: &mut Vec<&'a Node>) {
+            for child in &node.children {
+                out.push(child);
+                walk(child, out);
+            }
+        }
+
+        let mut out = Vec::new();
+        walk(self, &mut out);
+        out
+    }
+}
+
+/// Walk `root` and return every entry that passes the filters in `args`,
+/// excluding the root itself, along with the errors the walker reported.
+pub fn collect_entries(
+    root: &Path,
+    args: &ScanArgs,
+) -> Result<(Vec<DirEntry>, Vec<ScanError>)> {
+    collect_entries_with(root, args, |_| {})
+}
+
+/// `collect_entries`, showing every entry to `on_entry` as soon as it is found.
+fn collect_entries_with(
+    root: &Path,
+    args: &ScanArgs,
+    mut on_entry: impl FnMut(&DirEntry),
+) -> Result<(Vec<DirEntry>, Vec<ScanError>)> {
+    let mut entries = Vec::new();
+    let mut errors = Vec::new();
+    walk(
+        root,
+        args,
+        false,
+        |entry| {
+            on_entry(&entry);
+            entries.push(entry);
+            Ok(())
+        },
+        |err| {
+            errors.push(err);
+            Ok(())
+        },
+    )?;
+    Ok((entries, errors))
+}
+
+/// Walk `root`, handing every entry that passes the filters in `args` (except
+/// the root itself) to `visit` and every error to `on_error` as they come up.
+/// With `sorted` (or --first), each directory's entries are visited in file
+/// name order. The walk ends early once --first is satisfied.
+///
+/// With --unignore, a second pass ignoring the ignore files picks up what they
+/// hid and the patterns bring back, along with the directories leading there.
+pub fn walk(
+    root: &Path,
+    args: &ScanArgs,
+    sorted: bool,
+    mut visit: impl FnMut(DirEntry) -> Result<()>,
+    mut on_error: impl FnMut(ScanError) -> Result<()>,
+) -> Result<()> {
+    let _span = info_span!("walk", root = %root.display()).entered();
+    throttle::set_rates(&args.throttle);
+    if args.nice {
+        throttle::lower_priority();
+    }
+    if args.min_resolution.is_some() || args.exif_date_between.is_some() {
+        media::ensure_enabled()?;
+    }
+
+    // Create path filter
+    let path_filter = PathFilter::new(
+        &args.include_patterns,
+        &args.exclude_patterns,
+        &args.file_patterns,
+        &args.regex_patterns,
+        args.regex_engine,
+        &args.pattern_depths,
+    )?;
+    let subtree_depths = Arc::new(filter::parse_depth_rules(&args.depth_for)?);
+    let unignore = Unignore::new(root, &args.unignore)?.map(Arc::new);
+    let code_owners = args.owned_by.as_ref().map(|_| CodeOwners::find(root)).transpose()?;
+    let filters = Filters {
+        path: &path_filter,
+        code_owners: code_owners.as_ref(),
+    };
+
+    if args.fast {
+        return walk_parallel(root, args, &filters, subtree_depths, visit, on_error);
+    }
+
+    // Paths already visited, so the --unignore pass only adds what the first
+    // one skipped; directories it walked through wait in `pending` until
+    // something inside them turns out to be brought back
+    let mut seen: HashSet<PathBuf> = HashSet::new();
+    let mut pending: HashMap<PathBuf, DirEntry> = HashMap::new();
+
+    let mut visited = 0usize;
+    let mut matched = 0usize;
+    let mut errors = 0usize;
+    let mut found = 0usize;
+    let passes = if unignore.is_some() { 2 } else { 1 };
+    'passes: for pass in 0..passes {
+        let unignoring = pass == 1;
+        // Entries cut off by --max-link-depth; the walker only reports real loops
+        let too_deep = Arc::new(Mutex::new(Vec::new()));
+        let walker = walk_builder(
+            root,
+            args,
+            sorted,
+            Arc::clone(&subtree_depths),
+            Arc::clone(&too_deep),
+            unignore.as_ref().filter(|_| unignoring).map(Arc::clone),
+        )
+        .build();
+
+        for result in walker {
+            throttle::entry();
+            for err in too_deep.lock().unwrap().drain(..) {
+                errors += 1;
+                on_error(err)?;
+            }
+
+            let entry = match result {
+                Ok(entry) => entry,
+                // The first pass already reported these
+                Err(_) if unignoring => continue,
+                Err(err) => {
+                    errors += 1;
+                    on_error(ScanError::from(err))?;
+                    continue;
+                }
+            };
+
+            if unignoring {
+                let path = entry.path();
+                if seen.contains(path) || path == root {
+                    continue;
+                }
+                let is_dir = path.is_dir();
+                let brought_back = unignore.as_ref().is_some_and(|u| u.matches(path, is_dir));
+                if !brought_back || !is_shown(&entry, root, args, &filters) {
+                    if is_dir {
+                        pending.insert(path.to_path_buf(), entry);
+                    }
+                    continue;
+                }
+
+                // Hand over the hidden directories leading here first, top down
+                let mut ancestors: Vec<DirEntry> = path
+                    .ancestors()
+                    .skip(1)
+                    .map_while(|dir| pending.remove(dir))
+                    .collect();
+                ancestors.reverse();
+                for dir in ancestors {
+                    seen.insert(dir.path().to_path_buf());
+                    visit(dir)?;
+                }
+            } else {
+                visited += 1;
+                trace!(path = %entry.path().display(), "visit");
+                if visited.is_multiple_of(10_000) {
+                    debug!(visited, "walk progress");
+                }
+                if !is_shown(&entry, root, args, &filters) {
+                    continue;
+                }
+            }
+
+            matched += 1;
+            // Directories are only on the way to files, unless -d lists them
+            let counts = args.directories_only || !entry.path().is_dir();
+            if unignore.is_some() {
+                seen.insert(entry.path().to_path_buf());
+            }
+            visit(entry)?;
+            if counts {
+                found += 1;
+                if args.first.is_some_and(|first| found >= first.get()) {
+                    debug!(found, "stopping early (--first)");
+                    break 'passes;
+                }
+            }
+        }
+        for err in too_deep.lock().unwrap().drain(..) {
+            errors += 1;
+            on_error(err)?;
+        }
+    }
+
+    info!(visited, matched, errors, "walk finished");
+    Ok(())
+}
+
+/// The --fast walk: directories are read on all cores and entries are handed
+/// over in whatever order the threads found them, up to `FAST_MAX_ENTRIES`.
+/// A directory still comes before its contents.
+fn walk_parallel(
+    root: &Path,
+    args: &ScanArgs,
+    filters: &Filters,
+    subtree_depths: Arc<Vec<(Pattern, usize)>>,
+    mut visit: impl FnMut(DirEntry) -> Result<()>,
+    mut on_error: impl FnMut(ScanError) -> Result<()>,
+) -> Result<()> {
+    let too_deep = Arc::new(Mutex::new(Vec::new()));
+    let walker = walk_builder(root, args, false, subtree_depths, Arc::clone(&too_deep), None)
+        .build_parallel();
+
+    let found = Mutex::new(Vec::new());
+    let matched = AtomicUsize::new(0);
+    walker.run(|| {
+        Box::new(|result| {
+            throttle::entry();
+            let shown = match result {
+                Ok(entry) if is_shown(&entry, root, args, filters) => {
+                    if matched.fetch_add(1, Ordering::Relaxed) >= FAST_MAX_ENTRIES {
+                        return WalkState::Quit;
+                    }
+                    Ok(entry)
+                }
+                Ok(_) => return WalkState::Continue,
+                Err(err) => Err(ScanError::from(err)),
+            };
+            found.lock().unwrap().push(shown);
+            WalkState::Continue
+        })
+    });
+
+    let found = found.into_inner().unwrap();
+    info!(matched = found.len(), capped = matched.into_inner() > FAST_MAX_ENTRIES, "walk finished");
+    for err in too_deep.lock().unwrap().drain(..) {
+        on_error(err)?;
+    }
+    for result in found {
+        match result {
+            Ok(entry) => visit(entry)?,
+            Err(err) => on_error(err)?,
+        }
+    }
+    Ok(())
+}
+
+/// Set up the walker for `args`. With `unignore`, ignore files are left out
+/// and only directories that may hold something it brings back are entered.
+fn walk_builder(
+    root: &Path,
+    args: &ScanArgs,
+    sorted: bool,
+    subtree_depths: Arc<Vec<(Pattern, usize)>>,
+    too_deep: Arc<Mutex<Vec<ScanError>>>,
+    unignore: Option<Arc<Unignore>>,
+) -> WalkBuilder {
+    let respect_ignores = unignore.is_none();
+    let mut builder = WalkBuilder::new(root);
+    builder
+        .max_depth(Some(args.depth))
+        .hidden(!args.all && !args.traverse_hidden)
+        .git_ignore(respect_ignores && !args.no_git_ignore && !args.no_repo_gitignore)
+        .git_exclude(respect_ignores && !args.no_git_ignore && !args.no_exclude_file)
+        .git_global(respect_ignores && !args.no_git_ignore && !args.no_global_gitignore)
+        .ignore(respect_ignores)
+        .parents(respect_ignores)
+        .follow_links(args.follow);
+    if sorted || args.first.is_some() {
+        builder.sort_by_file_name(|a, b| a.cmp(b));
+    }
+
+    let max_link_depth = args.max_link_depth;
+    if max_link_depth.is_some() || !subtree_depths.is_empty() || unignore.is_some() {
+        // Pruning here keeps the walker from descending at all
+        let root = root.to_path_buf();
+        builder.filter_entry(move |entry| {
+            if let Some(unignore) = &unignore
+                && entry.file_type().is_some_and(|t| t.is_dir())
+                && !unignore.may_contain(&root, entry.path())
+            {
+                return false;
+            }
+
+            if !within_subtree_depth(&root, entry.path(), &subtree_depths) {
+                return false;
+            }
+
+            let Some(max) = max_link_depth else {
+                return true;
+            };
+            let links = link_depth(&root, entry.path());
+            if links <= max {
+                return true;
+            }
+            too_deep.lock().unwrap().push(ScanError {
+                path: Some(entry.path().to_path_buf()),
+                kind: ErrorKind::Loop,
+                message: format!(
+                    "reached through {} symlink{} (--max-link-depth {})",
+                    links,
+                    if links == 1 { "" } else { "s" },
+                    max
+                ),
+            });
+            false
+        });
+    }
+    builder
+}
+
+/// The `--unignore` patterns, matched like lines of a `.gitignore` file at
+/// the root: a match here is a path to show even though ignore rules hide it.
+struct Unignore {
+    matcher: Gitignore,
+    /// The literal leading directories of each pattern, or `None` when some
+    /// pattern can match at any depth, so the second pass must go everywhere
+    prefixes: Option<Vec<Vec<String>>>,
+}
+
+impl Unignore {
+    fn new(root: &Path, patterns: &[String]) -> Result<Option<Self>> {
+        if patterns.is_empty() {
+            return Ok(None);
+        }
+
+        let mut builder = GitignoreBuilder::new(root);
+        let mut prefixes = Some(Vec::new());
+        for pattern in patterns {
+            builder.add_line(None, pattern)?;
+
+            // Like gitignore, a pattern without an inner slash matches at any depth
+            let anchored = pattern.trim_end_matches('/');
+            if !anchored.contains('/') || anchored.starts_with("**") {
+                prefixes = None;
+            } else if let Some(prefixes) = &mut prefixes {
+                prefixes.push(
+                    anchored
+                        .trim_start_matches('/')
+                        .split('/')
+                        .take_while(|part| !part.contains(['*', '?', '[']))
+                        .map(str::to_string)
+                        .collect(),
+                );
+            }
+        }
+
+        Ok(Some(Self {
+            matcher: builder.build()?,
+            prefixes,
+        }))
+    }
+
+    fn matches(&self, path: &Path, is_dir: bool) -> bool {
+        self.matcher
+            .matched_path_or_any_parents(path, is_dir)
+            .is_ignore()
+    }
+
+    /// Whether the directory at `path` may hold, or be, a brought back path.
+    fn may_contain(&self, root: &Path, path: &Path) -> bool {
+        let Some(prefixes) = &self.prefixes else {
+            return true;
+        };
+        let Ok(relative) = path.strip_prefix(root) else {
+            return true;
+        };
+        let parts: Vec<String> = relative
+            .components()
+            .map(|part| part.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        // Either on the way to the pattern's directory, or inside it
+        prefixes
+            .iter()
+            .any(|prefix| parts.iter().zip(prefix).all(|(a, b)| a == b))
+    }
+}
+
+/// What `is_shown` needs beyond `args`, set up once per walk.
+struct Filters<'a> {
+    path: &'a PathFilter,
+    /// With --owned-by
+    code_owners: Option<&'a CodeOwners>,
+}
+
+/// Whether a walked entry passes the filters in `args`.
+fn is_shown(entry: &DirEntry, root: &Path, args: &ScanArgs, filters: &Filters) -> bool {
+    let path = entry.path();
+
+    // Skip the root directory itself
+    if path == root {
+        return false;
+    }
+
+    // Apply path filter
+    if !filters.path.should_include(path, entry.depth()) {
+        return false;
+    }
+
+    // Hidden files were only walked to reach what's inside hidden directories
+    if args.traverse_hidden && is_hidden(path) && !path.is_dir() {
+        debug!(path = %path.display(), "skipped: hidden (--traverse-hidden)");
+        return false;
+    }
+
+    // Filter directories only if requested
+    if args.directories_only && !path.is_dir() {
+        debug!(path = %path.display(), "skipped: not a directory (--directories-only)");
+        return false;
+    }
+
+    // Filter files only if requested
+    if args.files_only && !path.is_file() {
+        debug!(path = %path.display(), "skipped: not a file (--files-only)");
+        return false;
+    }
+
+    // Only symlinks that don't resolve (`exists` follows the link); the
+    // directories without any are pruned once the tree is built
+    let is_broken_link = entry.path_is_symlink() && !path.exists();
+    if args.broken_links && !path.is_dir() && !is_broken_link {
+        debug!(path = %path.display(), "skipped: not a broken link (--broken-links)");
+        return false;
+    }
+
+    if args.hide_special && is_special(entry) {
+        debug!(path = %path.display(), "skipped: special file (--hide-special)");
+        return false;
+    }
+
+    if args.executable && !path.is_dir() && !kind::is_executable(path) {
+        debug!(path = %path.display(), "skipped: not executable (--executable)");
+        return false;
+    }
+
+    if args.scan_secrets_names
+        && !path.is_dir()
+        && !(secrets::looks_like_secret(&entry.file_name().to_string_lossy())
+            && secrets::is_exposed(path))
+    {
+        debug!(path = %path.display(), "skipped: not an exposed secret (--scan-secrets-names)");
+        return false;
+    }
+
+    if let (Some(owner), Some(code_owners)) = (&args.owned_by, filters.code_owners)
+        && !path.is_dir()
+        && !code_owners.is_owned_by(path, false, owner)
+    {
+        debug!(path = %path.display(), "skipped: not owned by --owned-by");
+        return false;
+    }
+
+    // Sniff content last, since it means opening the file
+    if let Some(resolution) = args.min_resolution
+        && !path.is_dir()
+        && !resolution.admits(media::probe(path).as_ref())
+    {
+        debug!(path = %path.display(), "skipped: below --min-resolution");
+        return false;
+    }
+
+    if let Some(range) = args.exif_date_between
+        && !path.is_dir()
+        && !range.admits(media::capture_date(path))
+    {
+        debug!(path = %path.display(), "skipped: not taken within --exif-date-between");
+        return false;
+    }
+
+    if !args.kinds.is_empty() && !path.is_dir() {
+        let kind = kind::detect(path);
+        if !kind.is_some_and(|kind| args.kinds.contains(&kind)) {
+            debug!(path = %path.display(), "skipped: not of the requested --kind");
+            return false;
+        }
+    }
+
+    true
+}
+
+/// Scan `root` into a tree of nodes. When `with_sizes` is set, file sizes are
+/// read and directory sizes are accumulated from their displayed children.
+pub fn scan(root: &Path, args: &ScanArgs, with_sizes: bool) -> Result<Scan> {
+    scan_with(root, args, with_sizes, |_| {})
+}
+
+/// `scan`, showing every walked entry to `on_entry` as soon as it is found, so
+/// work on it can start while the walk goes on. Entries dropped afterwards
+/// (e.g. files with -d) are shown too.
+pub fn scan_with(
+    root: &Path,
+    args: &ScanArgs,
+    with_sizes: bool,
+    on_entry: impl FnMut(&DirEntry),
+) -> Result<Scan> {
+    // Directory sizes with -d still come from the files inside, so walk those
+    // too and only drop them once the sizes are known
+    let hide_files = with_sizes && args.directories_only;
+    let (entries, mut errors) = if hide_files {
+        let with_files = ScanArgs {
+            directories_only: false,
+            ..args.clone()
+        };
+        collect_entries_with(root, &with_files, on_entry)?
+    } else {
+        collect_entries_with(root, args, on_entry)?
+    };
+
+    let _span = info_span!("build", with_sizes).entered();
+
+    // Group entries by their parent directory
+    let mut dir_contents: HashMap<PathBuf, Vec<(PathBuf, u64)>> = HashMap::new();
+    // Links back into the scanned tree itself must not count it a second time
+    let mut seen_targets: HashSet<PathBuf> = std::fs::canonicalize(root).into_iter().collect();
+
+    for entry in entries {
+        let path = entry.path();
+        let size = if !with_sizes {
+            0
+        } else if args.dereference_size && entry.path_is_symlink() {
+            dereferenced_size(path, &mut seen_targets)
+        } else if is_special(&entry) && !args.include_special {
+            0
+        } else if !path.is_dir() {
+            entry.metadata().map(|m| m.len()).unwrap_or(0)
+        } else {
+            0
+        };
+        if let Some(parent) = path.parent() {
+            dir_contents
+                .entry(parent.to_path_buf())
+                .or_default()
+                .push((path.to_path_buf(), size));
+        }
+    }
+
+    let mut builder = TreeBuilder {
+        dir_contents,
+        sorted: !args.fast,
+        order_files: !args.no_order_files,
+        dirs_first: args.dirsfirst,
+        sort_mtime: args.sort_mtime,
+        mtimes: args.sort_mtime || args.dir_mtime == DirMtime::Newest,
+        newest_dir_mtime: args.dir_mtime == DirMtime::Newest,
+        du_exclude: args
+            .du_exclude_patterns
+            .iter()
+            .map(|p| Pattern::new(p))
+            .collect::<Result<_, _>>()?,
+        du_exclude_mounts: match args.proc_mounts {
+            Some(MountMode::Exclude) => mounts::volatile()?,
+            _ => HashMap::new(),
+        },
+    };
+    let mut root = builder.build(root.to_path_buf(), 0);
+    if args.traverse_hidden {
+        prune_hidden_dirs(&mut root);
+    }
+    if hide_files {
+        prune_files(&mut root);
+    }
+    if prunes_empty_dirs(args) {
+        prune_empty_dirs(&mut root);
+    }
+
+    if args.descend_archives {
+        archive::expand(&mut root, 0, args.depth, &mut errors);
+    }
+
+    Ok(Scan { root, errors })
+}
+
+/// The size of whatever the symlink at `path` points to, descending into
+/// linked directories (like `du -L`). `seen` holds the canonical paths already
+/// counted, so a target reached through several links is only counted once and
+/// links pointing back up the tree can't recurse forever. Broken links count as 0.
+fn dereferenced_size(path: &Path, seen: &mut HashSet<PathBuf>) -> u64 {
+    let Ok(target) = std::fs::canonicalize(path) else {
+        return 0;
+    };
+    if !seen.insert(target.clone()) {
+        return 0;
+    }
+
+    match std::fs::metadata(&target) {
+        Ok(meta) if meta.is_dir() => std::fs::read_dir(&target)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|entry| dereferenced_size(&entry.path(), seen))
+                    .sum()
+            })
+            .unwrap_or(0),
+        Ok(meta) => meta.len(),
+        Err(_) => 0,
+    }
+}
+
+/// The closest directory at or above `path` holding a `.git` directory (or, for
+/// worktrees and submodules, a `.git` file).
+pub fn find_repo_root(path: &Path) -> Option<PathBuf> {
+    let start = std::path::absolute(path).ok()?;
+    start
+        .ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .map(Path::to_path_buf)
+}
+
+/// Whether `path` is within the depth `--depth-for` allows below every
+/// matching directory above it (the root included).
+fn within_subtree_depth(root: &Path, path: &Path, limits: &[(Pattern, usize)]) -> bool {
+    if limits.is_empty() || path == root {
+        return true;
+    }
+
+    for (levels, ancestor) in path.ancestors().skip(1).enumerate() {
+        let name = ancestor.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+        let too_deep = limits
+            .iter()
+            .any(|(pattern, max)| levels + 1 > *max && pattern.matches(&name));
+        if too_deep {
+            debug!(path = %path.display(), "skipped: below --depth-for");
+            return false;
+        }
+        if ancestor == root {
+            break;
+        }
+    }
+    true
+}
+
+/// How many of the components between `root` and `path` are symlinks to
+/// directories, the only ones the walk descends through.
+fn link_depth(root: &Path, path: &Path) -> usize {
+    path.ancestors()
+        .take_while(|ancestor| *ancestor != root)
+        .filter(|ancestor| ancestor.is_symlink() && ancestor.is_dir())
+        .count()
+}
+
+/// Whether the entry is a FIFO, socket or device rather than a file or directory.
+fn is_special(entry: &DirEntry) -> bool {
+    entry.file_type().is_some_and(kind::is_special)
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+}
+
+/// Drop hidden directories that ended up without any shown entries below them.
+fn prune_hidden_dirs(node: &mut Node) {
+    for child in &mut node.children {
+        prune_hidden_dirs(child);
+    }
+    node.children
+        .retain(|child| !(child.is_dir && child.children.is_empty() && is_hidden(&child.path)));
+}
+
+/// Whether directories without any entries below them are left out, for
+/// filters that keep files but walk every directory to find them.
+pub fn prunes_empty_dirs(args: &ScanArgs) -> bool {
+    // Directories walked before the walk stopped may hold none of the matches
+    args.broken_links
+        || args.scan_secrets_names
+        || args.owned_by.is_some()
+        || (args.first.is_some() && !args.directories_only)
+}
+
+/// Drop directories that ended up without any entries below them.
+fn prune_empty_dirs(node: &mut Node) {
+    for child in &mut node.children {
+        prune_empty_dirs(child);
+    }
+    node.children
+        .retain(|child| !(child.is_dir && child.children.is_empty()));
+}
+
+/// Drop every file, keeping the directory sizes they contributed to.
+pub fn prune_files(node: &mut Node) {
+    node.children.retain(|child| child.is_dir);
+    for child in &mut node.children {
+        prune_files(child);
+    }
+}
+
+/// Name of the per-directory file listing children in their display order.
+const ORDER_FILE: &str = ".treee-order";
+
+/// Turns the flat, per-parent grouping of entries into a tree of nodes.
+struct TreeBuilder {
+    dir_contents: HashMap<PathBuf, Vec<(PathBuf, u64)>>,
+    /// Sort children by name (everything but --fast)
+    sorted: bool,
+    /// Honor `.treee-order` files
+    order_files: bool,
+    /// Sort directories before files
+    dirs_first: bool,
+    /// Sort by modification time, newest first
+    sort_mtime: bool,
+    /// Read modification times into the nodes
+    mtimes: bool,
+    newest_dir_mtime: bool,
+    /// Entries still shown but left out of their parents' sizes
+    du_exclude: Vec<Pattern>,
+    /// With --proc-mounts, the mounts left out of their parents' sizes
+    du_exclude_mounts: HashMap<PathBuf, String>,
+}
+
+impl TreeBuilder {
+    fn build(&mut self, path: PathBuf, size: u64) -> Node {
+        let is_dir = path.is_dir();
+        let mut children = Vec::new();
+
+        if is_dir && let Some(mut contents) = self.dir_contents.remove(&path) {
+            if self.sorted {
+                contents.sort();
+            }
+            children = contents
+                .into_iter()
+                .map(|(child, size)| self.build(child, size))
+                .collect();
+            // Each sort is stable, so the ones after take precedence
+            if self.sort_mtime {
+                children.sort_by_key(|child: &Node| std::cmp::Reverse(child.mtime));
+            }
+            if self.dirs_first {
+                children.sort_by_key(|child| !child.is_dir);
+            }
+            if self.order_files {
+                apply_order_file(&path, &mut children);
+            }
+        }
+
+        // Linked directories aren't descended into, so their dereferenced
+        // size is carried in `size` instead
+        let size = if is_dir {
+            size + children
+                .iter()
+                .filter(|child| !self.is_du_excluded(&child.path))
+                .map(|child| child.size)
+                .sum::<u64>()
+        } else {
+            size
+        };
+
+        // Links are described themselves, like `ls -l`; an empty directory
+        // falls back to its own time
+        let mtime = if !self.mtimes {
+            None
+        } else if self.newest_dir_mtime && !children.is_empty() {
+            children.iter().filter_map(|child| child.mtime).max()
+        } else {
+            time::of_path(&path)
+        };
+
+        Node {
+            link_target: std::fs::read_link(&path).ok(),
+            path,
+            is_dir,
+            size,
+            mtime,
+            children,
+        }
+    }
+
+    fn is_du_excluded(&self, path: &Path) -> bool {
+        let path_str = path.to_string_lossy();
+        let file_name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+        self.du_exclude
+            .iter()
+            .any(|pattern| pattern.matches(&path_str) || pattern.matches(&file_name))
+            || mounts::find(&self.du_exclude_mounts, path).is_some()
+    }
+}
+
+/// Move the children named in `dir/.treee-order` to the front, in the order
+/// listed there. Unlisted children keep their sorted order after them.
+fn apply_order_file(dir: &Path, children: &mut [Node]) {
+    let Ok(order) = std::fs::read_to_string(dir.join(ORDER_FILE)) else {
+        return;
+    };
+
+    let listed: Vec<&str> = order
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect();
+    debug!(dir = %dir.display(), entries = listed.len(), "applying {}", ORDER_FILE);
+
+    // Stable sort, so unlisted entries stay in their existing order
+    children.sort_by_key(|child| {
+        let name = child.path.file_name().map(|n| n.to_string_lossy());
+        name.and_then(|name| listed.iter().position(|entry| *entry == name))
+            .unwrap_or(usize::MAX)
+    });
+}
+
+/// Print non-fatal scan errors to stderr unless `quiet` is set.
+/// Returns whether there were any, so callers can reflect them in the exit status.
+pub fn report_errors(errors: &[ScanError], quiet: bool) -> bool {
+    if !quiet {
+        for err in errors {
+            eprintln!("treee: {}", err);
+        }
+    }
+    !errors.is_empty()
+}
+
+/// How `format_size` writes sizes.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SizeStyle {
+    /// Powers of 1024: `4.2K`, `13M`
+    Binary,
+    /// Powers of 1000: `4.3k`, `14M`
+    Si,
+    /// Exact byte counts
+    Bytes,
+}
+
+static SIZE_STYLE: OnceLock<SizeStyle> = OnceLock::new();
+
+/// Pick how every output writes sizes for the rest of the process. Later
+/// calls are ignored.
+pub fn set_size_style(style: SizeStyle) {
+    let _ = SIZE_STYLE.set(style);
+}
+
+/// The style picked with `set_size_style`, if any; sizes are binary otherwise,
+/// except in machine output that keeps exact counts unless asked.
+pub fn size_style() -> Option<SizeStyle> {
+    SIZE_STYLE.get().copied()
+}
+
+/// Format a byte count the way `tree -h` does: plain bytes below 1K, one
+/// decimal below 10 of a unit, whole numbers otherwise; `tree --si` has units
+/// of 1000 instead.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["K", "M", "G", "T", "P", "E"];
+    const SI_UNITS: [&str; 6] = ["k", "M", "G", "T", "P", "E"];
+
+    let (base, units) = match size_style().unwrap_or(SizeStyle::Binary) {
+        SizeStyle::Binary => (1024.0, UNITS),
+        SizeStyle::Si => (1000.0, SI_UNITS),
+        SizeStyle::Bytes => return bytes.to_string(),
+    };
+    if (bytes as f64) < base {
+        return bytes.to_string();
+    }
+
+    let mut value = bytes as f64 / base;
+    let mut unit = 0;
+    while value >= base && unit < units.len() - 1 {
+        value /= base;
+        unit += 1;
+    }
+
+    if value < 9.95 {
+        format!("{:.1}{}", value, units[unit])
+    } else {
+        format!("{:.0}{}", value, units[unit])
+    }
+}
+
+/// Parse a size like `512`, `1.5K`, `100M` or `2GiB` (binary units, as printed
+/// by `format_size`).
+pub fn parse_size(text: &str) -> Result<u64, String> {
+    const UNITS: [char; 6] = ['K', 'M', 'G', 'T', 'P', 'E'];
+
+    let text = text.trim();
+    let upper = text.to_ascii_uppercase();
+    let digits = upper.trim_end_matches("IB").trim_end_matches('B');
+    let (number, multiplier) = match digits.chars().last() {
+        Some(last) if last.is_ascii_alphabetic() => {
+            let Some(power) = UNITS.iter().position(|unit| *unit == last) else {
+                return Err(format!("unknown size unit in '{}'", text));
+            };
+            (&digits[..digits.len() - 1], 1024f64.powi(power as i32 + 1))
+        }
+        _ => (digits, 1.0),
+    };
+
+    match number.trim().parse::<f64>() {
+        Ok(value) if value >= 0.0 => Ok((value * multiplier) as u64),
+        _ => Err(format!("invalid size '{}'", text)),
+    }
+}
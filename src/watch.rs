@@ -0,0 +1,61 @@
+use anyhow::Result;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::cli::{ScanArgs, WatchArgs};
+use crate::tree;
+
+/// What we compare between polls to decide whether the tree needs redrawing.
+pub type Snapshot = Vec<(PathBuf, Option<SystemTime>, u64)>;
+
+/// Redraw the listing every time the set of entries, their sizes or their
+/// modification times change. Runs until interrupted.
+pub fn run(args: &WatchArgs, use_color: bool) -> Result<()> {
+    let interval = args.interval.max(Duration::from_millis(100));
+    let mut last: Option<Snapshot> = None;
+
+    loop {
+        let current = snapshot(&args.list.path, &args.list.scan)?;
+        if last.as_ref() != Some(&current) {
+            // Clear the screen and move the cursor home before redrawing
+            print!("\x1b[2J\x1b[H");
+            crate::run_list(&args.list, use_color)?;
+            io::stdout().flush()?;
+            last = Some(current);
+        }
+
+        thread::sleep(interval);
+    }
+}
+
+/// `0.5`, `2` or `30` seconds; anything but a positive number of them is an
+/// error.
+pub fn parse_interval(text: &str) -> Result<Duration, String> {
+    let seconds: f64 = text
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid interval '{}'", text))?;
+    if !seconds.is_finite() || seconds <= 0.0 {
+        return Err(format!("interval '{}' must be a positive number of seconds", text));
+    }
+    Duration::try_from_secs_f64(seconds).map_err(|_| format!("interval '{}' is too long", text))
+}
+
+/// The entries below `root` as `args` selects them, with their mtimes and sizes.
+pub fn snapshot(root: &Path, args: &ScanArgs) -> Result<Snapshot> {
+    let (entries, _) = tree::collect_entries(root, args)?;
+
+    Ok(entries
+        .into_iter()
+        .map(|entry| {
+            let metadata = entry.metadata().ok();
+            (
+                entry.into_path(),
+                metadata.as_ref().and_then(|m| m.modified().ok()),
+                metadata.map(|m| m.len()).unwrap_or(0),
+            )
+        })
+        .collect())
+}
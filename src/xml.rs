@@ -0,0 +1,74 @@
+use std::io::{self, BufWriter, Write};
+
+use crate::html::escape;
+use crate::tree::Node;
+
+/// Print the tree in the layout of GNU tree's `-X`, so tools that parse its
+/// output can read ours unchanged.
+pub fn print(root: &Node, with_size: bool) -> io::Result<()> {
+    let mut out = BufWriter::new(io::stdout().lock());
+    write(&mut out, root, with_size)?;
+    out.flush()
+}
+
+pub fn write(out: &mut impl Write, root: &Node, with_size: bool) -> io::Result<()> {
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(out, "<tree>")?;
+
+    // Like tree, the root is named as given on the command line
+    let name = root.path.to_string_lossy();
+    let mut counts = Counts::default();
+    write_node(out, root, &name, with_size, 1, &mut counts)?;
+
+    writeln!(out, "  <report>")?;
+    writeln!(out, "    <directories>{}</directories>", counts.directories)?;
+    writeln!(out, "    <files>{}</files>", counts.files)?;
+    writeln!(out, "  </report>")?;
+    writeln!(out, "</tree>")
+}
+
+#[derive(Default)]
+struct Counts {
+    directories: usize,
+    files: usize,
+}
+
+fn write_node(
+    out: &mut impl Write,
+    node: &Node,
+    name: &str,
+    with_size: bool,
+    depth: usize,
+    counts: &mut Counts,
+) -> io::Result<()> {
+    let indent = "  ".repeat(depth);
+    let element = match (&node.link_target, node.is_dir) {
+        (Some(_), _) => "link",
+        (None, true) => "directory",
+        (None, false) => "file",
+    };
+
+    write!(out, "{}<{} name=\"{}\"", indent, element, escape(name))?;
+    if let Some(target) = &node.link_target {
+        write!(out, " target=\"{}\"", escape(&target.to_string_lossy()))?;
+    }
+    if with_size {
+        write!(out, " size=\"{}\"", node.size)?;
+    }
+    write!(out, ">")?;
+
+    if !node.is_dir {
+        counts.files += 1;
+        return writeln!(out, "</{}>", element);
+    }
+
+    // The root itself is not counted, as in tree's report
+    if depth > 1 {
+        counts.directories += 1;
+    }
+    writeln!(out)?;
+    for child in &node.children {
+        write_node(out, child, &child.name(), with_size, depth + 1, counts)?;
+    }
+    writeln!(out, "{}</{}>", indent, element)
+}
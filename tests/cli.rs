@@ -0,0 +1,76 @@
+// The names these tests need (bytes outside UTF-8, `*`) can't be made on Windows
+#![cfg(unix)]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// A fresh, empty directory for one test, removed again when dropped.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("treee-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn treee(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_treee"))
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn errors_with_non_utf8_paths_serialize() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let dir = TempDir::new("non-utf8-errors");
+    // A link back to its own directory, followed with -l, is reported as a loop
+    std::os::unix::fs::symlink(".", dir.path().join(OsStr::from_bytes(b"bad\xff"))).unwrap();
+
+    let output = treee(dir.path(), &["-l", "-J"]);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let errors = json["errors"].as_array().unwrap();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0]["path"], "./bad\u{FFFD}");
+
+    let output = treee(dir.path(), &["-l", "--ndjson"]);
+    let lines: Vec<serde_json::Value> = output
+        .stdout
+        .split(|&b| b == b'\n')
+        .filter(|line| !line.is_empty())
+        .map(|line| serde_json::from_slice(line).unwrap())
+        .collect();
+    assert!(lines.iter().any(|line| line["type"] == "error" && line["path"] == "./bad\u{FFFD}"));
+}
+
+#[test]
+fn flat_combines_with_print0_and_shell_quote() {
+    let dir = TempDir::new("flat-quoting");
+    fs::create_dir(dir.path().join("a b")).unwrap();
+    fs::write(dir.path().join("a b").join("c*d"), "").unwrap();
+
+    let output = treee(dir.path(), &["--flat", "-f", "-0"]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"a b/c*d\0");
+
+    let output = treee(dir.path(), &["--flat", "-f", "--shell-quote"]);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "'a b/c*d'\n");
+}